@@ -30,12 +30,17 @@ use std::{
     sync::{Arc, LazyLock},
 };
 
+#[cfg(feature = "optimism")]
+pub mod optimism;
+
 const MAINNET_DEPOSIT_CONTRACT_ADDRESS: Address =
     address!("0x00000000219ab540356cbb839cbe05303d7705fa");
 const SEPOLIA_DEPOSIT_CONTRACT_ADDRESS: Address =
     address!("0x7f02c3e3c98b133055b8b348b2ac625669ed295d");
 const HOLESKY_DEPOSIT_CONTRACT_ADDRESS: Address =
     address!("0x4242424242424242424242424242424242424242");
+const HOODI_DEPOSIT_CONTRACT_ADDRESS: Address =
+    address!("0x00000000219ab540356cbb839cbe05303d7705fa");
 
 pub static MAINNET: LazyLock<Arc<ChainSpec>> = LazyLock::new(|| {
     let spec = ChainSpec {
@@ -70,6 +75,34 @@ pub static HOLESKY: LazyLock<Arc<ChainSpec>> = LazyLock::new(|| {
     spec.into()
 });
 
+pub static HOODI: LazyLock<Arc<ChainSpec>> = LazyLock::new(|| {
+    let spec = ChainSpec {
+        chain: NamedChain::Hoodi.into(),
+        forks: EthereumHardfork::hoodi().into(),
+        deposit_contract_address: Some(HOODI_DEPOSIT_CONTRACT_ADDRESS),
+        base_fee_params: BaseFeeParams::ethereum(),
+        blob_params: BlobScheduleBlobParams::mainnet(),
+    };
+    spec.into()
+});
+
+/// Returns the chain specification of the supported Ethereum chain with the given ID.
+pub fn by_chain_id(chain_id: u64) -> Option<Arc<ChainSpec>> {
+    match NamedChain::try_from(chain_id).ok()? {
+        NamedChain::Mainnet => Some(MAINNET.clone()),
+        NamedChain::Sepolia => Some(SEPOLIA.clone()),
+        NamedChain::Holesky => Some(HOLESKY.clone()),
+        NamedChain::Hoodi => Some(HOODI.clone()),
+        _ => None,
+    }
+}
+
+/// Minimal chain specification of an Ethereum chain.
+///
+/// It only contains the fork schedule, the fee and blob parameters and the deposit contract, which
+/// is all that is needed for the EVM configuration, the block assembly and the header checks of
+/// `EthBeaconConsensus`. The genesis, networking and pruning related methods are not supported and
+/// panic; neither the guest nor the host, the RPC proxy or the ExEx call them.
 #[derive(Clone, Debug)]
 pub struct ChainSpec {
     chain: Chain,
@@ -79,6 +112,13 @@ pub struct ChainSpec {
     blob_params: BlobScheduleBlobParams,
 }
 
+impl ChainSpec {
+    /// Creates a specification with the parameters of mainnet, but with the given hardforks.
+    pub fn mainnet_with_forks(forks: BTreeMap<EthereumHardfork, ForkCondition>) -> Self {
+        Self { forks, ..MAINNET.as_ref().clone() }
+    }
+}
+
 impl Display for ChainSpec {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.chain)
@@ -219,4 +259,9 @@ mod tests {
     fn holesky() {
         assert_eq(&HOLESKY, &reth_chainspec::HOLESKY);
     }
+
+    #[test]
+    fn hoodi() {
+        assert_eq(&HOODI, &reth_chainspec::HOODI);
+    }
 }
@@ -0,0 +1,241 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Chain specifications of OP-stack chains.
+
+use alloy_consensus::Header;
+use alloy_eips::{
+    eip1559::BaseFeeParams,
+    eip2124::{ForkFilter, ForkId, Head},
+    eip7840::BlobParams,
+};
+use alloy_genesis::Genesis;
+use alloy_hardforks::{EthereumHardfork, EthereumHardforks, ForkCondition, Hardfork};
+use alloy_primitives::{B256, U256, b256};
+use reth_chainspec::{Chain, ChainHardforks, DepositContract, EthChainSpec, Hardforks, NamedChain};
+use reth_optimism_forks::{
+    BASE_MAINNET_HARDFORKS, BASE_SEPOLIA_HARDFORKS, OP_MAINNET_HARDFORKS, OP_SEPOLIA_HARDFORKS,
+    OpHardfork, OpHardforks,
+};
+use std::{
+    fmt::{self, Debug, Display},
+    sync::{Arc, LazyLock},
+};
+
+pub static OP_MAINNET: LazyLock<Arc<OpChainSpec>> = LazyLock::new(|| {
+    let spec = OpChainSpec {
+        chain: NamedChain::Optimism.into(),
+        genesis_hash: b256!("7ca38a1916c42007829c55e69d3e9a73265554b586a499015373241b8a3fa48b"),
+        forks: OP_MAINNET_HARDFORKS.clone(),
+        base_fee_params: BaseFeeParams::optimism(),
+        canyon_base_fee_params: BaseFeeParams::optimism_canyon(),
+    };
+    spec.into()
+});
+
+pub static OP_SEPOLIA: LazyLock<Arc<OpChainSpec>> = LazyLock::new(|| {
+    let spec = OpChainSpec {
+        chain: NamedChain::OptimismSepolia.into(),
+        genesis_hash: b256!("102de6ffb001480cc9b8b548fd05c34cd4f46ae4aa91759393db90ea0409887d"),
+        forks: OP_SEPOLIA_HARDFORKS.clone(),
+        base_fee_params: BaseFeeParams::optimism_sepolia(),
+        canyon_base_fee_params: BaseFeeParams::optimism_sepolia_canyon(),
+    };
+    spec.into()
+});
+
+pub static BASE_MAINNET: LazyLock<Arc<OpChainSpec>> = LazyLock::new(|| {
+    let spec = OpChainSpec {
+        chain: NamedChain::Base.into(),
+        genesis_hash: b256!("f712aa9241cc24369b143cf6dce85f0902a9731e70d66818a3a5845b296c73dd"),
+        forks: BASE_MAINNET_HARDFORKS.clone(),
+        base_fee_params: BaseFeeParams::optimism(),
+        canyon_base_fee_params: BaseFeeParams::optimism_canyon(),
+    };
+    spec.into()
+});
+
+pub static BASE_SEPOLIA: LazyLock<Arc<OpChainSpec>> = LazyLock::new(|| {
+    let spec = OpChainSpec {
+        chain: NamedChain::BaseSepolia.into(),
+        genesis_hash: b256!("0dcc9e089e30b90ddfc55be9a37dd15bc551aeee999d2e2b51414c54eaf934e4"),
+        forks: BASE_SEPOLIA_HARDFORKS.clone(),
+        base_fee_params: BaseFeeParams::base_sepolia(),
+        canyon_base_fee_params: BaseFeeParams::base_sepolia_canyon(),
+    };
+    spec.into()
+});
+
+/// Minimal chain specification of an OP-stack chain.
+///
+/// In contrast to the chain spec of op-reth, this does not contain the genesis. Only its hash is
+/// kept, so the methods that require the full genesis or the fork IDs derived from it panic. They
+/// are only used for networking and syncing, which the stateless validation never does.
+#[derive(Clone, Debug)]
+pub struct OpChainSpec {
+    chain: Chain,
+    genesis_hash: B256,
+    forks: ChainHardforks,
+    base_fee_params: BaseFeeParams,
+    canyon_base_fee_params: BaseFeeParams,
+}
+
+impl Display for OpChainSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.chain)
+    }
+}
+
+impl EthereumHardforks for OpChainSpec {
+    fn ethereum_fork_activation(&self, fork: EthereumHardfork) -> ForkCondition {
+        self.forks.fork(fork)
+    }
+}
+
+impl OpHardforks for OpChainSpec {
+    fn op_fork_activation(&self, fork: OpHardfork) -> ForkCondition {
+        self.forks.fork(fork)
+    }
+}
+
+impl Hardforks for OpChainSpec {
+    fn fork<H: Hardfork>(&self, fork: H) -> ForkCondition {
+        self.forks.fork(fork)
+    }
+
+    fn forks_iter(&self) -> impl Iterator<Item = (&dyn Hardfork, ForkCondition)> {
+        self.forks.forks_iter()
+    }
+
+    fn fork_id(&self, _: &Head) -> ForkId {
+        unimplemented!()
+    }
+
+    fn latest_fork_id(&self) -> ForkId {
+        unimplemented!()
+    }
+
+    fn fork_filter(&self, _: Head) -> ForkFilter {
+        unimplemented!()
+    }
+}
+
+impl EthChainSpec for OpChainSpec {
+    type Header = Header;
+
+    fn chain(&self) -> Chain {
+        self.chain
+    }
+
+    fn base_fee_params_at_block(&self, _: u64) -> BaseFeeParams {
+        // Canyon is activated by timestamp, so it is never active for a plain block number
+        self.base_fee_params
+    }
+
+    fn base_fee_params_at_timestamp(&self, timestamp: u64) -> BaseFeeParams {
+        if self.is_canyon_active_at_timestamp(timestamp) {
+            self.canyon_base_fee_params
+        } else {
+            self.base_fee_params
+        }
+    }
+
+    fn blob_params_at_timestamp(&self, _: u64) -> Option<BlobParams> {
+        // OP-stack chains do not support blob transactions
+        None
+    }
+
+    fn deposit_contract(&self) -> Option<&DepositContract> {
+        None
+    }
+
+    fn genesis_hash(&self) -> B256 {
+        self.genesis_hash
+    }
+
+    fn prune_delete_limit(&self) -> usize {
+        // the limit op-reth uses for all OP-stack chains
+        10000
+    }
+
+    fn display_hardforks(&self) -> Box<dyn Display> {
+        unimplemented!()
+    }
+
+    fn genesis_header(&self) -> &Self::Header {
+        unimplemented!()
+    }
+
+    fn genesis(&self) -> &Genesis {
+        unimplemented!()
+    }
+
+    fn bootnodes(&self) -> Option<Vec<reth_network_peers::node_record::NodeRecord>> {
+        None
+    }
+
+    fn final_paris_total_difficulty(&self) -> Option<U256> {
+        if let ForkCondition::TTD { total_difficulty, .. } =
+            self.ethereum_fork_activation(EthereumHardfork::Paris)
+        {
+            Some(total_difficulty)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_eq(spec: &OpChainSpec, reth_spec: &reth_optimism_chainspec::OpChainSpec) {
+        assert_eq!(spec.chain, reth_spec.chain());
+        assert_eq!(spec.genesis_hash(), reth_spec.genesis_hash());
+        assert_eq!(spec.prune_delete_limit(), reth_spec.prune_delete_limit());
+        assert_eq!(
+            spec.forks_iter().map(|(f, c)| (f.name(), c)).collect::<Vec<_>>(),
+            reth_spec.forks_iter().map(|(f, c)| (f.name(), c)).collect::<Vec<_>>(),
+        );
+        // the base fee parameters can only change with timestamp-based forks
+        for (_, condition) in reth_spec.forks_iter() {
+            if let ForkCondition::Timestamp(timestamp) = condition {
+                assert_eq!(
+                    spec.base_fee_params_at_timestamp(timestamp),
+                    reth_spec.base_fee_params_at_timestamp(timestamp)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn op_mainnet() {
+        assert_eq(&OP_MAINNET, &reth_optimism_chainspec::OP_MAINNET);
+    }
+
+    #[test]
+    fn op_sepolia() {
+        assert_eq(&OP_SEPOLIA, &reth_optimism_chainspec::OP_SEPOLIA);
+    }
+
+    #[test]
+    fn base_mainnet() {
+        assert_eq(&BASE_MAINNET, &reth_optimism_chainspec::BASE_MAINNET);
+    }
+
+    #[test]
+    fn base_sepolia() {
+        assert_eq(&BASE_SEPOLIA, &reth_optimism_chainspec::BASE_SEPOLIA);
+    }
+}
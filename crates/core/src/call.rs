@@ -0,0 +1,273 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Execution of a call, like `eth_call`, against the state of a block.
+//!
+//! The state is given as MPT nodes of the state trie and the storage tries of the block, so that
+//! the result of the call is bound to the state root of its header.
+
+use crate::{EthEvmConfig, IndexedWitness, SparseState, db::WitnessDb};
+use alloy_consensus::Header;
+use alloy_primitives::{Address, B256, Bytes, TxKind, U256, keccak256};
+use alloy_rlp::Decodable;
+use reth_chainspec::{EthChainSpec, Hardforks};
+use reth_evm::{ConfigureEvm, Database, Evm, eth::spec::EthExecutorSpec};
+use reth_stateless::validation::StatelessValidationError;
+use revm::context::{TxEnv, result::ExecutionResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A call to a contract, the equivalent of the `eth_call` transaction object.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallRequest {
+    /// The sender of the call.
+    pub from: Address,
+    /// The called contract.
+    pub to: Address,
+    /// The value sent with the call.
+    pub value: U256,
+    /// The calldata.
+    pub data: Bytes,
+}
+
+/// The input of the call guest.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CallInput {
+    /// The chain ID selecting the chain specification.
+    pub chain_id: u64,
+    /// The RLP encoded header of the block whose state the call is executed against.
+    pub header: Bytes,
+    /// All MPT nodes of the state and storage tries accessed by the call.
+    pub state: Vec<Bytes>,
+    /// All bytecodes executed by the call.
+    pub codes: Vec<Bytes>,
+    /// The call to execute.
+    pub request: CallRequest,
+}
+
+/// The public output of the call guest.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CallJournal {
+    /// The chain ID used for the execution.
+    pub chain_id: u64,
+    /// The hash of the block whose state the call was executed against.
+    pub block_hash: B256,
+    /// The sender of the call.
+    pub from: Address,
+    /// The called contract.
+    pub to: Address,
+    /// The value sent with the call.
+    pub value: U256,
+    /// The Keccak hash of the calldata.
+    pub calldata_hash: B256,
+    /// The data returned by the call.
+    pub output: Bytes,
+}
+
+impl CallJournal {
+    /// Length of the fixed-size fields preceding the output.
+    const HEADER_LEN: usize = 8 + 32 + 20 + 20 + 32 + 32;
+
+    /// Encodes the journal as the concatenation of its fields, with the chain ID and the value in
+    /// big-endian.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::HEADER_LEN + self.output.len());
+        bytes.extend_from_slice(&self.chain_id.to_be_bytes());
+        bytes.extend_from_slice(self.block_hash.as_slice());
+        bytes.extend_from_slice(self.from.as_slice());
+        bytes.extend_from_slice(self.to.as_slice());
+        bytes.extend_from_slice(&self.value.to_be_bytes::<32>());
+        bytes.extend_from_slice(self.calldata_hash.as_slice());
+        bytes.extend_from_slice(&self.output);
+
+        bytes
+    }
+
+    /// Decodes a journal encoded with [CallJournal::encode].
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < Self::HEADER_LEN {
+            return None;
+        }
+        let (chain_id, rest) = bytes.split_at(8);
+        let (block_hash, rest) = rest.split_at(32);
+        let (from, rest) = rest.split_at(20);
+        let (to, rest) = rest.split_at(20);
+        let (value, rest) = rest.split_at(32);
+        let (calldata_hash, output) = rest.split_at(32);
+
+        Some(Self {
+            chain_id: u64::from_be_bytes(chain_id.try_into().unwrap()),
+            block_hash: B256::from_slice(block_hash),
+            from: Address::from_slice(from),
+            to: Address::from_slice(to),
+            value: U256::from_be_slice(value),
+            calldata_hash: B256::from_slice(calldata_hash),
+            output: Bytes::copy_from_slice(output),
+        })
+    }
+}
+
+/// Executes the call of the input against the state of its block.
+///
+/// The `BLOCKHASH` opcode is not supported and makes the call fail.
+pub fn execute_call<C>(
+    input: CallInput,
+    config: EthEvmConfig<C>,
+) -> Result<CallJournal, StatelessValidationError>
+where
+    C: EthExecutorSpec + EthChainSpec<Header = Header> + Hardforks + 'static,
+{
+    let header = Header::decode(&mut input.header.as_ref())
+        .map_err(|_| StatelessValidationError::HeaderDeserializationFailed)?;
+
+    let mut witness = IndexedWitness::default();
+    input.state.into_iter().for_each(|rlp| witness.insert_node(rlp));
+    witness.unhashed_codes = input.codes;
+
+    let state = SparseState::from_nodes(header.state_root, witness.state)?;
+    let block_hashes = BTreeMap::new();
+    let db = WitnessDb::new(&state, &witness.codes, &witness.unhashed_codes, &block_hashes);
+    let output = transact_call(&config, &header, db, &input.request)?;
+
+    Ok(CallJournal {
+        chain_id: input.chain_id,
+        block_hash: header.hash_slow(),
+        from: input.request.from,
+        to: input.request.to,
+        value: input.request.value,
+        calldata_hash: keccak256(&input.request.data),
+        output,
+    })
+}
+
+/// Executes the call on top of the given database and returns its output.
+///
+/// Like `eth_call`, the call has a gas price of zero, uses the gas limit of the block and does not
+/// check the nonce of the sender. Reverted or halted calls are returned as errors.
+pub fn transact_call<C, DB>(
+    config: &EthEvmConfig<C>,
+    header: &Header,
+    db: DB,
+    request: &CallRequest,
+) -> Result<Bytes, StatelessValidationError>
+where
+    C: EthExecutorSpec + EthChainSpec<Header = Header> + Hardforks + 'static,
+    DB: Database,
+{
+    let mut evm_env = config.evm_env(header);
+    evm_env.cfg_env.disable_nonce_check = true;
+    // a zero gas price is only valid without base fee
+    evm_env.block_env.basefee = 0;
+
+    let tx = TxEnv {
+        caller: request.from,
+        kind: TxKind::Call(request.to),
+        value: request.value,
+        data: request.data.clone(),
+        gas_limit: header.gas_limit,
+        gas_price: 0,
+        ..Default::default()
+    };
+
+    let mut evm = config.evm_with_env(db, evm_env);
+    let result = evm
+        .transact(tx)
+        .map_err(|err| StatelessValidationError::StatelessExecutionFailed(err.to_string()))?;
+    match result.result {
+        ExecutionResult::Success { output, .. } => Ok(output.into_data()),
+        ExecutionResult::Revert { output, .. } => Err(
+            StatelessValidationError::StatelessExecutionFailed(format!("call reverted: {output}")),
+        ),
+        ExecutionResult::Halt { reason, .. } => Err(
+            StatelessValidationError::StatelessExecutionFailed(format!("call halted: {reason:?}")),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{KECCAK256_EMPTY, bytes};
+    use alloy_trie::TrieAccount;
+    use reth_chainspec::ChainSpecBuilder;
+    use risc0_ethereum_trie::Trie;
+    use std::sync::Arc;
+
+    /// Returns storage slot 0 if called without calldata, otherwise reverts.
+    const READER_CODE: Bytes = bytes!("36600f5760005460005260206000f35b60006000fd");
+    const READER: Address = Address::repeat_byte(0xc0);
+    const SENDER: Address = Address::repeat_byte(0x01);
+
+    /// Returns the input of a call to the reader, with a state containing only the reader and
+    /// the funded sender.
+    fn reader_input(value: U256, data: Bytes) -> CallInput {
+        let mut storage_trie = Trie::default();
+        storage_trie.insert(keccak256(B256::ZERO), alloy_rlp::encode(U256::from(42)));
+
+        let mut state_trie = Trie::default();
+        let reader = TrieAccount {
+            nonce: 1,
+            storage_root: storage_trie.hash_slow(),
+            code_hash: keccak256(&READER_CODE),
+            ..Default::default()
+        };
+        state_trie.insert(keccak256(READER), alloy_rlp::encode(reader));
+        let sender = TrieAccount { balance: U256::from(100), ..Default::default() };
+        state_trie.insert(keccak256(SENDER), alloy_rlp::encode(sender));
+
+        let header = Header {
+            number: 1,
+            gas_limit: 30_000_000,
+            base_fee_per_gas: Some(1),
+            state_root: state_trie.hash_slow(),
+            ..Default::default()
+        };
+
+        CallInput {
+            chain_id: 1,
+            header: alloy_rlp::encode(&header).into(),
+            state: state_trie.rlp_nodes().into_iter().chain(storage_trie.rlp_nodes()).collect(),
+            codes: vec![READER_CODE],
+            request: CallRequest { from: SENDER, to: READER, value, data },
+        }
+    }
+
+    #[test]
+    fn execute_reader() {
+        let config =
+            EthEvmConfig::new(Arc::new(ChainSpecBuilder::mainnet().cancun_activated().build()));
+
+        let input = reader_input(U256::from(5), Bytes::new());
+        let header = Header::decode(&mut input.header.as_ref()).unwrap();
+        let journal = execute_call(input, config.clone()).unwrap();
+        assert_eq!(journal.block_hash, header.hash_slow());
+        assert_eq!((journal.from, journal.to, journal.value), (SENDER, READER, U256::from(5)));
+        assert_eq!(journal.calldata_hash, KECCAK256_EMPTY);
+        assert_eq!(journal.output, Bytes::from(U256::from(42).to_be_bytes::<32>()));
+
+        // the value is committed, as it can change the result of the call
+        let journal = execute_call(reader_input(U256::ZERO, Bytes::new()), config.clone()).unwrap();
+        assert_eq!(journal.value, U256::ZERO);
+
+        let err = execute_call(reader_input(U256::ZERO, bytes!("01")), config.clone()).unwrap_err();
+        assert!(
+            matches!(&err, StatelessValidationError::StatelessExecutionFailed(msg) if msg.contains("reverted")),
+            "unexpected error: {err}"
+        );
+
+        // the sender cannot send more than its balance
+        let input = reader_input(U256::from(101), Bytes::new());
+        assert!(execute_call(input, config).is_err());
+    }
+}
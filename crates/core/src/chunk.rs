@@ -0,0 +1,898 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Validation of a block split into chunks of transactions that can be proven independently.
+//!
+//! Each chunk receives a witness of the intermediate state it starts from, i.e. the state after all
+//! previous chunks, revealing only the accounts and storage slots accessed by the chunk itself. The
+//! input and the work of a chunk therefore only depend on its own transactions. The first chunk
+//! additionally applies the pre-execution changes of the block, the last chunk the post-execution
+//! changes, i.e. the withdrawals and the system calls for the requests.
+//!
+//! The resulting [ChunkJournal]s are checked by [aggregate_chunks] to form a contiguous chain from
+//! the state root of the parent to the state root of the block, covering all transactions. Every
+//! chunk must contain at least one transaction, unless the block has none.
+
+use crate::{
+    EthEvmConfig, ExecutionWitness, IndexedWitness, SparseState, db::WitnessDb, validation,
+    witness::node_references,
+};
+use alloy_consensus::{Header, Transaction, proofs::calculate_withdrawals_root};
+use alloy_eips::{eip4895::Withdrawals, eip6110::DEPOSIT_REQUEST_TYPE, eip7685::Requests};
+use alloy_primitives::{
+    Address, B256, Bytes, KECCAK256_EMPTY, U256, keccak256, logs_bloom,
+    map::{B256Map, B256Set},
+};
+use alloy_rlp::Decodable;
+use alloy_trie::{
+    EMPTY_ROOT_HASH, Nibbles, TrieAccount,
+    nodes::{RlpNode, TrieNode},
+};
+use reth_chainspec::{EthChainSpec, EthereumHardforks, Hardforks};
+use reth_consensus::{Consensus, ConsensusError, HeaderValidator};
+use reth_ethereum_consensus::EthBeaconConsensus;
+use reth_ethereum_primitives::{Block, Receipt, TransactionSigned};
+use reth_evm::{
+    ConfigureEvm,
+    eth::{EthBlockExecutionCtx, eip6110::parse_deposits_from_receipts, spec::EthExecutorSpec},
+    execute::BlockExecutor,
+};
+use reth_primitives_traits::{
+    Account, GotExpected, SealedBlock, SealedHeader, SignedTransaction,
+    receipt::gas_spent_by_transactions,
+};
+use reth_stateless::{StatelessTrie, validation::StatelessValidationError};
+use reth_trie_common::{HashedPostState, HashedStorage, KeccakKeyHasher};
+use revm::database::{BundleState, CacheState, State, states::bundle_state::BundleRetention};
+use risc0_ethereum_trie::Trie;
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    num::NonZeroUsize,
+    sync::Arc,
+};
+
+/// The account fields stored in the state trie, except for the storage root.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountInfo {
+    /// The nonce of the account.
+    pub nonce: u64,
+    /// The balance of the account.
+    pub balance: U256,
+    /// The hash of the bytecode of the account.
+    pub code_hash: B256,
+}
+
+/// The changes of a single account.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountDiff {
+    /// The new account fields, or `None` if the account was removed.
+    pub info: Option<AccountInfo>,
+    /// Whether the storage was cleared before applying the changed slots.
+    pub storage_wiped: bool,
+    /// The new values of all changed storage slots.
+    pub storage: BTreeMap<U256, U256>,
+}
+
+/// The state changes of one or more consecutive chunks.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateDiff(pub BTreeMap<Address, AccountDiff>);
+
+impl StateDiff {
+    /// Creates the diff from the changes of a [BundleState].
+    pub fn from_bundle_state(bundle: &BundleState) -> Self {
+        let accounts = bundle.state.iter().map(|(address, account)| {
+            let diff = AccountDiff {
+                info: account.info.as_ref().map(|info| AccountInfo {
+                    nonce: info.nonce,
+                    balance: info.balance,
+                    code_hash: info.code_hash,
+                }),
+                storage_wiped: account.was_destroyed(),
+                storage: account.storage.iter().map(|(k, v)| (*k, v.present_value)).collect(),
+            };
+            (*address, diff)
+        });
+
+        Self(accounts.collect())
+    }
+
+    /// Applies the changes of the next chunk on top of this diff.
+    pub fn extend(&mut self, other: StateDiff) {
+        for (address, diff) in other.0 {
+            let account = self.0.entry(address).or_default();
+            if diff.storage_wiped {
+                account.storage_wiped = true;
+                account.storage.clear();
+            }
+            account.storage.extend(diff.storage);
+            account.info = diff.info;
+        }
+    }
+
+    /// Returns the diff as a [HashedPostState] that can be applied to the state trie.
+    pub fn hashed_post_state(&self) -> HashedPostState {
+        let mut state = HashedPostState::default();
+        for (address, diff) in &self.0 {
+            let hashed_address = keccak256(address);
+            let account = diff.info.map(|info| Account {
+                nonce: info.nonce,
+                balance: info.balance,
+                bytecode_hash: (info.code_hash != KECCAK256_EMPTY).then_some(info.code_hash),
+            });
+            let storage = diff.storage.iter().map(|(k, v)| (keccak256(B256::from(*k)), *v));
+            state.accounts.insert(hashed_address, account);
+            state
+                .storages
+                .insert(hashed_address, HashedStorage::from_iter(diff.storage_wiped, storage));
+        }
+
+        state
+    }
+}
+
+/// The input of a single chunk.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChunkInput {
+    /// The header of the block.
+    pub header: Header,
+    /// The index of the first transaction of the chunk within the block.
+    pub start: u64,
+    /// The transactions of the chunk.
+    pub transactions: Vec<TransactionSigned>,
+    /// Whether this is the last chunk of the block.
+    pub last: bool,
+    /// The withdrawals of the block, only required for the last chunk.
+    pub withdrawals: Option<Withdrawals>,
+    /// The gas used by all previous chunks.
+    pub gas_used_before: u64,
+    /// The state root after all previous chunks.
+    pub pre_state_root: B256,
+}
+
+/// The public output of a chunk.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkJournal {
+    /// The chain ID used for the execution.
+    pub chain_id: u64,
+    /// The hash of the block.
+    pub block_hash: B256,
+    /// The index of the first transaction of the chunk.
+    pub start: u64,
+    /// The index after the last transaction of the chunk.
+    pub end: u64,
+    /// The hash of the RLP encoded list of the transactions of the chunk.
+    pub transactions_hash: B256,
+    /// The state root before the chunk.
+    pub pre_state_root: B256,
+    /// The state root after the chunk.
+    pub post_state_root: B256,
+    /// The cumulative gas used before the chunk.
+    pub gas_used_before: u64,
+    /// The cumulative gas used after the chunk.
+    pub gas_used_after: u64,
+    /// The root of the receipts of the chunk, with the cumulative gas of the block.
+    pub receipts_root: B256,
+    /// Whether this is the last chunk of the block.
+    pub last: bool,
+    /// The requests of the system calls of the last chunk, without the deposit requests.
+    pub requests: Vec<Bytes>,
+}
+
+/// The result of the execution of a chunk.
+#[derive(Debug, Clone)]
+pub struct ChunkExecution {
+    /// The public output of the chunk.
+    pub journal: ChunkJournal,
+    /// The receipts of the transactions of the chunk.
+    pub receipts: Vec<Receipt>,
+    /// The state changes of the chunk.
+    pub diff: StateDiff,
+    /// The bytecodes deployed by the chunk.
+    pub codes: Vec<Bytes>,
+}
+
+/// Executes a chunk of the transactions of a block on top of the state of the previous chunks.
+///
+/// The witness must reveal all the state accessed by the chunk at its pre-state root and contain
+/// the ancestor headers of the block, as created by [split_block].
+pub fn execute_chunk<C>(
+    input: ChunkInput,
+    witness: IndexedWitness,
+    config: EthEvmConfig<C>,
+) -> Result<ChunkExecution, StatelessValidationError>
+where
+    C: EthExecutorSpec + EthChainSpec<Header = Header> + Hardforks + 'static,
+{
+    execute(input, witness, config).map(|(execution, _)| execution)
+}
+
+/// Executes the chunk like [execute_chunk] and additionally returns the state it accessed.
+fn execute<C>(
+    input: ChunkInput,
+    witness: IndexedWitness,
+    config: EthEvmConfig<C>,
+) -> Result<(ChunkExecution, StateAccess), StatelessValidationError>
+where
+    C: EthExecutorSpec + EthChainSpec<Header = Header> + Hardforks + 'static,
+{
+    let ChunkInput {
+        header,
+        start,
+        transactions,
+        last,
+        withdrawals,
+        gas_used_before,
+        pre_state_root,
+    } = input;
+    let block_hash = header.hash_slow();
+    let (_, ancestor_hashes) = validation::decode_ancestors(&header, &witness.headers)?;
+    if last
+        && withdrawals.as_ref().map(|w| calculate_withdrawals_root(w)) != header.withdrawals_root
+    {
+        return Err(StatelessValidationError::StatelessExecutionFailed(
+            "withdrawals do not match the header".into(),
+        ));
+    }
+
+    let end = start + transactions.len() as u64;
+    let transactions_hash = transactions_hash(&transactions);
+    let transactions = transactions
+        .into_iter()
+        .map(SignedTransaction::try_into_recovered)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| StatelessValidationError::SignerRecovery)?;
+
+    let mut state = SparseState::from_nodes(pre_state_root, witness.state)?;
+
+    let mut db = State::builder()
+        .with_database(WitnessDb::new(
+            &state,
+            &witness.codes,
+            &witness.unhashed_codes,
+            &ancestor_hashes,
+        ))
+        .with_bundle_update()
+        .build();
+    let (mut receipts, requests, gas_used_after) = {
+        let ctx = EthBlockExecutionCtx {
+            parent_hash: header.parent_hash,
+            parent_beacon_block_root: header.parent_beacon_block_root,
+            ommers: &[],
+            withdrawals: withdrawals.as_ref().map(Cow::Borrowed),
+        };
+        let evm = config.evm_for_block(&mut db, &header);
+        let mut executor = config.create_executor(evm, ctx);
+        if start == 0 {
+            executor.apply_pre_execution_changes().map_err(execution_failed)?;
+        }
+
+        let mut gas_used = gas_used_before;
+        for tx in &transactions {
+            // the executor only knows the gas used by this chunk, so check the block limit here
+            let available = header.gas_limit.saturating_sub(gas_used);
+            if tx.gas_limit() > available {
+                return Err(StatelessValidationError::StatelessExecutionFailed(format!(
+                    "transaction gas limit {} is more than the available block gas {available}",
+                    tx.gas_limit()
+                )));
+            }
+            gas_used +=
+                executor.execute_transaction(tx.as_recovered_ref()).map_err(execution_failed)?;
+        }
+
+        if last {
+            let (_, result) = executor.finish().map_err(execution_failed)?;
+            let requests = result
+                .requests
+                .take()
+                .into_iter()
+                .filter(|request| request.first() != Some(&DEPOSIT_REQUEST_TYPE))
+                .collect();
+            (result.receipts, requests, gas_used)
+        } else {
+            (executor.receipts().to_vec(), Vec::new(), gas_used)
+        }
+    };
+    db.merge_transitions(BundleRetention::Reverts);
+    let bundle = db.take_bundle();
+    let access = StateAccess::from_cache(&db.cache);
+    drop(db);
+
+    // the receipts of the chunk only contain the gas used by the chunk itself
+    receipts.iter_mut().for_each(|receipt| receipt.cumulative_gas_used += gas_used_before);
+
+    let hashed_state = HashedPostState::from_bundle_state::<KeccakKeyHasher>(&bundle.state);
+    let post_state_root = state.calculate_state_root(hashed_state)?;
+
+    let journal = ChunkJournal {
+        chain_id: config.chain_spec().chain_id(),
+        block_hash,
+        start,
+        end,
+        transactions_hash,
+        pre_state_root,
+        post_state_root,
+        gas_used_before,
+        gas_used_after,
+        receipts_root: Receipt::calculate_receipt_root_no_memo(&receipts),
+        last,
+        requests,
+    };
+
+    let execution = ChunkExecution {
+        journal,
+        receipts,
+        diff: StateDiff::from_bundle_state(&bundle),
+        codes: bundle.contracts.values().map(|code| code.original_bytes()).collect(),
+    };
+
+    Ok((execution, access))
+}
+
+/// The input of the aggregation of the chunks of a block.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AggregationInput {
+    /// The block to validate.
+    pub block: Block,
+    /// The header of the parent block.
+    pub parent: Header,
+    /// The journals of all chunks, in order.
+    pub chunks: Vec<ChunkJournal>,
+    /// The receipts of all transactions of the block.
+    pub receipts: Vec<Receipt>,
+}
+
+/// The public output of the aggregation guest.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AggregationJournal {
+    /// The chain ID used for the validation.
+    pub chain_id: u64,
+    /// The image ID of the chunk guest whose proofs have been aggregated.
+    pub chunk_image_id: B256,
+    /// The hash of the validated block.
+    pub block_hash: B256,
+}
+
+impl AggregationJournal {
+    /// Length of the encoded journal.
+    const LEN: usize = 8 + 32 + 32;
+
+    /// Encodes the journal as the concatenation of its fields, with the chain ID in big-endian.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::LEN);
+        bytes.extend_from_slice(&self.chain_id.to_be_bytes());
+        bytes.extend_from_slice(self.chunk_image_id.as_slice());
+        bytes.extend_from_slice(self.block_hash.as_slice());
+
+        bytes
+    }
+
+    /// Returns whether the journal attests to the block with the given hash on the given chain,
+    /// aggregated from proofs of the chunk guest with the given image ID.
+    ///
+    /// The aggregation guest verifies the chunk proofs against the image ID of its input, so a
+    /// journal is only meaningful if this image ID is the expected one.
+    pub fn verify(&self, chain_id: u64, chunk_image_id: B256, block_hash: B256) -> bool {
+        self.chain_id == chain_id
+            && self.chunk_image_id == chunk_image_id
+            && self.block_hash == block_hash
+    }
+
+    /// Decodes a journal encoded with [AggregationJournal::encode].
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != Self::LEN {
+            return None;
+        }
+        let (chain_id, rest) = bytes.split_at(8);
+        let (chunk_image_id, block_hash) = rest.split_at(32);
+
+        Some(Self {
+            chain_id: u64::from_be_bytes(chain_id.try_into().unwrap()),
+            chunk_image_id: B256::from_slice(chunk_image_id),
+            block_hash: B256::from_slice(block_hash),
+        })
+    }
+}
+
+/// A block split into chunks, as created by [split_block].
+#[derive(Debug, Clone)]
+pub struct ChunkedBlock {
+    /// The input of every chunk together with the witness of its pre-state.
+    pub chunks: Vec<(ChunkInput, ExecutionWitness)>,
+    /// The input of the aggregation of the chunks.
+    pub aggregation: AggregationInput,
+}
+
+/// Splits the block into chunks of at most `chunk_size` transactions and executes them in order to
+/// create the inputs of all chunks and of their aggregation.
+///
+/// The witness must be the witness of the complete block.
+pub fn split_block<C>(
+    block: Block,
+    witness: ExecutionWitness,
+    config: EthEvmConfig<C>,
+    chunk_size: NonZeroUsize,
+) -> Result<ChunkedBlock, StatelessValidationError>
+where
+    C: EthExecutorSpec + EthChainSpec<Header = Header> + Hardforks + 'static,
+{
+    let (parent, _) = validation::decode_ancestors(&block.header, &witness.headers)?;
+
+    let mut ranges: Vec<_> = block.body.transactions.chunks(chunk_size.get()).collect();
+    if ranges.is_empty() {
+        ranges.push(&[]);
+    }
+
+    // all nodes of the pre-state and of every intermediate state are collected, so that the
+    // witness of each chunk can be extracted at its own pre-state root
+    let mut state = IntermediateState::new(parent.state_root, &witness)?;
+    let mut codes: B256Map<Bytes> =
+        witness.codes.iter().map(|code| (keccak256(code), code.clone())).collect();
+
+    let mut chunks = Vec::with_capacity(ranges.len());
+    let mut journals = Vec::with_capacity(ranges.len());
+    let mut receipts = Vec::with_capacity(block.body.transactions.len());
+    let mut start = 0;
+    let mut gas_used = 0;
+    for (i, transactions) in ranges.iter().enumerate() {
+        let last = i + 1 == ranges.len();
+        let input = ChunkInput {
+            header: block.header.clone(),
+            start,
+            transactions: transactions.to_vec(),
+            last,
+            withdrawals: block.body.withdrawals.clone().filter(|_| last),
+            gas_used_before: gas_used,
+            pre_state_root: state.root,
+        };
+        let full_witness = IndexedWitness {
+            headers: witness.headers.clone(),
+            state: state.nodes.clone(),
+            codes: codes.clone(),
+            unhashed_codes: Vec::new(),
+        };
+        let (execution, access) = execute(input.clone(), full_witness, config.clone())?;
+        let chunk_witness = state.witness(&access, &execution.diff, witness.headers.clone());
+        chunks.push((input, chunk_witness));
+
+        // later chunks may call contracts that have been deployed by this chunk
+        codes.extend(execution.codes.into_iter().map(|code| (keccak256(&code), code)));
+        if state.apply(&execution.diff)? != execution.journal.post_state_root {
+            return Err(StatelessValidationError::StatelessStateRootCalculationFailed);
+        }
+        start = execution.journal.end;
+        gas_used = execution.journal.gas_used_after;
+        receipts.extend(execution.receipts);
+        journals.push(execution.journal);
+    }
+
+    let aggregation =
+        AggregationInput { block, parent: parent.unseal(), chunks: journals, receipts };
+
+    Ok(ChunkedBlock { chunks, aggregation })
+}
+
+/// Validates the block against the journals of its chunks and returns its hash.
+///
+/// The journals themselves must have been verified to be the outputs of proven chunks.
+pub fn aggregate_chunks<C>(
+    input: AggregationInput,
+    chain_spec: Arc<C>,
+) -> Result<B256, StatelessValidationError>
+where
+    C: EthExecutorSpec + EthChainSpec<Header = Header>,
+{
+    let AggregationInput { block, parent, chunks, receipts } = input;
+    let block = SealedBlock::seal_slow(block);
+    if parent.hash_slow() != block.parent_hash {
+        return Err(StatelessValidationError::InvalidAncestorChain);
+    }
+    let parent = SealedHeader::seal_slow(parent);
+
+    let consensus = EthBeaconConsensus::new(chain_spec.clone());
+    consensus.validate_header(block.sealed_header())?;
+    consensus.validate_header_against_parent(block.sealed_header(), &parent)?;
+    consensus.validate_block_pre_execution(&block)?;
+
+    let transactions = &block.body().transactions;
+    if receipts.len() != transactions.len() {
+        return Err(invalid_chunks("number of receipts does not match the block"));
+    }
+
+    // only an empty block may be proven by a single empty chunk, as every chunk starting at the
+    // first transaction applies the pre-execution changes
+    let allow_empty = transactions.is_empty() && chunks.len() == 1;
+    // the chunks must form a contiguous chain over all transactions
+    let mut state_root = parent.state_root;
+    let mut gas_used = 0;
+    let mut start = 0;
+    for (i, chunk) in chunks.iter().enumerate() {
+        let range = start as usize..chunk.end as usize;
+        if chunk.chain_id != chain_spec.chain_id()
+            || chunk.block_hash != block.hash()
+            || chunk.start != start
+            || chunk.end < start
+            || (chunk.end == start && !allow_empty)
+            || range.end > transactions.len()
+            || chunk.pre_state_root != state_root
+            || chunk.gas_used_before != gas_used
+            || chunk.last != (i + 1 == chunks.len())
+        {
+            return Err(invalid_chunks(format!("chunk {i} does not follow its predecessor")));
+        }
+        if chunk.transactions_hash != transactions_hash(&transactions[range.clone()]) {
+            return Err(invalid_chunks(format!("chunk {i} has wrong transactions")));
+        }
+        if chunk.receipts_root != Receipt::calculate_receipt_root_no_memo(&receipts[range]) {
+            return Err(invalid_chunks(format!("chunk {i} has wrong receipts")));
+        }
+
+        state_root = chunk.post_state_root;
+        gas_used = chunk.gas_used_after;
+        start = chunk.end;
+    }
+    let last = chunks.last().ok_or_else(|| invalid_chunks("no chunks"))?;
+    if start as usize != transactions.len() {
+        return Err(invalid_chunks("chunks do not cover all transactions"));
+    }
+
+    // the checks of `validate_block_post_execution`, but with the receipts of all chunks
+    if block.gas_used != gas_used {
+        return Err(ConsensusError::BlockGasUsed {
+            gas: GotExpected { got: gas_used, expected: block.gas_used },
+            gas_spent_by_tx: gas_spent_by_transactions(&receipts),
+        }
+        .into());
+    }
+    let receipts_root = Receipt::calculate_receipt_root_no_memo(&receipts);
+    if receipts_root != block.receipts_root {
+        return Err(ConsensusError::BodyReceiptRootDiff(
+            GotExpected { got: receipts_root, expected: block.receipts_root }.into(),
+        )
+        .into());
+    }
+    let bloom = logs_bloom(receipts.iter().flat_map(|receipt| &receipt.logs));
+    if bloom != block.logs_bloom {
+        return Err(ConsensusError::BodyBloomLogDiff(
+            GotExpected { got: bloom, expected: block.logs_bloom }.into(),
+        )
+        .into());
+    }
+    if chain_spec.is_prague_active_at_timestamp(block.timestamp) {
+        // the last chunk only knows its own deposits, so they are parsed from all receipts here
+        let deposits = parse_deposits_from_receipts(chain_spec.as_ref(), &receipts)
+            .map_err(|err| StatelessValidationError::StatelessExecutionFailed(err.to_string()))?;
+        let mut requests = Requests::default();
+        if !deposits.is_empty() {
+            requests.push_request_with_type(DEPOSIT_REQUEST_TYPE, deposits);
+        }
+        last.requests.iter().for_each(|request| requests.push_request(request.clone()));
+
+        let requests_hash = requests.requests_hash();
+        let expected = block.requests_hash.ok_or(ConsensusError::RequestsHashMissing)?;
+        if requests_hash != expected {
+            return Err(ConsensusError::BodyRequestsHashDiff(
+                GotExpected { got: requests_hash, expected }.into(),
+            )
+            .into());
+        }
+    }
+
+    if state_root != block.state_root {
+        return Err(StatelessValidationError::PostStateRootMismatch {
+            got: state_root,
+            expected: block.state_root,
+        });
+    }
+
+    Ok(block.hash())
+}
+
+/// The accounts, storage slots and bytecodes accessed by an execution.
+#[derive(Debug, Default)]
+struct StateAccess {
+    /// The accessed storage slots by the address of each accessed account.
+    accounts: BTreeMap<Address, BTreeSet<U256>>,
+    /// The accessed bytecodes.
+    codes: Vec<Bytes>,
+}
+
+impl StateAccess {
+    /// Collects all accounts, slots and bytecodes that have been loaded into the cache.
+    fn from_cache(cache: &CacheState) -> Self {
+        let accounts = cache
+            .accounts
+            .iter()
+            .map(|(address, account)| {
+                let slots = account.account.iter().flat_map(|a| a.storage.keys().copied());
+                (*address, slots.collect())
+            })
+            .collect();
+        let codes = cache
+            .contracts
+            .iter()
+            .filter(|(code_hash, _)| **code_hash != KECCAK256_EMPTY)
+            .map(|(_, code)| code.original_bytes())
+            .collect();
+
+        Self { accounts, codes }
+    }
+}
+
+/// The state of a block between two of its chunks, used to create the witnesses of the chunks.
+struct IntermediateState {
+    /// The current state root.
+    root: B256,
+    /// The revealed part of the current state trie.
+    state_trie: Trie,
+    /// The revealed parts of the current storage tries of all modified accounts.
+    storage_tries: B256Map<Trie>,
+    /// All MPT nodes of the pre-state and of every intermediate state by their Keccak hash.
+    nodes: B256Map<Bytes>,
+}
+
+impl IntermediateState {
+    /// Creates the state with the given root from the witness of the complete block.
+    fn new(root: B256, witness: &ExecutionWitness) -> Result<Self, StatelessValidationError> {
+        let nodes: B256Map<Bytes> =
+            witness.state.iter().map(|rlp| (keccak256(rlp), rlp.clone())).collect();
+        let state_trie = reveal(root, &nodes)
+            .ok_or(StatelessValidationError::WitnessRevealFailed { pre_state_root: root })?;
+
+        Ok(Self { root, state_trie, storage_tries: B256Map::default(), nodes })
+    }
+
+    /// Applies the changes of a chunk and returns the new state root.
+    fn apply(&mut self, diff: &StateDiff) -> Result<B256, StatelessValidationError> {
+        let state = diff.hashed_post_state();
+        let mut removed_accounts = Vec::new();
+        for (hashed_address, account) in &state.accounts {
+            let Some(account) = account else {
+                removed_accounts.push(*hashed_address);
+                continue;
+            };
+
+            let storage = state.storages.get(hashed_address);
+            let wiped = storage.is_some_and(|storage| storage.wiped);
+            if wiped || !self.storage_tries.contains_key(hashed_address) {
+                let storage_root = match self.state_trie.get(hashed_address) {
+                    Some(rlp) if !wiped => {
+                        alloy_rlp::decode_exact::<TrieAccount>(rlp)
+                            .map_err(|_| {
+                                StatelessValidationError::StatelessStateRootCalculationFailed
+                            })?
+                            .storage_root
+                    }
+                    _ => EMPTY_ROOT_HASH,
+                };
+                let storage_trie = reveal(storage_root, &self.nodes)
+                    .ok_or(StatelessValidationError::StatelessStateRootCalculationFailed)?;
+                self.storage_tries.insert(*hashed_address, storage_trie);
+            }
+
+            let storage_trie = self.storage_tries.get_mut(hashed_address).unwrap();
+            if let Some(storage) = storage {
+                for (hashed_key, value) in &storage.storage {
+                    if !value.is_zero() {
+                        storage_trie.insert(hashed_key, alloy_rlp::encode(value));
+                    }
+                }
+                // removals must happen last, otherwise unresolved orphans might still exist
+                for (hashed_key, value) in &storage.storage {
+                    if value.is_zero() {
+                        storage_trie.remove(hashed_key);
+                    }
+                }
+            }
+            let storage_root = storage_trie.hash_slow();
+            insert_nodes(&mut self.nodes, storage_trie);
+
+            let account = TrieAccount {
+                nonce: account.nonce,
+                balance: account.balance,
+                storage_root,
+                code_hash: account.bytecode_hash.unwrap_or(KECCAK256_EMPTY),
+            };
+            self.state_trie.insert(hashed_address, alloy_rlp::encode(account));
+        }
+        for hashed_address in &removed_accounts {
+            self.state_trie.remove(hashed_address);
+            self.storage_tries.remove(hashed_address);
+        }
+
+        self.root = self.state_trie.hash_slow();
+        insert_nodes(&mut self.nodes, &self.state_trie);
+
+        Ok(self.root)
+    }
+
+    /// Returns the witness of the current state for a chunk with the given accesses and changes.
+    fn witness(
+        &self,
+        access: &StateAccess,
+        diff: &StateDiff,
+        headers: Vec<Bytes>,
+    ) -> ExecutionWitness {
+        let mut proof = B256Map::default();
+        for (address, slots) in &access.accounts {
+            let changes = diff.0.get(address);
+            let removed = changes.is_some_and(|changes| changes.info.is_none());
+            let account = prove(&self.nodes, self.root, keccak256(address), removed, &mut proof);
+
+            let Some(account) =
+                account.and_then(|rlp| alloy_rlp::decode_exact::<TrieAccount>(rlp).ok())
+            else {
+                continue;
+            };
+            // the storage trie is created whenever the account is accessed, so its root is needed
+            if let Some(rlp) = self.nodes.get(&account.storage_root) {
+                proof.insert(account.storage_root, rlp.clone());
+            }
+            for slot in slots {
+                let removed = changes
+                    .and_then(|changes| changes.storage.get(slot))
+                    .is_some_and(U256::is_zero);
+                let hashed_key = keccak256(B256::from(*slot));
+                prove(&self.nodes, account.storage_root, hashed_key, removed, &mut proof);
+            }
+        }
+
+        ExecutionWitness {
+            state: proof.into_values().collect(),
+            codes: access.codes.clone(),
+            keys: vec![],
+            headers,
+        }
+    }
+}
+
+/// Creates the trie with the given root from the nodes reachable from it.
+fn reveal(root: B256, nodes: &B256Map<Bytes>) -> Option<Trie> {
+    if root == EMPTY_ROOT_HASH {
+        return Some(Trie::default());
+    }
+
+    let mut trie_nodes = Vec::new();
+    let mut seen = B256Set::default();
+    let mut stack = vec![root];
+    while let Some(digest) = stack.pop() {
+        if !seen.insert(digest) {
+            continue;
+        }
+        let Some(rlp) = nodes.get(&digest) else { continue };
+        // the references of account leaves are not followed, as they belong to the storage tries
+        stack.extend(node_references(rlp, false).into_iter().map(|(digest, _)| digest));
+        trie_nodes.push(rlp);
+    }
+
+    let mut trie = Trie::from_digest(root);
+    trie.hydrate_from_rlp(trie_nodes).ok()?;
+
+    Some(trie)
+}
+
+/// Inserts all RLP encoded nodes of the trie by their Keccak hash.
+fn insert_nodes(nodes: &mut B256Map<Bytes>, trie: &Trie) {
+    for rlp in trie.rlp_nodes() {
+        nodes.insert(keccak256(&rlp), rlp);
+    }
+}
+
+/// Collects the nodes on the path of `key` in the trie with the given root into `proof` and
+/// returns the value of the key, if it exists.
+///
+/// With `siblings`, all children of the branch nodes on the path are collected as well, as they are
+/// required when removing the key collapses one of these branches.
+fn prove(
+    nodes: &B256Map<Bytes>,
+    root: B256,
+    key: B256,
+    siblings: bool,
+    proof: &mut B256Map<Bytes>,
+) -> Option<Vec<u8>> {
+    let path = Nibbles::unpack(key).to_vec();
+    let mut path = &path[..];
+    let mut rlp = resolve(nodes, &RlpNode::word_rlp(&root), proof)?;
+    loop {
+        let child = match TrieNode::decode(&mut &rlp[..]).ok()? {
+            TrieNode::Branch(branch) => {
+                let mut children = branch.stack.iter();
+                let mut next = None;
+                for nibble in 0..16 {
+                    if !branch.state_mask.is_bit_set(nibble) {
+                        continue;
+                    }
+                    let child = children.next()?;
+                    if siblings {
+                        resolve(nodes, child, proof);
+                    }
+                    if path.first() == Some(&nibble) {
+                        next = Some(child.clone());
+                    }
+                }
+                path = path.get(1..)?;
+                next?
+            }
+            TrieNode::Extension(extension) => {
+                path = path.strip_prefix(&extension.key.to_vec()[..])?;
+                extension.child
+            }
+            TrieNode::Leaf(leaf) => return (path == &leaf.key.to_vec()[..]).then_some(leaf.value),
+            TrieNode::EmptyRoot => return None,
+        };
+        rlp = resolve(nodes, &child, proof)?;
+    }
+}
+
+/// Returns the RLP encoding of the referenced node and adds it to `proof`, unless it is inlined.
+fn resolve(nodes: &B256Map<Bytes>, node: &RlpNode, proof: &mut B256Map<Bytes>) -> Option<Bytes> {
+    match node.as_hash() {
+        Some(digest) => {
+            let rlp = nodes.get(&digest)?;
+            proof.insert(digest, rlp.clone());
+            Some(rlp.clone())
+        }
+        None => Some(Bytes::copy_from_slice(node)),
+    }
+}
+
+/// Returns the hash of the RLP encoded list of transactions.
+pub fn transactions_hash(transactions: &[TransactionSigned]) -> B256 {
+    keccak256(alloy_rlp::encode(transactions))
+}
+
+fn execution_failed(err: impl ToString) -> StatelessValidationError {
+    StatelessValidationError::StatelessExecutionFailed(err.to_string())
+}
+
+fn invalid_chunks(reason: impl Into<String>) -> StatelessValidationError {
+    StatelessValidationError::StatelessExecutionFailed(format!("invalid chunks: {}", reason.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_extend() {
+        let address = Address::repeat_byte(0x01);
+        let info = AccountInfo { nonce: 1, balance: U256::from(2), code_hash: KECCAK256_EMPTY };
+        let mut diff = StateDiff::default();
+        diff.0.insert(
+            address,
+            AccountDiff {
+                info: Some(info),
+                storage_wiped: false,
+                storage: BTreeMap::from([(U256::from(1), U256::from(1))]),
+            },
+        );
+
+        // a later change overwrites the account, and a wipe removes all earlier slots
+        let next = AccountDiff {
+            info: Some(AccountInfo { nonce: 2, ..info }),
+            storage_wiped: true,
+            storage: BTreeMap::from([(U256::from(2), U256::from(2))]),
+        };
+        diff.extend(StateDiff(BTreeMap::from([(address, next.clone())])));
+        assert_eq!(diff.0[&address], next);
+
+        let state = diff.hashed_post_state();
+        let hashed_address = keccak256(address);
+        assert_eq!(state.accounts[&hashed_address].unwrap().nonce, 2);
+        assert_eq!(state.accounts[&hashed_address].unwrap().bytecode_hash, None);
+        assert!(state.storages[&hashed_address].wiped);
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::SparseState;
+use alloy_primitives::{Address, B256, Bytes, U256, keccak256, map::B256Map};
+use reth_errors::ProviderError;
+use reth_stateless::StatelessTrie;
+use revm::{Database, state::AccountInfo};
+use revm_bytecode::Bytecode;
+use std::{collections::BTreeMap, slice};
+
+/// Database for the EVM that serves all reads from the witness data.
+#[derive(Debug)]
+pub(crate) struct WitnessDb<'a> {
+    state: &'a SparseState,
+    codes: &'a B256Map<Bytes>,
+    /// Remaining codes without a claimed hash, they are only hashed on a miss in `codes`.
+    unhashed_codes: slice::Iter<'a, Bytes>,
+    /// Codes of `unhashed_codes` that have been hashed but not yet requested.
+    hashed_codes: B256Map<&'a Bytes>,
+    /// Verified and analyzed bytecodes by their hash.
+    bytecodes: B256Map<Bytecode>,
+    block_hashes: &'a BTreeMap<u64, B256>,
+}
+
+impl<'a> WitnessDb<'a> {
+    pub(crate) fn new(
+        state: &'a SparseState,
+        codes: &'a B256Map<Bytes>,
+        unhashed_codes: &'a [Bytes],
+        block_hashes: &'a BTreeMap<u64, B256>,
+    ) -> Self {
+        Self {
+            state,
+            codes,
+            unhashed_codes: unhashed_codes.iter(),
+            hashed_codes: B256Map::default(),
+            bytecodes: B256Map::default(),
+            block_hashes,
+        }
+    }
+
+    /// Returns the code with the given hash, verifying its claimed hash or hashing the unhashed
+    /// codes until it is found.
+    fn code(&mut self, code_hash: B256) -> Result<&'a Bytes, ProviderError> {
+        if let Some(code) = self.codes.get(&code_hash) {
+            // the claimed hash of the code is only verified here, when the code is actually used
+            if keccak256(code) != code_hash {
+                return Err(ProviderError::TrieWitnessError(format!(
+                    "bytecode for {code_hash} does not match its hash"
+                )));
+            }
+            return Ok(code);
+        }
+        if let Some(code) = self.hashed_codes.remove(&code_hash) {
+            return Ok(code);
+        }
+        for code in self.unhashed_codes.by_ref() {
+            let digest = keccak256(code);
+            if digest == code_hash {
+                return Ok(code);
+            }
+            self.hashed_codes.insert(digest, code);
+        }
+
+        Err(ProviderError::TrieWitnessError(format!("bytecode for {code_hash} not found")))
+    }
+}
+
+impl Database for WitnessDb<'_> {
+    type Error = ProviderError;
+
+    fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        let account = self.state.account(address)?;
+        Ok(account.map(|account| AccountInfo {
+            balance: account.balance,
+            nonce: account.nonce,
+            code_hash: account.code_hash,
+            code: None,
+        }))
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        if let Some(bytecode) = self.bytecodes.get(&code_hash) {
+            return Ok(bytecode.clone());
+        }
+        // each code is analyzed at most once, even if it is requested repeatedly
+        let bytecode = Bytecode::new_raw(self.code(code_hash)?.clone());
+        self.bytecodes.insert(code_hash, bytecode.clone());
+
+        Ok(bytecode)
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        self.state.storage(address, index)
+    }
+
+    fn block_hash(&mut self, number: u64) -> Result<B256, Self::Error> {
+        self.block_hashes.get(&number).copied().ok_or(ProviderError::StateForNumberNotFound(number))
+    }
+}
@@ -0,0 +1,182 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Validation of a chain of headers without executing the blocks.
+//!
+//! This proves that the first header is an ancestor of the last one, e.g. to connect a proven
+//! block to a trusted checkpoint.
+
+use alloy_consensus::Header;
+use alloy_primitives::{B256, Bytes};
+use alloy_rlp::Decodable;
+use reth_chainspec::EthChainSpec;
+use reth_consensus::HeaderValidator;
+use reth_ethereum_consensus::EthBeaconConsensus;
+use reth_evm::eth::spec::EthExecutorSpec;
+use reth_primitives_traits::SealedHeader;
+use reth_stateless::validation::StatelessValidationError;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// The input of the header chain guest.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HeaderChainInput {
+    /// The chain ID selecting the chain specification.
+    pub chain_id: u64,
+    /// The RLP encoded headers in ascending order.
+    pub headers: Vec<Bytes>,
+    /// Whether to also validate each header against the consensus rules of the chain.
+    pub validate: bool,
+}
+
+/// The public output of the header chain guest.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeaderChainJournal {
+    /// The chain ID of the headers.
+    pub chain_id: u64,
+    /// The hash of the first header.
+    pub first_hash: B256,
+    /// The hash of the last header.
+    pub last_hash: B256,
+    /// The number of headers in the chain.
+    pub count: u64,
+    /// Whether the headers have been validated against the consensus rules.
+    pub validated: bool,
+}
+
+impl HeaderChainJournal {
+    /// Length of the encoded journal.
+    const LEN: usize = 8 + 32 + 32 + 8 + 1;
+
+    /// Encodes the journal as the concatenation of its fields, with integers in big-endian.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::LEN);
+        bytes.extend_from_slice(&self.chain_id.to_be_bytes());
+        bytes.extend_from_slice(self.first_hash.as_slice());
+        bytes.extend_from_slice(self.last_hash.as_slice());
+        bytes.extend_from_slice(&self.count.to_be_bytes());
+        bytes.push(self.validated as u8);
+
+        bytes
+    }
+
+    /// Decodes a journal encoded with [HeaderChainJournal::encode].
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != Self::LEN {
+            return None;
+        }
+        let (chain_id, rest) = bytes.split_at(8);
+        let (first_hash, rest) = rest.split_at(32);
+        let (last_hash, rest) = rest.split_at(32);
+        let (count, validated) = rest.split_at(8);
+
+        Some(Self {
+            chain_id: u64::from_be_bytes(chain_id.try_into().unwrap()),
+            first_hash: B256::from_slice(first_hash),
+            last_hash: B256::from_slice(last_hash),
+            count: u64::from_be_bytes(count.try_into().unwrap()),
+            validated: match validated[0] {
+                0 => false,
+                1 => true,
+                _ => return None,
+            },
+        })
+    }
+}
+
+/// Verifies that the headers form a contiguous chain and, if requested, that each header is valid
+/// with respect to its parent, e.g. regarding the base fee and the gas limit bounds.
+pub fn validate_header_chain<C>(
+    input: HeaderChainInput,
+    chain_spec: Arc<C>,
+) -> Result<HeaderChainJournal, StatelessValidationError>
+where
+    C: EthExecutorSpec + EthChainSpec<Header = Header>,
+{
+    let chain_id = chain_spec.chain_id();
+    let consensus = EthBeaconConsensus::new(chain_spec);
+
+    let mut headers = input.headers.iter().map(|rlp| {
+        let header = Header::decode(&mut rlp.as_ref())
+            .map_err(|_| StatelessValidationError::HeaderDeserializationFailed)?;
+        Ok::<_, StatelessValidationError>(SealedHeader::seal_slow(header))
+    });
+
+    let first = headers.next().ok_or(StatelessValidationError::MissingAncestorHeader)??;
+    if input.validate {
+        consensus.validate_header(&first)?;
+    }
+
+    let first_hash = first.hash();
+    let mut parent = first;
+    for header in headers {
+        let header = header?;
+        if header.number != parent.number + 1 || header.parent_hash != parent.hash() {
+            return Err(StatelessValidationError::InvalidAncestorChain);
+        }
+        if input.validate {
+            consensus.validate_header(&header)?;
+            consensus.validate_header_against_parent(&header, &parent)?;
+        }
+        parent = header;
+    }
+
+    Ok(HeaderChainJournal {
+        chain_id,
+        first_hash,
+        last_hash: parent.hash(),
+        count: input.headers.len() as u64,
+        validated: input.validate,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_chainspec::MAINNET;
+
+    fn chain(len: u64) -> Vec<Bytes> {
+        let mut headers = vec![Header { number: 100, ..Default::default() }];
+        for _ in 1..len {
+            let parent = headers.last().unwrap();
+            headers.push(Header {
+                number: parent.number + 1,
+                parent_hash: parent.hash_slow(),
+                ..Default::default()
+            });
+        }
+        headers.iter().map(|header| alloy_rlp::encode(header).into()).collect()
+    }
+
+    #[test]
+    fn linked_headers() {
+        let headers = chain(3);
+        let input = HeaderChainInput { chain_id: 1, headers: headers.clone(), validate: false };
+        let journal = validate_header_chain(input, MAINNET.clone()).unwrap();
+        assert_eq!(journal.first_hash, alloy_primitives::keccak256(&headers[0]));
+        assert_eq!(journal.last_hash, alloy_primitives::keccak256(&headers[2]));
+        assert_eq!(journal.count, 3);
+    }
+
+    #[test]
+    fn broken_link() {
+        let mut headers = chain(3);
+        headers.remove(1);
+        let input = HeaderChainInput { chain_id: 1, headers, validate: false };
+        assert!(matches!(
+            validate_header_chain(input, MAINNET.clone()),
+            Err(StatelessValidationError::InvalidAncestorChain)
+        ));
+    }
+}
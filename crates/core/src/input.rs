@@ -0,0 +1,145 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Wire format of the guest input.
+//!
+//! The input always starts with the [InputFormat] followed by the block. With
+//! [InputFormat::Buffered], the complete [ExecutionWitness] follows as a single value. With
+//! [InputFormat::Streamed], the ancestor headers are followed by the number of codes and nodes, and
+//! then each code with its hash and each node as a separate value. This allows the guest to insert
+//! every node directly into the hashed node map, so that it never holds more than one copy of the
+//! witness in memory. With [InputFormat::Tree], the nodes are sent as a [TreeWitness] instead, so
+//! that the guest only hashes the nodes it reaches from the state root.
+
+use crate::{ExecutionWitness, IndexedWitness, TreeWitness};
+use alloy_consensus::Header;
+use alloy_primitives::{B256, Bytes, keccak256};
+use alloy_rlp::Decodable;
+use alloy_trie::EMPTY_ROOT_HASH;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+/// Format of the guest input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputFormat {
+    /// The complete [ExecutionWitness] as a single value.
+    Buffered,
+    /// The witness data as individual values.
+    #[default]
+    Streamed,
+    /// Like [InputFormat::Streamed], but with the nodes pre-linked as a [TreeWitness].
+    Tree,
+}
+
+impl InputFormat {
+    /// All the available formats.
+    pub const ALL: [InputFormat; 3] =
+        [InputFormat::Buffered, InputFormat::Streamed, InputFormat::Tree];
+}
+
+/// A source of serialized values, e.g. the standard input of the guest.
+pub trait InputReader {
+    /// Reads the next value.
+    fn read<T: DeserializeOwned>(&mut self) -> T;
+}
+
+/// A sink for serialized values, e.g. the environment of the executor.
+pub trait InputWriter {
+    type Error;
+
+    /// Writes the next value.
+    fn write<T: Serialize>(&mut self, value: &T) -> Result<(), Self::Error>;
+}
+
+/// Writes the block and its witness in the given format.
+///
+/// The block can be of any type, e.g. an Ethereum or an OP-stack block.
+pub fn write_input<W: InputWriter, B: Serialize>(
+    writer: &mut W,
+    block: &B,
+    witness: &ExecutionWitness,
+    format: InputFormat,
+) -> Result<(), W::Error> {
+    writer.write(&format)?;
+    writer.write(block)?;
+    match format {
+        InputFormat::Buffered => writer.write(witness)?,
+        InputFormat::Streamed => {
+            writer.write(&witness.headers)?;
+            writer.write(&(witness.codes.len() as u32, witness.state.len() as u32))?;
+            for code in &witness.codes {
+                writer.write(&(keccak256(code), code))?;
+            }
+            for rlp in &witness.state {
+                writer.write(rlp)?;
+            }
+        }
+        InputFormat::Tree => {
+            // the parent is the ancestor with the highest number
+            let pre_state_root = witness
+                .headers
+                .iter()
+                .filter_map(|rlp| Header::decode(&mut rlp.as_ref()).ok())
+                .max_by_key(|header| header.number)
+                .map_or(EMPTY_ROOT_HASH, |header| header.state_root);
+            let witness = TreeWitness::from_execution_witness(witness, pre_state_root);
+
+            writer.write(&witness.headers)?;
+            writer.write(&(witness.codes.len() as u32, witness.nodes.len() as u32))?;
+            for code in &witness.codes {
+                writer.write(&code)?;
+            }
+            for node in &witness.nodes {
+                writer.write(node)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the input written by [write_input] and returns the block and its witness.
+pub fn read_input<R: InputReader, B: DeserializeOwned>(reader: &mut R) -> (B, IndexedWitness) {
+    let format: InputFormat = reader.read();
+    let block: B = reader.read();
+    match format {
+        InputFormat::Buffered => {
+            let witness: ExecutionWitness = reader.read();
+            (block, witness.into())
+        }
+        InputFormat::Streamed => {
+            let mut witness = IndexedWitness::new(reader.read());
+            let (num_codes, num_nodes): (u32, u32) = reader.read();
+            for _ in 0..num_codes {
+                let (code_hash, code): (B256, Bytes) = reader.read();
+                witness.insert_claimed_code(code_hash, code);
+            }
+            for _ in 0..num_nodes {
+                witness.insert_node(reader.read::<Bytes>());
+            }
+
+            (block, witness)
+        }
+        InputFormat::Tree => {
+            let mut witness = TreeWitness { headers: reader.read(), ..Default::default() };
+            let (num_codes, num_nodes): (u32, u32) = reader.read();
+            for _ in 0..num_codes {
+                let (code_hash, code): (B256, Bytes) = reader.read();
+                witness.codes.insert(code_hash, code);
+            }
+            witness.nodes = (0..num_nodes).map(|_| reader.read()).collect();
+
+            (block, witness.into())
+        }
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Stateless validation of OP-stack blocks.
+//!
+//! This uses the same witness handling and [SparseState] as the validation of Ethereum blocks, but
+//! executes the block with the OP EVM and validates it against the OP consensus rules. Deposit
+//! transactions carry their sender and need no signature recovery. The L1 attributes, which are
+//! required for the L1 data fee, are read by the EVM from the `L1Block` predeploy, so its state is
+//! part of the witness like any other account.
+
+use crate::{ExecutionWitness, IndexedWitness, SparseState, db::WitnessDb, validation};
+use alloy_consensus::Header;
+use alloy_primitives::{Address, B256, address};
+use alloy_trie::EMPTY_ROOT_HASH;
+use reth_chainspec::EthChainSpec;
+use reth_consensus::{Consensus, ConsensusError, FullConsensus, HeaderValidator};
+use reth_evm::{ConfigureEvm, execute::Executor};
+use reth_optimism_consensus::OpBeaconConsensus;
+use reth_optimism_forks::OpHardforks;
+use reth_primitives_traits::{Block as _, GotExpected};
+use reth_stateless::{StatelessTrie, validation::StatelessValidationError};
+use reth_trie_common::{HashedPostState, KeccakKeyHasher};
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+
+pub use reth_optimism_evm::OpEvmConfig;
+pub use reth_optimism_primitives::{OpBlock, OpPrimitives};
+
+/// Address of the `L2ToL1MessagePasser` predeploy.
+///
+/// Since Isthmus, the `withdrawals_root` of the header is the storage root of this account.
+const L2_TO_L1_MESSAGE_PASSER_ADDRESS: Address =
+    address!("0x4200000000000000000000000000000000000016");
+
+/// The input for the stateless validation of an OP-stack block.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct OpStatelessInput {
+    /// The block to validate.
+    pub block: OpBlock,
+    /// The witness for the pre-state of the block.
+    pub witness: ExecutionWitness,
+}
+
+/// Performs stateless validation of an OP-stack block using the provided witness data.
+#[inline]
+pub fn validate_op_block<C>(
+    block: OpBlock,
+    witness: ExecutionWitness,
+    config: OpEvmConfig<C>,
+) -> Result<B256, StatelessValidationError>
+where
+    C: EthChainSpec<Header = Header> + OpHardforks + Debug + Send + Sync + 'static,
+    OpEvmConfig<C>: ConfigureEvm<Primitives = OpPrimitives>,
+{
+    validate_op_block_with_witness(block, witness.into(), config)
+}
+
+/// Performs stateless validation of an OP-stack block using the provided indexed witness.
+pub fn validate_op_block_with_witness<C>(
+    block: OpBlock,
+    witness: IndexedWitness,
+    config: OpEvmConfig<C>,
+) -> Result<B256, StatelessValidationError>
+where
+    C: EthChainSpec<Header = Header> + OpHardforks + Debug + Send + Sync + 'static,
+    OpEvmConfig<C>: ConfigureEvm<Primitives = OpPrimitives>,
+{
+    let chain_spec = config.chain_spec().clone();
+    assert!(
+        chain_spec.is_bedrock_active_at_block(block.number),
+        "only post-Bedrock blocks supported"
+    );
+
+    let block = block.try_into_recovered().map_err(|_| StatelessValidationError::SignerRecovery)?;
+    let (parent, ancestor_hashes) = validation::decode_ancestors(block.header(), &witness.headers)?;
+
+    let consensus = OpBeaconConsensus::new(chain_spec.clone());
+    consensus.validate_header(block.sealed_header())?;
+    consensus.validate_header_against_parent(block.sealed_header(), &parent)?;
+    consensus.validate_block_pre_execution(&block)?;
+
+    // execute the block reading the pre-state from the witness
+    let mut state = SparseState::from_nodes(parent.state_root, witness.state)?;
+    let db = WitnessDb::new(&state, &witness.codes, &witness.unhashed_codes, &ancestor_hashes);
+    let output = config
+        .executor(db)
+        .execute(&block)
+        .map_err(|err| StatelessValidationError::StatelessExecutionFailed(err.to_string()))?;
+
+    FullConsensus::<OpPrimitives>::validate_block_post_execution(
+        &consensus,
+        &block,
+        &output.result,
+    )
+    .map_err(StatelessValidationError::ConsensusValidationFailed)?;
+
+    // compute and check the post-state root
+    let hashed_state = HashedPostState::from_bundle_state::<KeccakKeyHasher>(&output.state.state);
+    let state_root = state.calculate_state_root(hashed_state)?;
+    if state_root != block.state_root {
+        return Err(StatelessValidationError::PostStateRootMismatch {
+            got: state_root,
+            expected: block.state_root,
+        });
+    }
+
+    if chain_spec.is_isthmus_active_at_timestamp(block.timestamp) {
+        // always use the root of the post-state, even if the message passer was not modified
+        let withdrawals_root = message_passer_storage_root(&state)?;
+        validate_withdrawals_root(block.header(), withdrawals_root)?;
+    }
+
+    Ok(block.hash())
+}
+
+/// Returns the storage root of the `L2ToL1MessagePasser` from the post-state.
+fn message_passer_storage_root(state: &SparseState) -> Result<B256, StatelessValidationError> {
+    let account = state.account(L2_TO_L1_MESSAGE_PASSER_ADDRESS).map_err(|err| {
+        StatelessValidationError::StatelessExecutionFailed(format!(
+            "failed to load L2ToL1MessagePasser: {err}"
+        ))
+    })?;
+
+    Ok(account.map_or(EMPTY_ROOT_HASH, |account| account.storage_root))
+}
+
+/// Verifies that the `withdrawals_root` of the header matches the given storage root.
+fn validate_withdrawals_root(header: &Header, root: B256) -> Result<(), StatelessValidationError> {
+    let expected = header.withdrawals_root.ok_or(ConsensusError::WithdrawalsRootMissing)?;
+    if root != expected {
+        return Err(StatelessValidationError::ConsensusValidationFailed(
+            ConsensusError::BodyWithdrawalsRootDiff(GotExpected { got: root, expected }.into()),
+        ));
+    }
+
+    Ok(())
+}
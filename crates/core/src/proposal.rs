@@ -0,0 +1,216 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Stateless execution of a proposed block that has not been sealed yet.
+//!
+//! In contrast to [validate_block](crate::validate_block), the header fields that result from the
+//! execution, i.e. the state root, receipts root, logs bloom, gas used and requests hash, are not
+//! checked but computed. Together with the fields derived from the parent and the attributes of
+//! the proposal, this yields the complete header of the block.
+
+use crate::{EthEvmConfig, IndexedWitness, SparseState, db::WitnessDb, validation};
+use alloy_consensus::{
+    BlockBody, EMPTY_OMMER_ROOT_HASH, Header, Transaction,
+    proofs::{calculate_transaction_root, calculate_withdrawals_root},
+};
+use alloy_eips::eip4895::Withdrawals;
+use alloy_primitives::{Address, B64, B256, Bytes, U256, logs_bloom};
+use alloy_rlp::{Decodable, Encodable};
+use reth_chainspec::{EthChainSpec, EthereumHardforks, Hardforks};
+use reth_consensus::{Consensus, ConsensusError, HeaderValidator};
+use reth_ethereum_consensus::EthBeaconConsensus;
+use reth_ethereum_primitives::{Block, EthPrimitives, Receipt, TransactionSigned};
+use reth_evm::{ConfigureEvm, eth::spec::EthExecutorSpec, execute::Executor};
+use reth_primitives_traits::{Block as _, SealedHeader};
+use reth_stateless::{StatelessTrie, validation::StatelessValidationError};
+use reth_trie_common::{HashedPostState, KeccakKeyHasher};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The attributes of a proposed block that are chosen by its builder.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProposalAttributes {
+    /// The timestamp of the block.
+    pub timestamp: u64,
+    /// The beneficiary of the priority fees.
+    pub suggested_fee_recipient: Address,
+    /// The randomness from the beacon chain, stored in the `mix_hash` of the header.
+    pub prev_randao: B256,
+    /// The gas limit of the block.
+    pub gas_limit: u64,
+    /// The extra data of the header.
+    pub extra_data: Bytes,
+    /// The root of the parent beacon block, required since Cancun.
+    pub parent_beacon_block_root: Option<B256>,
+    /// The withdrawals of the block, required since Shanghai.
+    pub withdrawals: Option<Withdrawals>,
+}
+
+/// A block proposal consisting of its parent, its attributes and an ordered list of transactions.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BlockProposal {
+    /// The header of the parent block.
+    pub parent: Header,
+    /// The attributes of the proposed block.
+    pub attributes: ProposalAttributes,
+    /// The transactions of the proposed block, in execution order.
+    pub transactions: Vec<TransactionSigned>,
+}
+
+/// The public output of the proposal guest.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProposalJournal {
+    /// The chain ID used for the execution.
+    pub chain_id: u64,
+    /// The complete header of the proposed block.
+    pub header: Header,
+}
+
+impl ProposalJournal {
+    /// Encodes the journal as the big-endian chain ID followed by the RLP encoded header.
+    ///
+    /// This way, the hash of the proposed block is the Keccak hash of the journal without its
+    /// first eight bytes.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 + self.header.length());
+        bytes.extend_from_slice(&self.chain_id.to_be_bytes());
+        self.header.encode(&mut bytes);
+
+        bytes
+    }
+
+    /// Decodes a journal encoded with [ProposalJournal::encode].
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let (chain_id, mut rlp) = bytes.split_first_chunk::<8>()?;
+        let header = Header::decode(&mut rlp).ok()?;
+        if !rlp.is_empty() {
+            return None;
+        }
+
+        Some(Self { chain_id: u64::from_be_bytes(*chain_id), header })
+    }
+}
+
+/// Executes the proposed block on top of its parent and returns the resulting header.
+///
+/// The witness must contain the pre-state, i.e. the state after the parent, in the same format as
+/// the witness of a sealed block. Since its state only depends on the parent and the
+/// transactions, it can be recorded by executing the proposal on the parent state of a node, e.g.
+/// with `zeth_exex::execution_witness`. Its headers are only needed for the `BLOCKHASH` opcode
+/// beyond the parent and, if present, must be contiguous and end with the parent.
+pub fn execute_proposal<C, F>(
+    proposal: BlockProposal,
+    witness: IndexedWitness,
+    config: EthEvmConfig<C, F>,
+) -> Result<Header, StatelessValidationError>
+where
+    C: EthExecutorSpec + EthChainSpec<Header = Header> + Hardforks + 'static,
+    EthEvmConfig<C, F>: ConfigureEvm<Primitives = EthPrimitives>,
+{
+    let chain_spec = config.chain_spec().clone();
+    let BlockProposal { parent, attributes, transactions } = proposal;
+    let parent = SealedHeader::seal_slow(parent);
+
+    #[cfg(not(feature = "unsafe-pre-merge"))]
+    if !chain_spec.is_paris_active_at_block(parent.number + 1) {
+        return Err(StatelessValidationError::StatelessExecutionFailed(
+            "only post-merge blocks supported".to_string(),
+        ));
+    }
+
+    let header = next_block_header(chain_spec.as_ref(), &parent, &attributes, &transactions)?;
+
+    // the headers of the witness, if any, are checked to be the ancestors of the proposed block
+    let mut ancestor_hashes = if witness.headers.is_empty() {
+        BTreeMap::new()
+    } else {
+        validation::decode_ancestors(&header, &witness.headers)?.1
+    };
+    ancestor_hashes.insert(parent.number, parent.hash());
+    let body = BlockBody { transactions, ommers: vec![], withdrawals: attributes.withdrawals };
+    let block = Block::new(header, body)
+        .try_into_recovered()
+        .map_err(|_| StatelessValidationError::SignerRecovery)?;
+
+    // the assembled block must be consistent with its header before it is executed
+    let consensus = EthBeaconConsensus::new(chain_spec.clone());
+    consensus.validate_block_pre_execution(&block)?;
+
+    // execute the block reading the pre-state from the witness
+    let mut state = SparseState::from_nodes(parent.state_root, witness.state)?;
+    let db = WitnessDb::new(&state, &witness.codes, &witness.unhashed_codes, &ancestor_hashes);
+    let output = config
+        .executor(db)
+        .execute(&block)
+        .map_err(|err| StatelessValidationError::StatelessExecutionFailed(err.to_string()))?;
+
+    // fill in the fields resulting from the execution
+    let mut header = block.into_block().header;
+    header.gas_used = output.gas_used;
+    header.receipts_root = Receipt::calculate_receipt_root_no_memo(&output.receipts);
+    header.logs_bloom = logs_bloom(output.receipts.iter().flat_map(|receipt| &receipt.logs));
+    if chain_spec.is_prague_active_at_timestamp(header.timestamp) {
+        header.requests_hash = Some(output.requests.requests_hash());
+    }
+    let hashed_state = HashedPostState::from_bundle_state::<KeccakKeyHasher>(&output.state.state);
+    header.state_root = state.calculate_state_root(hashed_state)?;
+
+    // the computed header must be valid on its own and as the child of the parent
+    let header = SealedHeader::seal_slow(header);
+    consensus.validate_header(&header)?;
+    consensus.validate_header_against_parent(&header, &parent)?;
+
+    Ok(header.unseal())
+}
+
+/// Builds the header of the next block, leaving the fields resulting from the execution empty.
+fn next_block_header<C>(
+    chain_spec: &C,
+    parent: &SealedHeader,
+    attributes: &ProposalAttributes,
+    transactions: &[TransactionSigned],
+) -> Result<Header, StatelessValidationError>
+where
+    C: EthExecutorSpec + EthChainSpec<Header = Header>,
+{
+    let timestamp = attributes.timestamp;
+    let base_fee_params = chain_spec.base_fee_params_at_timestamp(timestamp);
+    let base_fee =
+        parent.next_block_base_fee(base_fee_params).ok_or(ConsensusError::BaseFeeMissing)?;
+
+    let mut header = Header {
+        parent_hash: parent.hash(),
+        ommers_hash: EMPTY_OMMER_ROOT_HASH,
+        beneficiary: attributes.suggested_fee_recipient,
+        transactions_root: calculate_transaction_root(transactions),
+        difficulty: U256::ZERO,
+        number: parent.number + 1,
+        gas_limit: attributes.gas_limit,
+        timestamp,
+        extra_data: attributes.extra_data.clone(),
+        mix_hash: attributes.prev_randao,
+        nonce: B64::ZERO,
+        base_fee_per_gas: Some(base_fee),
+        withdrawals_root: attributes.withdrawals.as_ref().map(|w| calculate_withdrawals_root(w)),
+        ..Default::default()
+    };
+    if chain_spec.is_cancun_active_at_timestamp(timestamp) {
+        let blob_params = chain_spec.blob_params_at_timestamp(timestamp);
+        header.blob_gas_used = Some(transactions.iter().filter_map(|tx| tx.blob_gas_used()).sum());
+        header.excess_blob_gas = parent.maybe_next_block_excess_blob_gas(blob_params);
+        header.parent_beacon_block_root = attributes.parent_beacon_block_root;
+    }
+
+    Ok(header)
+}
@@ -0,0 +1,244 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy_primitives::{Address, B256, Bytes, KECCAK256_EMPTY, U256, keccak256, map::B256Map};
+use alloy_trie::{EMPTY_ROOT_HASH, TrieAccount};
+use reth_errors::ProviderError;
+use reth_stateless::{ExecutionWitness, StatelessTrie, validation::StatelessValidationError};
+use reth_trie_common::HashedPostState;
+use revm_bytecode::Bytecode;
+use risc0_ethereum_trie::CachedTrie;
+use std::{cell::RefCell, collections::hash_map::Entry, fmt::Debug, marker::PhantomData};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Zero-overhead helper for tries that only contain RLP encoded data.
+#[derive(Debug, Clone, Default)]
+#[repr(transparent)]
+struct RlpTrie<T> {
+    inner: CachedTrie,
+    phantom: PhantomData<T>,
+}
+
+impl<T: alloy_rlp::Decodable + alloy_rlp::Encodable> RlpTrie<T> {
+    fn new(inner: CachedTrie) -> Self {
+        Self { inner, phantom: PhantomData }
+    }
+
+    pub fn from_prehashed(
+        root: B256,
+        rlp_by_digest: &B256Map<impl AsRef<[u8]>>,
+    ) -> alloy_rlp::Result<Self> {
+        Ok(Self::new(CachedTrie::from_prehashed_nodes(root, rlp_by_digest)?))
+    }
+
+    pub fn get(&self, key: impl AsRef<[u8]>) -> alloy_rlp::Result<Option<T>> {
+        self.inner.get(key).map(alloy_rlp::decode_exact).transpose()
+    }
+
+    pub fn insert(&mut self, key: impl AsRef<[u8]>, value: T) {
+        self.inner.insert(key, alloy_rlp::encode(value));
+    }
+
+    pub fn remove(&mut self, key: impl AsRef<[u8]>) -> bool {
+        self.inner.remove(key)
+    }
+
+    pub fn hash(&mut self) -> B256 {
+        self.inner.hash()
+    }
+}
+
+/// Represents a sparse version of the Ethereum world state.
+/// This is significantly more performant than the Reth default.
+#[derive(Debug, Clone)]
+pub struct SparseState {
+    /// state MPT containing all used accounts
+    state: RlpTrie<TrieAccount>,
+    /// storage MPTs sorted by the hashed address of their account
+    storages: RefCell<B256Map<RlpTrie<U256>>>,
+
+    /// all relevant MPT nodes by their Keccak hash
+    rlp_by_digest: B256Map<Bytes>,
+}
+
+impl SparseState {
+    /// Creates the state with the given root from MPT nodes indexed by their Keccak hash.
+    pub fn from_nodes(
+        pre_state_root: B256,
+        rlp_by_digest: B256Map<Bytes>,
+    ) -> Result<Self, StatelessValidationError> {
+        // construct the state trie from the witness data and the given state root
+        let state = RlpTrie::from_prehashed(pre_state_root, &rlp_by_digest)
+            .map_err(|_| StatelessValidationError::WitnessRevealFailed { pre_state_root })?;
+
+        Ok(Self { state, storages: RefCell::new(B256Map::default()), rlp_by_digest })
+    }
+
+    /// Removes an account from the state.
+    fn remove_account(&mut self, hashed_address: &B256) {
+        self.state.remove(hashed_address);
+        self.storages.get_mut().remove(hashed_address);
+    }
+
+    /// Clears the storage of an account.
+    fn clear_storage(&mut self, hashed_address: B256) -> &mut RlpTrie<U256> {
+        self.storages.get_mut().entry(hashed_address).insert_entry(RlpTrie::default()).into_mut()
+    }
+
+    /// Returns a mutable version of the storage trie of the given account.
+    fn storage_trie_mut(&mut self, hashed_address: B256) -> alloy_rlp::Result<&mut RlpTrie<U256>> {
+        let trie = match self.storages.get_mut().entry(hashed_address) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                // build the storage trie matching the storage root of the account
+                let storage_root =
+                    self.state.get(hashed_address)?.map_or(EMPTY_ROOT_HASH, |a| a.storage_root);
+                entry.insert(RlpTrie::from_prehashed(storage_root, &self.rlp_by_digest)?)
+            }
+        };
+
+        Ok(trie)
+    }
+}
+
+impl StatelessTrie for SparseState {
+    /// Initialize the stateless trie using the `ExecutionWitness`.
+    ///
+    /// The trait requires all bytecodes to be hashed and analyzed upfront. The validation of zeth
+    /// uses [SparseState::from_nodes] together with an [IndexedWitness](crate::IndexedWitness)
+    /// instead, which only hashes and analyzes the codes the EVM actually requests.
+    fn new(
+        witness: &ExecutionWitness,
+        pre_state_root: B256,
+    ) -> Result<(Self, B256Map<Bytecode>), StatelessValidationError> {
+        // fist, hash all the RLP nodes once
+        let rlp_by_digest: B256Map<_> =
+            maybe_par_iter!(witness.state).map(|rlp| (keccak256(rlp), rlp.clone())).collect();
+        let state = Self::from_nodes(pre_state_root, rlp_by_digest)?;
+
+        // hash all the supplied bytecode
+        let bytecode = maybe_par_iter!(witness.codes)
+            .map(|code| (keccak256(code), Bytecode::new_raw(code.clone())))
+            .collect();
+
+        Ok((state, bytecode))
+    }
+
+    /// Returns the `TrieAccount` that corresponds to the `Address`.
+    fn account(&self, address: Address) -> Result<Option<TrieAccount>, ProviderError> {
+        let hashed_address = keccak256(address);
+        match self.state.get(hashed_address)? {
+            None => Ok(None),
+            Some(account) => {
+                // each time an account is accessed, check whether its storage trie already exists
+                // otherwise construct it from the witness data and the account's storage root
+                match self.storages.borrow_mut().entry(hashed_address) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(RlpTrie::from_prehashed(
+                            account.storage_root,
+                            &self.rlp_by_digest,
+                        )?);
+                    }
+                    Entry::Occupied(_) => {}
+                }
+
+                Ok(Some(account))
+            }
+        }
+    }
+
+    /// Returns the storage slot value that corresponds to the given (address, slot) tuple.
+    fn storage(&self, address: Address, slot: U256) -> Result<U256, ProviderError> {
+        let storages = self.storages.borrow();
+        // storage() is always be called after account(), so the storage trie must already exist
+        let storage_trie = storages.get(&keccak256(address)).ok_or_else(|| {
+            ProviderError::TrieWitnessError(format!("storage of {address} accessed before account"))
+        })?;
+        Ok(storage_trie.get(keccak256(B256::from(slot)))?.unwrap_or(U256::ZERO))
+    }
+
+    /// Computes the new state root from the HashedPostState.
+    fn calculate_state_root(
+        &mut self,
+        state: HashedPostState,
+    ) -> Result<B256, StatelessValidationError> {
+        // make sure that the storage tries of all updated accounts exist
+        for (hashed_address, _) in state.accounts.iter().filter(|(_, account)| account.is_some()) {
+            match state.storages.get(hashed_address) {
+                Some(storage) if storage.wiped => {
+                    self.clear_storage(*hashed_address);
+                }
+                _ => {
+                    self.storage_trie_mut(*hashed_address).map_err(|_| {
+                        StatelessValidationError::StatelessStateRootCalculationFailed
+                    })?;
+                }
+            }
+        }
+
+        // the storage tries are independent, so they can be updated and hashed in any order
+        let storage_tries: Vec<_> = self
+            .storages
+            .get_mut()
+            .iter_mut()
+            .filter(|(hashed_address, _)| {
+                state.accounts.get(*hashed_address).is_some_and(Option::is_some)
+            })
+            .collect();
+        let storage_roots: B256Map<B256> = maybe_par_iter!(storage_tries, into)
+            .map(|(hashed_address, storage_trie)| {
+                // apply storage changes before computing the storage root
+                if let Some(storage) = state.storages.get(hashed_address) {
+                    // apply all state modifications
+                    for (hashed_key, value) in &storage.storage {
+                        if !value.is_zero() {
+                            storage_trie.insert(hashed_key, *value);
+                        }
+                    }
+                    // removals must happen last, otherwise unresolved orphans might still exist
+                    for (hashed_key, value) in &storage.storage {
+                        if value.is_zero() {
+                            storage_trie.remove(hashed_key);
+                        }
+                    }
+                }
+
+                (*hashed_address, storage_trie.hash())
+            })
+            .collect();
+
+        let mut removed_accounts = Vec::new();
+        for (hashed_address, account) in state.accounts {
+            // nonexisting accounts must be removed from the state
+            let Some(account) = account else {
+                removed_accounts.push(hashed_address);
+                continue;
+            };
+
+            // update/insert the account after all changes have been processed
+            let account = TrieAccount {
+                nonce: account.nonce,
+                balance: account.balance,
+                storage_root: storage_roots[&hashed_address],
+                code_hash: account.bytecode_hash.unwrap_or(KECCAK256_EMPTY),
+            };
+            self.state.insert(hashed_address, account);
+        }
+        removed_accounts.iter().for_each(|hashed_address| self.remove_account(hashed_address));
+
+        Ok(self.state.hash())
+    }
+}
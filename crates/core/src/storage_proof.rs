@@ -0,0 +1,354 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verification of EIP-1186 account and storage proofs, as returned by `eth_getProof`, against the
+//! state root of a block header.
+
+use crate::SparseState;
+use alloy_consensus::Header;
+use alloy_primitives::{Address, B256, Bytes, KECCAK256_EMPTY, U256, keccak256, map::B256Map};
+use alloy_trie::EMPTY_ROOT_HASH;
+use reth_stateless::{StatelessTrie, validation::StatelessValidationError};
+use serde::{Deserialize, Serialize};
+
+/// The Merkle proof of an account and a set of its storage slots.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountProof {
+    /// The address of the account.
+    pub address: Address,
+    /// The RLP encoded nodes of the state trie from the root to the account.
+    pub proof: Vec<Bytes>,
+    /// The proofs of the requested storage slots.
+    pub storage: Vec<StorageProof>,
+}
+
+/// The Merkle proof of a single storage slot.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageProof {
+    /// The storage key.
+    pub key: B256,
+    /// The RLP encoded nodes of the storage trie from the root to the slot.
+    pub proof: Vec<Bytes>,
+}
+
+/// The input of the storage proof guest.
+///
+/// No chain ID is needed, as the block hash already identifies the chain.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StorageProofInput {
+    /// The RLP encoded header of the block.
+    pub header: Bytes,
+    /// The proofs of all queried accounts.
+    pub accounts: Vec<AccountProof>,
+}
+
+/// The proven state of an account and the values of the queried storage slots.
+///
+/// Accounts that do not exist are reported as empty accounts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProvenAccount {
+    /// The address of the account.
+    pub address: Address,
+    /// The nonce of the account.
+    pub nonce: u64,
+    /// The balance of the account.
+    pub balance: U256,
+    /// The hash of the code of the account.
+    pub code_hash: B256,
+    /// The root of the storage trie of the account.
+    pub storage_root: B256,
+    /// The queried storage keys and their values.
+    pub storage: Vec<(B256, U256)>,
+}
+
+/// The public output of the storage proof guest.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StorageProofJournal {
+    /// The hash of the block whose state has been queried.
+    pub block_hash: B256,
+    /// The proven accounts in the order of the input.
+    pub accounts: Vec<ProvenAccount>,
+}
+
+impl StorageProofJournal {
+    /// Encodes the journal as the block hash followed by the accounts, with integers in big-endian.
+    ///
+    /// Each account is encoded as its address, nonce, balance, code hash, storage root and the
+    /// number of slots, followed by the key and value of each slot.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(self.block_hash.as_slice());
+        for account in &self.accounts {
+            bytes.extend_from_slice(account.address.as_slice());
+            bytes.extend_from_slice(&account.nonce.to_be_bytes());
+            bytes.extend_from_slice(&account.balance.to_be_bytes::<32>());
+            bytes.extend_from_slice(account.code_hash.as_slice());
+            bytes.extend_from_slice(account.storage_root.as_slice());
+            bytes.extend_from_slice(&(account.storage.len() as u64).to_be_bytes());
+            for (key, value) in &account.storage {
+                bytes.extend_from_slice(key.as_slice());
+                bytes.extend_from_slice(&value.to_be_bytes::<32>());
+            }
+        }
+
+        bytes
+    }
+
+    /// Decodes a journal encoded with [StorageProofJournal::encode].
+    pub fn decode(mut bytes: &[u8]) -> Option<Self> {
+        let block_hash = B256::from(*take::<32>(&mut bytes)?);
+        let mut accounts = Vec::new();
+        while !bytes.is_empty() {
+            let address = Address::from(*take::<20>(&mut bytes)?);
+            let nonce = u64::from_be_bytes(*take::<8>(&mut bytes)?);
+            let balance = U256::from_be_bytes(*take::<32>(&mut bytes)?);
+            let code_hash = B256::from(*take::<32>(&mut bytes)?);
+            let storage_root = B256::from(*take::<32>(&mut bytes)?);
+            let len = u64::from_be_bytes(*take::<8>(&mut bytes)?);
+            let storage = (0..len)
+                .map(|_| {
+                    let key = B256::from(*take::<32>(&mut bytes)?);
+                    let value = U256::from_be_bytes(*take::<32>(&mut bytes)?);
+                    Some((key, value))
+                })
+                .collect::<Option<_>>()?;
+            accounts.push(ProvenAccount {
+                address,
+                nonce,
+                balance,
+                code_hash,
+                storage_root,
+                storage,
+            });
+        }
+
+        Some(Self { block_hash, accounts })
+    }
+}
+
+/// Splits off the first `N` bytes.
+fn take<'a, const N: usize>(bytes: &mut &'a [u8]) -> Option<&'a [u8; N]> {
+    let (head, tail) = bytes.split_first_chunk::<N>()?;
+    *bytes = tail;
+    Some(head)
+}
+
+/// Verifies the account and storage proofs against the state root of the header and returns the
+/// proven values together with the block hash.
+///
+/// The proofs of all accounts are combined into a single sparse state, so proofs sharing nodes
+/// are cheap. Incomplete proofs cannot be resolved and lead to a failure.
+pub fn verify_storage_proofs(
+    input: StorageProofInput,
+) -> Result<StorageProofJournal, StatelessValidationError> {
+    // the block hash is only valid if the input contains nothing but the header
+    let header: Header = alloy_rlp::decode_exact(&input.header)
+        .map_err(|_| StatelessValidationError::HeaderDeserializationFailed)?;
+    let block_hash = keccak256(&input.header);
+
+    let nodes: B256Map<Bytes> = input
+        .accounts
+        .iter()
+        .flat_map(|account| {
+            let storage = account.storage.iter().flat_map(|slot| &slot.proof);
+            account.proof.iter().chain(storage)
+        })
+        .map(|rlp| (keccak256(rlp), rlp.clone()))
+        .collect();
+    let state = SparseState::from_nodes(header.state_root, nodes)?;
+    let reveal_failed =
+        |_| StatelessValidationError::WitnessRevealFailed { pre_state_root: header.state_root };
+
+    let mut accounts = Vec::with_capacity(input.accounts.len());
+    for AccountProof { address, storage, .. } in input.accounts {
+        let account = state.account(address).map_err(reveal_failed)?;
+        let storage = storage
+            .into_iter()
+            .map(|StorageProof { key, .. }| {
+                // the storage of an account that does not exist is empty
+                let value = match account {
+                    Some(_) => state.storage(address, key.into()).map_err(reveal_failed)?,
+                    None => U256::ZERO,
+                };
+                Ok((key, value))
+            })
+            .collect::<Result<_, StatelessValidationError>>()?;
+
+        accounts.push(match account {
+            Some(account) => ProvenAccount {
+                address,
+                nonce: account.nonce,
+                balance: account.balance,
+                code_hash: account.code_hash,
+                storage_root: account.storage_root,
+                storage,
+            },
+            None => ProvenAccount {
+                address,
+                code_hash: KECCAK256_EMPTY,
+                storage_root: EMPTY_ROOT_HASH,
+                storage,
+                ..Default::default()
+            },
+        });
+    }
+
+    Ok(StorageProofJournal { block_hash, accounts })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_trie::{HashBuilder, Nibbles, TrieAccount, proof::ProofRetainer};
+    use std::collections::BTreeMap;
+
+    /// Returns the root of the trie with the given leaves and the minimal proof of the key, the
+    /// same nodes that `eth_getProof` returns.
+    fn prove(leaves: &BTreeMap<B256, Vec<u8>>, key: B256) -> (B256, Vec<Bytes>) {
+        let retainer = ProofRetainer::new(vec![Nibbles::unpack(key)]);
+        let mut hash_builder = HashBuilder::default().with_proof_retainer(retainer);
+        for (key, value) in leaves {
+            hash_builder.add_leaf(Nibbles::unpack(key), value);
+        }
+        let root = hash_builder.root();
+        let proof = hash_builder.take_proof_nodes().into_nodes_sorted();
+
+        (root, proof.into_iter().map(|(_, node)| node).collect())
+    }
+
+    #[test]
+    fn wrong_storage_value() {
+        let slot = B256::from(U256::from(1));
+        let storage =
+            |value: u64| BTreeMap::from([(keccak256(slot), alloy_rlp::encode(U256::from(value)))]);
+        let (_, storage_proof) = prove(&storage(10), keccak256(slot));
+
+        let address = Address::repeat_byte(0xc0);
+        let input = |storage_root| {
+            let account = TrieAccount { nonce: 1, storage_root, ..Default::default() };
+            let accounts = BTreeMap::from([(keccak256(address), alloy_rlp::encode(account))]);
+            let (state_root, proof) = prove(&accounts, keccak256(address));
+            let header = Header { state_root, ..Default::default() };
+            StorageProofInput {
+                header: alloy_rlp::encode(&header).into(),
+                accounts: vec![AccountProof {
+                    address,
+                    proof,
+                    storage: vec![StorageProof { key: slot, proof: storage_proof.clone() }],
+                }],
+            }
+        };
+
+        let journal = verify_storage_proofs(input(prove(&storage(10), B256::ZERO).0)).unwrap();
+        assert_eq!(journal.accounts[0].storage, vec![(slot, U256::from(10))]);
+
+        // the proof of the value 10 does not match a storage root committing to 11
+        assert!(verify_storage_proofs(input(prove(&storage(11), B256::ZERO).0)).is_err());
+    }
+
+    #[test]
+    fn empty_state() {
+        let header = Header { state_root: EMPTY_ROOT_HASH, ..Default::default() };
+        let address = Address::repeat_byte(1);
+        let input = StorageProofInput {
+            header: alloy_rlp::encode(&header).into(),
+            accounts: vec![AccountProof {
+                address,
+                proof: vec![],
+                storage: vec![StorageProof { key: B256::ZERO, proof: vec![] }],
+            }],
+        };
+        let journal = verify_storage_proofs(input).unwrap();
+        assert_eq!(journal.block_hash, header.hash_slow());
+        assert_eq!(journal.accounts[0].code_hash, KECCAK256_EMPTY);
+        assert_eq!(journal.accounts[0].storage, vec![(B256::ZERO, U256::ZERO)]);
+    }
+
+    #[test]
+    fn minimal_proofs() {
+        let slot = |key: u64| B256::from(U256::from(key));
+        let storage: BTreeMap<B256, Vec<u8>> = [(slot(1), 10u64), (slot(2), 20)]
+            .into_iter()
+            .map(|(key, value)| (keccak256(key), alloy_rlp::encode(U256::from(value))))
+            .collect();
+        let storage_root = prove(&storage, B256::ZERO).0;
+
+        // enough accounts so that the proofs consist of several nodes
+        let contract = Address::repeat_byte(0xc0);
+        let missing = Address::repeat_byte(0xee);
+        let mut accounts: BTreeMap<B256, Vec<u8>> = (0..32u8)
+            .map(|i| {
+                let account = TrieAccount { nonce: i.into(), ..Default::default() };
+                (keccak256(Address::repeat_byte(i)), alloy_rlp::encode(account))
+            })
+            .collect();
+        let account = TrieAccount {
+            nonce: 1,
+            balance: U256::from(2),
+            storage_root,
+            code_hash: B256::repeat_byte(3),
+        };
+        accounts.insert(keccak256(contract), alloy_rlp::encode(account));
+
+        let (state_root, contract_proof) = prove(&accounts, keccak256(contract));
+        let (_, missing_proof) = prove(&accounts, keccak256(missing));
+        let header = Header { state_root, ..Default::default() };
+        let input = StorageProofInput {
+            header: alloy_rlp::encode(&header).into(),
+            accounts: vec![
+                AccountProof {
+                    address: contract,
+                    proof: contract_proof.clone(),
+                    storage: [slot(1), slot(3)]
+                        .map(|key| StorageProof { key, proof: prove(&storage, keccak256(key)).1 })
+                        .to_vec(),
+                },
+                AccountProof {
+                    address: missing,
+                    proof: missing_proof,
+                    storage: vec![StorageProof { key: slot(1), proof: vec![] }],
+                },
+            ],
+        };
+
+        let journal = verify_storage_proofs(input.clone()).unwrap();
+        assert_eq!(journal.block_hash, header.hash_slow());
+        assert_eq!(
+            journal.accounts,
+            vec![
+                ProvenAccount {
+                    address: contract,
+                    nonce: 1,
+                    balance: U256::from(2),
+                    code_hash: B256::repeat_byte(3),
+                    storage_root,
+                    storage: vec![(slot(1), U256::from(10)), (slot(3), U256::ZERO)],
+                },
+                ProvenAccount {
+                    address: missing,
+                    code_hash: KECCAK256_EMPTY,
+                    storage_root: EMPTY_ROOT_HASH,
+                    storage: vec![(slot(1), U256::ZERO)],
+                    ..Default::default()
+                },
+            ]
+        );
+
+        // without its leaf, the account cannot be resolved
+        let mut incomplete = input;
+        incomplete.accounts.truncate(1);
+        incomplete.accounts[0].proof.pop();
+        assert!(verify_storage_proofs(incomplete).is_err());
+    }
+}
@@ -0,0 +1,131 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Stateless block validation using an [IndexedWitness].
+//!
+//! This follows `reth_stateless::stateless_validation` step by step, but operates on the already
+//! hashed witness data instead of an `ExecutionWitness`.
+
+use crate::{EthEvmConfig, IndexedWitness, SparseState, db::WitnessDb};
+use alloy_consensus::Header;
+use alloy_primitives::{B256, Bytes};
+use reth_chainspec::{EthChainSpec, Hardforks};
+use reth_consensus::{Consensus, HeaderValidator};
+use reth_ethereum_consensus::{EthBeaconConsensus, validate_block_post_execution};
+use reth_ethereum_primitives::{Block, EthPrimitives};
+use reth_evm::{ConfigureEvm, eth::spec::EthExecutorSpec, execute::Executor};
+use reth_primitives_traits::{Block as _, RecoveredBlock, SealedHeader};
+use reth_stateless::{StatelessTrie, validation::StatelessValidationError};
+use reth_trie_common::{HashedPostState, KeccakKeyHasher};
+use std::{collections::BTreeMap, sync::Arc};
+
+/// Performs stateless validation of a block using the provided indexed witness.
+///
+/// The block is executed with the EVM created by the factory of the configuration, so that chains
+/// with additional precompiles can reuse the complete pipeline.
+pub fn validate_block_with_witness<C, F>(
+    block: Block,
+    witness: IndexedWitness,
+    config: EthEvmConfig<C, F>,
+) -> Result<B256, StatelessValidationError>
+where
+    C: EthExecutorSpec + EthChainSpec<Header = Header> + Hardforks + 'static,
+    EthEvmConfig<C, F>: ConfigureEvm<Primitives = EthPrimitives>,
+{
+    let chain_spec = config.chain_spec().clone();
+
+    #[cfg(not(feature = "unsafe-pre-merge"))]
+    assert!(
+        reth_chainspec::EthereumHardforks::is_paris_active_at_block(&chain_spec, block.number),
+        "only post-merge blocks supported"
+    );
+
+    let block = block.try_into_recovered().map_err(|_| StatelessValidationError::SignerRecovery)?;
+
+    let (parent, ancestor_hashes) = decode_ancestors(block.header(), &witness.headers)?;
+
+    validate_block_consensus(chain_spec.clone(), &block, &parent)?;
+
+    // execute the block reading the pre-state from the witness
+    let mut state = SparseState::from_nodes(parent.state_root, witness.state)?;
+    let db = WitnessDb::new(&state, &witness.codes, &witness.unhashed_codes, &ancestor_hashes);
+    let output = config
+        .executor(db)
+        .execute(&block)
+        .map_err(|err| StatelessValidationError::StatelessExecutionFailed(err.to_string()))?;
+
+    validate_block_post_execution(&block, chain_spec.as_ref(), &output.receipts, &output.requests)
+        .map_err(StatelessValidationError::ConsensusValidationFailed)?;
+
+    // compute and check the post-state root
+    let hashed_state = HashedPostState::from_bundle_state::<KeccakKeyHasher>(&output.state.state);
+    let state_root = state.calculate_state_root(hashed_state)?;
+    if state_root != block.state_root {
+        return Err(StatelessValidationError::PostStateRootMismatch {
+            got: state_root,
+            expected: block.state_root,
+        });
+    }
+
+    Ok(block.hash())
+}
+
+/// Validates the block against the consensus rules that do not require execution.
+fn validate_block_consensus<C>(
+    chain_spec: Arc<C>,
+    block: &RecoveredBlock<Block>,
+    parent: &SealedHeader,
+) -> Result<(), StatelessValidationError>
+where
+    C: EthExecutorSpec + EthChainSpec<Header = Header>,
+{
+    let consensus = EthBeaconConsensus::new(chain_spec);
+    consensus.validate_header(block.sealed_header())?;
+    consensus.validate_header_against_parent(block.sealed_header(), parent)?;
+    consensus.validate_block_pre_execution(block)?;
+
+    Ok(())
+}
+
+/// Decodes the RLP encoded ancestor headers and verifies that they form a contiguous chain ending
+/// in the parent of the block with the given header.
+///
+/// Returns the sealed parent header and the hashes of all ancestors by block number.
+pub(crate) fn decode_ancestors(
+    header: &Header,
+    headers: &[Bytes],
+) -> Result<(SealedHeader, BTreeMap<u64, B256>), StatelessValidationError> {
+    let mut ancestor_headers = headers
+        .iter()
+        .map(alloy_rlp::decode_exact::<Header>)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| StatelessValidationError::HeaderDeserializationFailed)?;
+    ancestor_headers.sort_by_key(|header| header.number);
+
+    let mut ancestor_hashes = BTreeMap::new();
+    let mut child = header;
+    for parent in ancestor_headers.iter().rev() {
+        let parent_hash = child.parent_hash;
+        if parent.number + 1 != child.number || parent.hash_slow() != parent_hash {
+            return Err(StatelessValidationError::InvalidAncestorChain);
+        }
+        ancestor_hashes.insert(parent.number, parent_hash);
+        child = parent;
+    }
+
+    let parent = ancestor_headers.pop().ok_or(StatelessValidationError::MissingAncestorHeader)?;
+    let parent = SealedHeader::new(parent, header.parent_hash);
+
+    Ok((parent, ancestor_hashes))
+}
@@ -0,0 +1,257 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy_primitives::{B256, Bytes, keccak256, map::B256Map};
+use alloy_rlp::Decodable;
+use alloy_trie::{EMPTY_ROOT_HASH, TrieAccount, nodes::TrieNode};
+use reth_stateless::ExecutionWitness;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Execution witness with all MPT nodes and bytecodes indexed by their Keccak hash.
+///
+/// In contrast to [ExecutionWitness], this can be built incrementally without keeping a second
+/// copy of the nodes in memory.
+///
+/// The bytecodes are indexed by a claimed hash, which is only verified when the EVM requests the
+/// code. Codes without a claimed hash are kept unhashed and only hashed when a requested code is
+/// not found otherwise. This way, codes that are never executed are neither analyzed nor hashed
+/// more than necessary, while extra or wrong codes in the witness cannot affect the validation
+/// result.
+#[derive(Debug, Clone, Default)]
+pub struct IndexedWitness {
+    /// RLP encoded ancestor headers, at least the parent of the validated block.
+    pub headers: Vec<Bytes>,
+    /// All relevant MPT nodes by their Keccak hash.
+    pub state: B256Map<Bytes>,
+    /// All relevant bytecodes by their claimed Keccak hash.
+    pub codes: B256Map<Bytes>,
+    /// Additional bytecodes without a claimed hash.
+    pub unhashed_codes: Vec<Bytes>,
+}
+
+impl IndexedWitness {
+    /// Creates an empty witness with the given ancestor headers.
+    pub fn new(headers: Vec<Bytes>) -> Self {
+        Self { headers, ..Default::default() }
+    }
+
+    /// Hashes and inserts an RLP encoded MPT node.
+    #[inline]
+    pub fn insert_node(&mut self, rlp: Bytes) {
+        self.state.insert(keccak256(&rlp), rlp);
+    }
+
+    /// Hashes and inserts a bytecode.
+    #[inline]
+    pub fn insert_code(&mut self, code: Bytes) {
+        self.codes.insert(keccak256(&code), code);
+    }
+
+    /// Inserts a bytecode with the claimed hash, without hashing it.
+    #[inline]
+    pub fn insert_claimed_code(&mut self, code_hash: B256, code: Bytes) {
+        self.codes.insert(code_hash, code);
+    }
+}
+
+impl From<ExecutionWitness> for IndexedWitness {
+    fn from(witness: ExecutionWitness) -> Self {
+        let state =
+            maybe_par_iter!(witness.state, into).map(|rlp| (keccak256(&rlp), rlp)).collect();
+
+        Self {
+            headers: witness.headers,
+            state,
+            codes: B256Map::default(),
+            unhashed_codes: witness.codes,
+        }
+    }
+}
+
+/// A node of a [TreeWitness] together with the indices of the nodes it references.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreeNode {
+    /// The RLP encoded MPT node.
+    pub rlp: Bytes,
+    /// Indices of the child nodes, or of the storage root node for accounts in the state trie.
+    pub children: Vec<u32>,
+}
+
+/// Execution witness with the MPT nodes pre-linked in tree form.
+///
+/// The first node is the root of the state trie. When converted into an [IndexedWitness], only
+/// the nodes reachable from it are hashed, each at most once. Every link is verified against the
+/// references of the parent node and all nodes are indexed by their actual hash, so wrong links
+/// can make the validation fail, but never succeed for an invalid block.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TreeWitness {
+    /// RLP encoded ancestor headers, at least the parent of the validated block.
+    pub headers: Vec<Bytes>,
+    /// All relevant MPT nodes, starting with the root of the state trie.
+    pub nodes: Vec<TreeNode>,
+    /// All relevant bytecodes by their claimed Keccak hash.
+    pub codes: B256Map<Bytes>,
+}
+
+impl TreeWitness {
+    /// Converts the witness into tree form, starting at the given pre-state root.
+    ///
+    /// Nodes that are not reachable from the pre-state root are dropped.
+    pub fn from_execution_witness(witness: &ExecutionWitness, pre_state_root: B256) -> Self {
+        let index_by_digest: B256Map<usize> =
+            witness.state.iter().enumerate().map(|(i, rlp)| (keccak256(rlp), i)).collect();
+
+        // walk the state trie and all the storage tries and collect the references of each node
+        let mut new_index = vec![None; witness.state.len()];
+        let mut order = Vec::new();
+        let mut references = vec![Vec::new(); witness.state.len()];
+        let mut stack: Vec<_> =
+            index_by_digest.get(&pre_state_root).map(|&index| (index, true)).into_iter().collect();
+        while let Some((index, is_state_trie)) = stack.pop() {
+            if new_index[index].is_some() {
+                continue;
+            }
+            new_index[index] = Some(order.len() as u32);
+            order.push(index);
+
+            for (digest, is_state_trie) in node_references(&witness.state[index], is_state_trie) {
+                if let Some(&child) = index_by_digest.get(&digest) {
+                    references[index].push(child);
+                    stack.push((child, is_state_trie));
+                }
+            }
+        }
+
+        let nodes = order
+            .into_iter()
+            .map(|index| TreeNode {
+                rlp: witness.state[index].clone(),
+                children: references[index].iter().filter_map(|&child| new_index[child]).collect(),
+            })
+            .collect();
+        let codes = witness.codes.iter().map(|code| (keccak256(code), code.clone())).collect();
+
+        Self { headers: witness.headers.clone(), nodes, codes }
+    }
+}
+
+/// Returns the digests referenced by the RLP encoded node and whether they belong to the state
+/// trie. Account leaves of the state trie reference the root of their storage trie.
+pub(crate) fn node_references(rlp: &[u8], is_state_trie: bool) -> Vec<(B256, bool)> {
+    let Ok(node) = TrieNode::decode(&mut &rlp[..]) else {
+        return vec![];
+    };
+    match node {
+        TrieNode::Branch(branch) => branch
+            .stack
+            .iter()
+            .filter_map(|child| child.as_hash().map(|digest| (digest, is_state_trie)))
+            .collect(),
+        TrieNode::Extension(extension) => {
+            extension.child.as_hash().map(|digest| (digest, is_state_trie)).into_iter().collect()
+        }
+        TrieNode::Leaf(leaf) if is_state_trie => TrieAccount::decode(&mut &leaf.value[..])
+            .ok()
+            .filter(|account| account.storage_root != EMPTY_ROOT_HASH)
+            .map(|account| (account.storage_root, false))
+            .into_iter()
+            .collect(),
+        TrieNode::Leaf(_) | TrieNode::EmptyRoot => vec![],
+    }
+}
+
+impl From<TreeWitness> for IndexedWitness {
+    fn from(witness: TreeWitness) -> Self {
+        let mut indexed = IndexedWitness::new(witness.headers);
+        indexed.codes = witness.codes;
+
+        let mut nodes: Vec<_> = witness.nodes.into_iter().map(Some).collect();
+        let Some(root) = nodes.first_mut().and_then(Option::take) else {
+            return indexed;
+        };
+
+        // walk the tree, hashing and decoding each reachable node exactly once; a child is only
+        // inserted if its hash is actually referenced by its parent
+        let mut stack = vec![(node_references(&root.rlp, true), root.children)];
+        indexed.state.insert(keccak256(&root.rlp), root.rlp);
+        while let Some((references, children)) = stack.pop() {
+            for index in children {
+                let Some(child) = nodes.get_mut(index as usize).and_then(Option::take) else {
+                    continue;
+                };
+                let digest = keccak256(&child.rlp);
+                let Some(&(_, is_state_trie)) =
+                    references.iter().find(|(reference, _)| *reference == digest)
+                else {
+                    continue;
+                };
+                stack.push((node_references(&child.rlp, is_state_trie), child.children));
+                indexed.state.insert(digest, child.rlp);
+            }
+        }
+
+        indexed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::U256;
+    use risc0_ethereum_trie::Trie;
+
+    /// Returns the state root, the storage root of the only account with storage and the witness
+    /// of both tries.
+    fn execution_witness() -> (B256, B256, ExecutionWitness) {
+        let mut storage_trie = Trie::default();
+        for slot in 0..16u64 {
+            storage_trie.insert(
+                keccak256(B256::from(U256::from(slot))),
+                alloy_rlp::encode(U256::from(slot)),
+            );
+        }
+        let storage_root = storage_trie.hash_slow();
+
+        let mut state_trie = Trie::default();
+        for i in 0..16u8 {
+            let storage_root = if i == 0 { storage_root } else { EMPTY_ROOT_HASH };
+            let account = TrieAccount { nonce: i.into(), storage_root, ..Default::default() };
+            state_trie.insert(keccak256([i]), alloy_rlp::encode(account));
+        }
+
+        let state = state_trie.rlp_nodes().into_iter().chain(storage_trie.rlp_nodes()).collect();
+        (state_trie.hash_slow(), storage_root, ExecutionWitness { state, ..Default::default() })
+    }
+
+    #[test]
+    fn tree_witness() {
+        let (state_root, storage_root, witness) = execution_witness();
+        let tree = TreeWitness::from_execution_witness(&witness, state_root);
+        assert_eq!(IndexedWitness::from(tree.clone()).state, IndexedWitness::from(witness).state);
+
+        // link the storage root to the state root instead of its account leaf
+        let mut invalid = tree;
+        let index = invalid.nodes.iter().position(|node| keccak256(&node.rlp) == storage_root);
+        let index = index.unwrap() as u32;
+        invalid.nodes.iter_mut().for_each(|node| node.children.retain(|&child| child != index));
+        invalid.nodes[0].children.push(index);
+
+        let indexed = IndexedWitness::from(invalid);
+        assert!(indexed.state.contains_key(&state_root));
+        assert!(!indexed.state.contains_key(&storage_root));
+    }
+}
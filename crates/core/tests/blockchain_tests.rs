@@ -0,0 +1,305 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runner for the blockchain tests of `ethereum/tests` and the Ethereum Execution Spec Tests.
+//!
+//! The fixtures are loaded from the directory in `BLOCKCHAIN_TESTS_DIR`, which defaults to
+//! `testdata/blockchain_tests` in this crate. The pinned release is fetched by running
+//! `./fetch-blockchain-tests.sh` in the root of the repository, and the test fails without it.
+//! For each block, a stateless witness is built from the full pre-state and the block is validated
+//! using [validate_block]. Blocks that are expected to be invalid must be rejected.
+
+use alloy_consensus::Header;
+use alloy_hardforks::{EthereumHardfork, ForkCondition};
+use alloy_primitives::{Address, B256, Bytes, KECCAK256_EMPTY, U64, U256, keccak256};
+use alloy_rlp::Decodable;
+use alloy_trie::TrieAccount;
+use reth_ethereum_primitives::Block;
+use reth_evm::{ConfigureEvm, execute::Executor};
+use reth_primitives_traits::Block as _;
+use revm::{
+    database::{BundleState, CacheDB, EmptyDB},
+    state::{AccountInfo, Bytecode},
+};
+use risc0_ethereum_trie::Trie;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    panic::{AssertUnwindSafe, catch_unwind},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use zeth_chainspec::ChainSpec;
+use zeth_core::{EthEvmConfig, ExecutionWitness, validate_block};
+
+/// Maximum number of ancestor headers that can be accessed by the BLOCKHASH opcode.
+const MAX_ANCESTOR_HEADERS: usize = 256;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlockchainTest {
+    network: String,
+    #[serde(rename = "genesisRLP")]
+    genesis_rlp: Bytes,
+    pre: BTreeMap<Address, Account>,
+    blocks: Vec<TestBlock>,
+    lastblockhash: B256,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TestBlock {
+    rlp: Bytes,
+    expect_exception: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+struct Account {
+    nonce: U64,
+    balance: U256,
+    code: Bytes,
+    storage: BTreeMap<U256, U256>,
+}
+
+/// Returns the chain spec for the given fixture network, or `None` if it is not supported.
+fn chain_spec(network: &str) -> Option<Arc<ChainSpec>> {
+    let (active, transition) = match network {
+        "Cancun" => (EthereumHardfork::Cancun, None),
+        "Prague" => (EthereumHardfork::Prague, None),
+        "Osaka" => (EthereumHardfork::Osaka, None),
+        "ShanghaiToCancunAtTime15k" => (EthereumHardfork::Shanghai, Some(EthereumHardfork::Cancun)),
+        "CancunToPragueAtTime15k" => (EthereumHardfork::Cancun, Some(EthereumHardfork::Prague)),
+        "PragueToOsakaAtTime15k" => (EthereumHardfork::Prague, Some(EthereumHardfork::Osaka)),
+        _ => return None,
+    };
+
+    // activate all forks up to `active` at genesis and the `transition` fork at timestamp 15k
+    let forks = EthereumHardfork::mainnet()
+        .into_iter()
+        .chain([(EthereumHardfork::Osaka, ForkCondition::Never)])
+        .map(|(fork, condition)| {
+            let condition = match condition {
+                _ if Some(fork) == transition => ForkCondition::Timestamp(15_000),
+                _ if fork > active => ForkCondition::Never,
+                ForkCondition::Block(_) => ForkCondition::Block(0),
+                ForkCondition::TTD { .. } => ForkCondition::TTD {
+                    activation_block_number: 0,
+                    fork_block: Some(0),
+                    total_difficulty: U256::ZERO,
+                },
+                _ => ForkCondition::Timestamp(0),
+            };
+            (fork, condition)
+        })
+        .collect();
+
+    Some(ChainSpec::mainnet_with_forks(forks).into())
+}
+
+/// Builds an execution witness containing the complete state and the given ancestors.
+fn build_witness(state: &BTreeMap<Address, Account>, ancestors: &[Header]) -> ExecutionWitness {
+    let mut nodes = Vec::new();
+    let mut codes = Vec::new();
+
+    let mut state_trie = Trie::default();
+    for (address, account) in state {
+        let mut storage_trie = Trie::default();
+        for (slot, value) in account.storage.iter().filter(|(_, value)| !value.is_zero()) {
+            storage_trie.insert(keccak256(B256::from(*slot)), alloy_rlp::encode(value));
+        }
+        nodes.extend(storage_trie.rlp_nodes());
+
+        let code_hash = if account.code.is_empty() {
+            KECCAK256_EMPTY
+        } else {
+            codes.push(account.code.clone());
+            keccak256(&account.code)
+        };
+        let trie_account = TrieAccount {
+            nonce: account.nonce.to(),
+            balance: account.balance,
+            storage_root: storage_trie.hash_slow(),
+            code_hash,
+        };
+        state_trie.insert(keccak256(address), alloy_rlp::encode(trie_account));
+    }
+    nodes.extend(state_trie.rlp_nodes());
+
+    let first = ancestors.len().saturating_sub(MAX_ANCESTOR_HEADERS);
+    let headers =
+        ancestors[first..].iter().map(|header| alloy_rlp::encode(header).into()).collect();
+
+    ExecutionWitness { state: nodes, codes, keys: vec![], headers }
+}
+
+/// Executes the block on top of the given state and returns the resulting state changes.
+fn execute(
+    config: &EthEvmConfig<ChainSpec>,
+    state: &BTreeMap<Address, Account>,
+    ancestors: &[Header],
+    block: &Block,
+) -> BundleState {
+    let mut db = CacheDB::new(EmptyDB::default());
+    for (address, account) in state {
+        let code = Bytecode::new_raw(account.code.clone());
+        let info = AccountInfo {
+            balance: account.balance,
+            nonce: account.nonce.to(),
+            code_hash: code.hash_slow(),
+            code: Some(code),
+        };
+        db.insert_account_info(*address, info);
+        for (slot, value) in &account.storage {
+            db.insert_account_storage(*address, *slot, *value).unwrap();
+        }
+    }
+    for header in ancestors {
+        db.cache.block_hashes.insert(U256::from(header.number), header.hash_slow());
+    }
+
+    let block = block.clone().try_into_recovered().unwrap();
+    config.executor(&mut db).execute(&block).unwrap().state
+}
+
+/// Applies the state changes of a block to the state.
+fn apply(state: &mut BTreeMap<Address, Account>, bundle: BundleState) {
+    for (address, bundle_account) in bundle.state {
+        let Some(info) = bundle_account.info.as_ref() else {
+            state.remove(&address);
+            continue;
+        };
+
+        let account = state.entry(address).or_default();
+        if bundle_account.was_destroyed() {
+            account.storage.clear();
+        }
+        account.nonce = U64::from(info.nonce);
+        account.balance = info.balance;
+        if info.code_hash == KECCAK256_EMPTY {
+            account.code = Bytes::new();
+        } else if let Some(code) = bundle.contracts.get(&info.code_hash) {
+            account.code = code.original_bytes();
+        }
+        for (slot, value) in bundle_account.storage {
+            if value.present_value.is_zero() {
+                account.storage.remove(&slot);
+            } else {
+                account.storage.insert(slot, value.present_value);
+            }
+        }
+    }
+}
+
+/// Runs a single blockchain test and returns a description of the failure, if any.
+fn run_test(test: BlockchainTest, spec: Arc<ChainSpec>) -> Result<(), String> {
+    let config = EthEvmConfig::new(spec);
+    let genesis = Block::decode(&mut test.genesis_rlp.as_ref())
+        .map_err(|err| format!("invalid genesis RLP: {err}"))?;
+
+    let mut state = test.pre;
+    let mut ancestors = vec![genesis.header];
+    for (i, test_block) in test.blocks.into_iter().enumerate() {
+        // blocks with an invalid encoding are rejected before they can be validated
+        let block = match Block::decode(&mut test_block.rlp.as_ref()) {
+            Ok(block) => block,
+            Err(_) if test_block.expect_exception.is_some() => continue,
+            Err(err) => return Err(format!("block {i}: invalid RLP: {err}")),
+        };
+        let block_hash = block.hash_slow();
+
+        let witness = build_witness(&state, &ancestors);
+        let result = validate_block(block.clone(), witness, config.clone());
+        match (result, test_block.expect_exception) {
+            (Ok(hash), None) if hash == block_hash => {}
+            (Ok(hash), None) => return Err(format!("block {i}: hash mismatch: {hash}")),
+            (Err(err), None) => return Err(format!("block {i}: validation failed: {err}")),
+            (Ok(_), Some(exception)) => {
+                return Err(format!("block {i}: expected exception {exception}"));
+            }
+            (Err(_), Some(_)) => continue,
+        }
+
+        let bundle = execute(&config, &state, &ancestors, &block);
+        apply(&mut state, bundle);
+        ancestors.push(block.header);
+    }
+
+    let last_hash = ancestors.last().unwrap().hash_slow();
+    if last_hash != test.lastblockhash {
+        return Err(format!("last block hash mismatch: {last_hash} != {}", test.lastblockhash));
+    }
+
+    Ok(())
+}
+
+fn collect_fixtures(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect_fixtures(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "json") {
+            files.push(path);
+        }
+    }
+}
+
+#[test]
+fn blockchain_tests() {
+    let dir = env::var_os("BLOCKCHAIN_TESTS_DIR").map(PathBuf::from).unwrap_or_else(|| {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/blockchain_tests")
+    });
+    assert!(
+        dir.is_dir(),
+        "no blockchain tests found in {dir:?}, run ./fetch-blockchain-tests.sh to fetch them"
+    );
+
+    let mut files = Vec::new();
+    collect_fixtures(&dir, &mut files);
+    files.sort();
+
+    let (mut passed, mut skipped, mut failures) = (0, 0, Vec::new());
+    for file in files {
+        let json = fs::read_to_string(&file).unwrap();
+        let tests: BTreeMap<String, BlockchainTest> = match serde_json::from_str(&json) {
+            Ok(tests) => tests,
+            Err(err) => {
+                failures.push(format!("{file:?}: invalid fixture: {err}"));
+                continue;
+            }
+        };
+        for (name, test) in tests {
+            let Some(spec) = chain_spec(&test.network) else {
+                skipped += 1;
+                continue;
+            };
+            // a panic in one test case must not abort the remaining ones
+            match catch_unwind(AssertUnwindSafe(|| run_test(test, spec))) {
+                Ok(Ok(())) => passed += 1,
+                Ok(Err(err)) => failures.push(format!("{name}: {err}")),
+                Err(panic) => {
+                    let msg = panic
+                        .downcast_ref::<String>()
+                        .map(String::as_str)
+                        .or_else(|| panic.downcast_ref::<&str>().copied())
+                        .unwrap_or("unknown panic");
+                    failures.push(format!("{name}: panicked: {msg}"));
+                }
+            }
+        }
+    }
+
+    println!("passed: {passed}, skipped: {skipped}, failed: {}", failures.len());
+    assert!(failures.is_empty(), "failed blockchain tests:\n{}", failures.join("\n"));
+}
@@ -0,0 +1,68 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Round trip of a block with several transactions through [split_block], [execute_chunk] and
+//! [aggregate_chunks].
+
+mod common;
+
+use common::{block_and_witness, chain_spec};
+use reth_ethereum_primitives::Receipt;
+use reth_stateless::validation::StatelessValidationError;
+use std::num::NonZeroUsize;
+use zeth_core::{
+    EthEvmConfig,
+    chunk::{ChunkJournal, aggregate_chunks, execute_chunk, split_block, transactions_hash},
+};
+
+#[test]
+fn split_execute_aggregate() {
+    let chain_spec = chain_spec();
+    let config = EthEvmConfig::new(chain_spec.clone());
+    let (block, witness) = block_and_witness();
+    let block_nodes = witness.state.len();
+
+    let chunked =
+        split_block(block.clone(), witness, config.clone(), NonZeroUsize::new(2).unwrap()).unwrap();
+    assert_eq!(chunked.chunks.len(), 3);
+
+    // every chunk can be executed with only its own witness and yields the same journal
+    for ((input, witness), journal) in chunked.chunks.into_iter().zip(&chunked.aggregation.chunks) {
+        assert!(witness.state.len() < block_nodes, "chunk {} got the full witness", input.start);
+        let execution = execute_chunk(input, witness.into(), config.clone()).unwrap();
+        assert_eq!(&execution.journal, journal);
+    }
+
+    let block_hash = aggregate_chunks(chunked.aggregation.clone(), chain_spec.clone()).unwrap();
+    assert_eq!(block_hash, block.hash_slow());
+
+    // empty chunks are rejected, as a second chunk starting at 0 would repeat the pre-execution
+    // changes
+    let mut aggregation = chunked.aggregation;
+    let previous = aggregation.chunks[0].clone();
+    let empty = ChunkJournal {
+        start: previous.end,
+        transactions_hash: transactions_hash(&[]),
+        pre_state_root: previous.post_state_root,
+        gas_used_before: previous.gas_used_after,
+        receipts_root: Receipt::calculate_receipt_root_no_memo(&[]),
+        ..previous
+    };
+    aggregation.chunks.insert(1, empty);
+    let err = aggregate_chunks(aggregation, chain_spec).unwrap_err();
+    assert!(
+        matches!(&err, StatelessValidationError::StatelessExecutionFailed(msg) if msg.contains("chunk 1")),
+        "unexpected error: {err}"
+    );
+}
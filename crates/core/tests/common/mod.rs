@@ -0,0 +1,176 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A small block with several transactions, shared by the integration tests.
+//!
+//! The block is built on top of a pre-state with a few funded accounts and a contract that either
+//! increments or clears one of its storage slots, so that it reads, writes and removes both
+//! accounts and storage slots.
+
+use alloy_consensus::{Header, SignableTransaction, TxEip1559, proofs::calculate_transaction_root};
+use alloy_eips::eip4895::Withdrawals;
+use alloy_primitives::{
+    Address, B256, Bytes, KECCAK256_EMPTY, TxKind, U256, bytes, keccak256, logs_bloom,
+};
+use alloy_signer::SignerSync;
+use alloy_signer_local::PrivateKeySigner;
+use alloy_trie::{EMPTY_ROOT_HASH, TrieAccount};
+use reth_chainspec::{ChainSpec, ChainSpecBuilder};
+use reth_ethereum_primitives::{Block, BlockBody, TransactionSigned};
+use risc0_ethereum_trie::Trie;
+use std::sync::Arc;
+use zeth_core::{
+    EthEvmConfig, ExecutionWitness,
+    chunk::{ChunkInput, execute_chunk},
+};
+
+const GAS_LIMIT: u64 = 30_000_000;
+const BASE_FEE: u64 = 1_000_000_000;
+
+/// Increments slot 0 if called without calldata, otherwise clears slot 1.
+const COUNTER_CODE: Bytes = bytes!("36600e57600054600101600055005b600060015500");
+const COUNTER: Address = Address::repeat_byte(0xc0);
+const RECIPIENT: Address = Address::repeat_byte(0xee);
+
+pub fn chain_spec() -> Arc<ChainSpec> {
+    Arc::new(ChainSpecBuilder::mainnet().cancun_activated().build())
+}
+
+fn signers() -> Vec<PrivateKeySigner> {
+    (1..=3).map(|i| PrivateKeySigner::from_bytes(&B256::with_last_byte(i)).unwrap()).collect()
+}
+
+/// Commits to the pre-state and returns its root together with all its nodes.
+fn pre_state(signers: &[PrivateKeySigner]) -> (B256, Vec<Bytes>) {
+    let mut nodes = Vec::new();
+
+    let mut storage_trie = Trie::default();
+    for (slot, value) in [(0u64, 5u64), (1, 7)] {
+        storage_trie
+            .insert(keccak256(B256::from(U256::from(slot))), alloy_rlp::encode(U256::from(value)));
+    }
+    nodes.extend(storage_trie.rlp_nodes());
+
+    let mut state_trie = Trie::default();
+    let counter = TrieAccount {
+        nonce: 1,
+        balance: U256::ZERO,
+        storage_root: storage_trie.hash_slow(),
+        code_hash: keccak256(&COUNTER_CODE),
+    };
+    state_trie.insert(keccak256(COUNTER), alloy_rlp::encode(counter));
+    for signer in signers {
+        let account = TrieAccount {
+            nonce: 0,
+            balance: U256::from(10u128.pow(19)),
+            storage_root: EMPTY_ROOT_HASH,
+            code_hash: KECCAK256_EMPTY,
+        };
+        state_trie.insert(keccak256(signer.address()), alloy_rlp::encode(account));
+    }
+    nodes.extend(state_trie.rlp_nodes());
+
+    (state_trie.hash_slow(), nodes)
+}
+
+fn transaction(
+    signer: &PrivateKeySigner,
+    nonce: u64,
+    to: Address,
+    value: u64,
+    input: Bytes,
+) -> TransactionSigned {
+    let tx = TxEip1559 {
+        chain_id: 1,
+        nonce,
+        gas_limit: 100_000,
+        max_fee_per_gas: 2 * BASE_FEE as u128,
+        max_priority_fee_per_gas: 1,
+        to: TxKind::Call(to),
+        value: U256::from(value),
+        access_list: Default::default(),
+        input,
+    };
+    let signature = signer.sign_hash_sync(&tx.signature_hash()).unwrap();
+
+    tx.into_signed(signature).into()
+}
+
+/// Builds a valid block with five transactions and the witness of its complete pre-state.
+pub fn block_and_witness() -> (Block, ExecutionWitness) {
+    let signers = signers();
+    let (state_root, nodes) = pre_state(&signers);
+
+    let parent = Header {
+        gas_limit: GAS_LIMIT,
+        gas_used: GAS_LIMIT / 2,
+        base_fee_per_gas: Some(BASE_FEE),
+        state_root,
+        withdrawals_root: Some(EMPTY_ROOT_HASH),
+        blob_gas_used: Some(0),
+        excess_blob_gas: Some(0),
+        parent_beacon_block_root: Some(B256::ZERO),
+        ..Default::default()
+    };
+    let witness = ExecutionWitness {
+        state: nodes,
+        codes: vec![COUNTER_CODE],
+        keys: vec![],
+        headers: vec![alloy_rlp::encode(&parent).into()],
+    };
+
+    let transactions = vec![
+        transaction(&signers[0], 0, RECIPIENT, 1_000, Bytes::new()),
+        transaction(&signers[1], 0, COUNTER, 0, Bytes::new()),
+        transaction(&signers[0], 1, COUNTER, 0, Bytes::new()),
+        transaction(&signers[2], 0, COUNTER, 0, bytes!("01")),
+        transaction(&signers[1], 1, RECIPIENT, 2_000, Bytes::new()),
+    ];
+    let mut header = Header {
+        parent_hash: parent.hash_slow(),
+        beneficiary: Address::repeat_byte(0xfe),
+        transactions_root: calculate_transaction_root(&transactions),
+        withdrawals_root: Some(EMPTY_ROOT_HASH),
+        number: 1,
+        gas_limit: GAS_LIMIT,
+        timestamp: 12,
+        base_fee_per_gas: Some(BASE_FEE),
+        blob_gas_used: Some(0),
+        excess_blob_gas: Some(0),
+        parent_beacon_block_root: Some(B256::repeat_byte(0x01)),
+        ..Default::default()
+    };
+
+    // execute all transactions as a single chunk to complete the header
+    let input = ChunkInput {
+        header: header.clone(),
+        start: 0,
+        transactions: transactions.clone(),
+        last: true,
+        withdrawals: Some(Withdrawals::default()),
+        gas_used_before: 0,
+        pre_state_root: state_root,
+    };
+    let config = EthEvmConfig::new(chain_spec());
+    let execution = execute_chunk(input, witness.clone().into(), config).unwrap();
+    header.state_root = execution.journal.post_state_root;
+    header.receipts_root = execution.journal.receipts_root;
+    header.logs_bloom = logs_bloom(execution.receipts.iter().flat_map(|receipt| &receipt.logs));
+    header.gas_used = execution.journal.gas_used_after;
+
+    let body =
+        BlockBody { transactions, ommers: vec![], withdrawals: Some(Withdrawals::default()) };
+
+    (Block { header, body }, witness)
+}
@@ -0,0 +1,130 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test of the encoding of the journals committed by the guests.
+//!
+//! Each journal must decode to the same value it was encoded from, and the decoding must reject
+//! bytes that are not the encoding of any journal, e.g. truncated or extended ones.
+
+use alloy_consensus::Header;
+use alloy_primitives::{Address, B256, Bytes, U256, keccak256};
+use zeth_core::{
+    call::CallJournal,
+    header_chain::HeaderChainJournal,
+    proposal::ProposalJournal,
+    storage_proof::{ProvenAccount, StorageProofJournal},
+};
+
+/// An encoded journal together with encodings that must be rejected.
+struct Case {
+    name: &'static str,
+    encoded: Vec<u8>,
+    /// Decodes the journal and encodes it again.
+    reencode: fn(&[u8]) -> Option<Vec<u8>>,
+    invalid: Vec<Vec<u8>>,
+}
+
+/// Returns the bytes with one additional byte appended.
+fn extended(bytes: &[u8]) -> Vec<u8> {
+    [bytes, &[0]].concat()
+}
+
+fn cases() -> Vec<Case> {
+    let call = CallJournal {
+        chain_id: 1,
+        block_hash: B256::repeat_byte(0x01),
+        from: Address::repeat_byte(0x02),
+        to: Address::repeat_byte(0x03),
+        value: U256::from(5),
+        calldata_hash: keccak256([0x70, 0xa0, 0x82, 0x31]),
+        output: Bytes::from_static(&[0x04; 32]),
+    }
+    .encode();
+    let header_chain = HeaderChainJournal {
+        chain_id: 1,
+        first_hash: B256::repeat_byte(0x01),
+        last_hash: B256::repeat_byte(0x02),
+        count: 3,
+        validated: true,
+    }
+    .encode();
+    let header = Header {
+        number: 1,
+        gas_limit: 30_000_000,
+        base_fee_per_gas: Some(7),
+        extra_data: Bytes::from_static(b"zeth"),
+        ..Default::default()
+    };
+    let proposal = ProposalJournal { chain_id: 1, header }.encode();
+    let storage_proof = StorageProofJournal {
+        block_hash: B256::repeat_byte(1),
+        accounts: vec![
+            ProvenAccount {
+                address: Address::repeat_byte(2),
+                nonce: 3,
+                balance: U256::from(4),
+                code_hash: B256::repeat_byte(5),
+                storage_root: B256::repeat_byte(6),
+                storage: vec![(B256::repeat_byte(7), U256::from(8))],
+            },
+            ProvenAccount { address: Address::repeat_byte(9), ..Default::default() },
+        ],
+    }
+    .encode();
+
+    vec![
+        Case {
+            name: "call",
+            // the output takes all remaining bytes, so only the fixed fields can be truncated
+            invalid: vec![call[..call.len() - 33].to_vec()],
+            encoded: call,
+            reencode: |bytes| CallJournal::decode(bytes).map(|journal| journal.encode()),
+        },
+        Case {
+            name: "header chain",
+            invalid: vec![
+                header_chain[..header_chain.len() - 1].to_vec(),
+                extended(&header_chain),
+                [&header_chain[..header_chain.len() - 1], &[2]].concat(),
+            ],
+            encoded: header_chain,
+            reencode: |bytes| HeaderChainJournal::decode(bytes).map(|journal| journal.encode()),
+        },
+        Case {
+            name: "proposal",
+            invalid: vec![proposal[..proposal.len() - 1].to_vec(), extended(&proposal)],
+            encoded: proposal,
+            reencode: |bytes| ProposalJournal::decode(bytes).map(|journal| journal.encode()),
+        },
+        Case {
+            name: "storage proof",
+            invalid: vec![
+                storage_proof[..storage_proof.len() - 1].to_vec(),
+                extended(&storage_proof),
+            ],
+            encoded: storage_proof,
+            reencode: |bytes| StorageProofJournal::decode(bytes).map(|journal| journal.encode()),
+        },
+    ]
+}
+
+#[test]
+fn journal_encoding() {
+    for Case { name, encoded, reencode, invalid } in cases() {
+        assert_eq!(reencode(&encoded).as_ref(), Some(&encoded), "{name}: roundtrip");
+        for bytes in invalid {
+            assert_eq!(reencode(&bytes), None, "{name}: accepted invalid {}", Bytes::from(bytes));
+        }
+    }
+}
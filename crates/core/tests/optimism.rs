@@ -0,0 +1,114 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! End-to-end test of [validate_op_block] with the OP chain specs of zeth.
+//!
+//! These chain specs do not contain the genesis, so the methods that need it panic. Validating a
+//! block of the latest hardfork runs all the consensus checks and the execution of op-reth, which
+//! must never call any of them.
+#![cfg(feature = "optimism")]
+
+use alloy_consensus::{BlockBody, Header};
+use alloy_eips::{eip4895::Withdrawals, eip7685::EMPTY_REQUESTS_HASH};
+use alloy_hardforks::ForkCondition;
+use alloy_primitives::{B256, Bytes};
+use alloy_trie::EMPTY_ROOT_HASH;
+use reth_evm::{ConfigureEvm, execute::Executor};
+use reth_optimism_forks::{OpHardfork, OpHardforks};
+use reth_primitives_traits::Block as _;
+use reth_trie_common::{HashedPostState, KeccakKeyHasher};
+use revm::database::EmptyDB;
+use zeth_chainspec::optimism::OP_MAINNET;
+use zeth_core::{
+    ExecutionWitness, SparseState, StatelessTrie,
+    optimism::{OpBlock, OpEvmConfig, validate_op_block},
+};
+
+const GAS_LIMIT: u64 = 30_000_000;
+
+/// Returns the Holocene `extra_data` with the EIP-1559 parameters of OP Mainnet.
+fn holocene_extra_data() -> Bytes {
+    let mut extra_data = vec![0];
+    extra_data.extend(250u32.to_be_bytes());
+    extra_data.extend(6u32.to_be_bytes());
+    extra_data.into()
+}
+
+/// Returns an empty OP Mainnet block after Isthmus together with the witness of its parent.
+fn block_and_witness() -> (OpBlock, ExecutionWitness) {
+    let ForkCondition::Timestamp(isthmus) = OP_MAINNET.op_fork_activation(OpHardfork::Isthmus)
+    else {
+        panic!("Isthmus is not activated by timestamp");
+    };
+
+    // the parent uses exactly its gas target, so the base fee does not change
+    let parent = Header {
+        number: 140_000_000,
+        timestamp: isthmus + 1000,
+        gas_limit: GAS_LIMIT,
+        gas_used: GAS_LIMIT / 6,
+        base_fee_per_gas: Some(1_000_000),
+        withdrawals_root: Some(EMPTY_ROOT_HASH),
+        blob_gas_used: Some(0),
+        excess_blob_gas: Some(0),
+        parent_beacon_block_root: Some(B256::ZERO),
+        requests_hash: Some(EMPTY_REQUESTS_HASH),
+        extra_data: holocene_extra_data(),
+        ..Default::default()
+    };
+    let header = Header {
+        parent_hash: parent.hash_slow(),
+        number: parent.number + 1,
+        timestamp: parent.timestamp + 2,
+        gas_used: 0,
+        ..parent.clone()
+    };
+    let body = BlockBody {
+        transactions: vec![],
+        ommers: vec![],
+        withdrawals: Some(Withdrawals::default()),
+    };
+    let mut block = OpBlock { header, body };
+
+    // execute the block on the empty pre-state to compute its post-state root
+    let config = OpEvmConfig::optimism(OP_MAINNET.clone());
+    let recovered = block.clone().try_into_recovered().unwrap();
+    let output = config.executor(EmptyDB::default()).execute(&recovered).unwrap();
+    let hashed_state = HashedPostState::from_bundle_state::<KeccakKeyHasher>(&output.state.state);
+    let (mut state, _) = SparseState::new(&ExecutionWitness::default(), EMPTY_ROOT_HASH).unwrap();
+    block.header.state_root = state.calculate_state_root(hashed_state).unwrap();
+
+    let witness =
+        ExecutionWitness { headers: vec![alloy_rlp::encode(&parent).into()], ..Default::default() };
+
+    (block, witness)
+}
+
+#[test]
+fn empty_isthmus_block() {
+    let (block, witness) = block_and_witness();
+    let block_hash = block.hash_slow();
+
+    let config = OpEvmConfig::optimism(OP_MAINNET.clone());
+    assert_eq!(validate_op_block(block, witness, config).unwrap(), block_hash);
+}
+
+#[test]
+fn wrong_withdrawals_root() {
+    let (mut block, witness) = block_and_witness();
+    block.header.withdrawals_root = Some(B256::repeat_byte(0x01));
+
+    let config = OpEvmConfig::optimism(OP_MAINNET.clone());
+    validate_op_block(block, witness, config).unwrap_err();
+}
@@ -0,0 +1,94 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test of [execute_proposal] against the header of a sealed block.
+//!
+//! Proposing the transactions of a known block on top of its parent, with the attributes chosen
+//! by its builder, must reproduce exactly the header of that block.
+
+mod common;
+
+use alloy_consensus::Header;
+use alloy_primitives::keccak256;
+use common::{block_and_witness, chain_spec};
+use reth_consensus::ConsensusError;
+use reth_ethereum_primitives::Block;
+use reth_stateless::{ExecutionWitness, validation::StatelessValidationError};
+use zeth_core::{
+    EthEvmConfig,
+    proposal::{BlockProposal, ProposalAttributes, ProposalJournal, execute_proposal},
+};
+
+/// Returns the proposal that reproduces the given block.
+fn proposal(block: Block, witness: &ExecutionWitness) -> BlockProposal {
+    let parent: Header = alloy_rlp::decode_exact(&witness.headers[0]).unwrap();
+
+    let attributes = ProposalAttributes {
+        timestamp: block.header.timestamp,
+        suggested_fee_recipient: block.header.beneficiary,
+        prev_randao: block.header.mix_hash,
+        gas_limit: block.header.gas_limit,
+        extra_data: block.header.extra_data.clone(),
+        parent_beacon_block_root: block.header.parent_beacon_block_root,
+        withdrawals: block.body.withdrawals.clone(),
+    };
+    BlockProposal { parent, attributes, transactions: block.body.transactions }
+}
+
+#[test]
+fn sealed_block_header() {
+    let (block, witness) = block_and_witness();
+    let proposal = proposal(block.clone(), &witness);
+
+    // the witness of the sealed block, including the parent header, is also valid for the proposal
+    let config = EthEvmConfig::new(chain_spec());
+    let header =
+        execute_proposal(proposal.clone(), witness.clone().into(), config.clone()).unwrap();
+    assert_eq!(header, block.header);
+
+    // the block hash can be recomputed from the journal committed by the guest
+    let journal = ProposalJournal { chain_id: 1, header }.encode();
+    assert_eq!(keccak256(&journal[8..]), block.header.hash_slow());
+
+    // the headers are optional, as the proposal already contains the parent
+    let mut without_headers = witness;
+    without_headers.headers.clear();
+    assert_eq!(execute_proposal(proposal, without_headers.into(), config).unwrap(), block.header);
+}
+
+#[test]
+fn missing_withdrawals() {
+    let (block, witness) = block_and_witness();
+    let mut proposal = proposal(block, &witness);
+    proposal.attributes.withdrawals = None;
+
+    // only the check of the assembled block before its execution looks at the body
+    let config = EthEvmConfig::new(chain_spec());
+    let result = execute_proposal(proposal, witness.into(), config);
+    assert!(matches!(
+        result,
+        Err(StatelessValidationError::ConsensusValidationFailed(
+            ConsensusError::BodyWithdrawalsMissing
+        ))
+    ));
+}
+
+#[test]
+#[cfg(not(feature = "unsafe-pre-merge"))]
+fn pre_merge_parent() {
+    let config = EthEvmConfig::new(reth_chainspec::MAINNET.clone());
+    let result =
+        execute_proposal(BlockProposal::default(), ExecutionWitness::default().into(), config);
+    assert!(matches!(result, Err(StatelessValidationError::StatelessExecutionFailed(_))));
+}
@@ -0,0 +1,278 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Differential test of [SparseState] against the default stateless trie of reth.
+//!
+//! Random pre-states are committed to, and witnesses are generated either for the complete state or
+//! only for the touched keys, as `debug_executionWitness` would. Both implementations then perform
+//! the same reads and apply the same random [HashedPostState], and must agree on all the results,
+//! on the step that failed and on the kind of the error. Panics are not tolerated.
+
+use alloy_primitives::{Address, B256, Bytes, KECCAK256_EMPTY, U256, keccak256};
+use alloy_trie::{HashBuilder, Nibbles, TrieAccount, proof::ProofRetainer};
+use proptest::{collection::btree_map, option, prelude::*};
+use reth_primitives_traits::Account;
+use reth_stateless::{trie::StatelessSparseTrie, validation::StatelessValidationError};
+use reth_trie_common::{HashedPostState, HashedStorage};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    mem::{Discriminant, discriminant},
+};
+use zeth_core::{ExecutionWitness, SparseState, StatelessTrie};
+
+/// Number of distinct addresses, small enough that updates frequently hit existing accounts.
+const NUM_ADDRESSES: u8 = 8;
+/// Number of distinct storage slots per account.
+const NUM_SLOTS: u8 = 16;
+
+#[derive(Clone, Debug, Default)]
+struct TestAccount {
+    nonce: u64,
+    balance: U256,
+    storage: BTreeMap<U256, U256>,
+}
+
+type TestState = BTreeMap<Address, TestAccount>;
+
+/// Changes to a single account, `None` deletes the account.
+#[derive(Clone, Debug)]
+struct TestUpdate {
+    account: Option<(u64, U256)>,
+    wiped: bool,
+    storage: BTreeMap<U256, U256>,
+}
+
+fn address() -> impl Strategy<Value = Address> {
+    (0..NUM_ADDRESSES).prop_map(Address::with_last_byte)
+}
+
+fn slot() -> impl Strategy<Value = U256> {
+    (0..NUM_SLOTS).prop_map(U256::from)
+}
+
+fn nonzero_value() -> impl Strategy<Value = U256> {
+    (1..u64::MAX).prop_map(U256::from)
+}
+
+/// Values of storage updates, where zero values remove the slot and may create orphans.
+fn value() -> impl Strategy<Value = U256> {
+    prop_oneof![Just(U256::ZERO), nonzero_value()]
+}
+
+fn pre_state() -> impl Strategy<Value = TestState> {
+    let account = (any::<u64>(), any::<u64>(), btree_map(slot(), nonzero_value(), 0..16)).prop_map(
+        |(nonce, balance, storage)| TestAccount { nonce, balance: U256::from(balance), storage },
+    );
+    btree_map(address(), account, 0..NUM_ADDRESSES as usize)
+}
+
+fn updates() -> impl Strategy<Value = BTreeMap<Address, TestUpdate>> {
+    let update = (
+        option::weighted(0.8, (any::<u64>(), any::<u64>())),
+        prop::bool::weighted(0.2),
+        btree_map(slot(), value(), 0..8),
+    )
+        .prop_map(|(account, wiped, storage)| match account {
+            Some((nonce, balance)) => {
+                TestUpdate { account: Some((nonce, U256::from(balance))), wiped, storage }
+            }
+            // deleted accounts always have their storage wiped
+            None => TestUpdate { account: None, wiped: true, storage: BTreeMap::new() },
+        });
+    btree_map(address(), update, 1..NUM_ADDRESSES as usize)
+}
+
+/// Builds the trie of the given leaves and returns its root and the proof nodes for the targets.
+fn build_trie(leaves: &BTreeMap<B256, Vec<u8>>, targets: &BTreeSet<B256>) -> (B256, Vec<Bytes>) {
+    let retainer = ProofRetainer::new(targets.iter().map(Nibbles::unpack).collect());
+    let mut builder = HashBuilder::default().with_proof_retainer(retainer);
+    for (key, value) in leaves {
+        builder.add_leaf(Nibbles::unpack(key), value);
+    }
+    let root = builder.root();
+    let nodes = builder.take_proof_nodes().values().cloned().collect();
+
+    (root, nodes)
+}
+
+/// Computes the state root and the witness for the given state.
+///
+/// If `full` is false, the witness only contains the proofs for the touched accounts and slots.
+fn build_witness(
+    state: &TestState,
+    touched: &BTreeMap<Address, BTreeSet<U256>>,
+    full: bool,
+) -> (B256, ExecutionWitness) {
+    let mut nodes = Vec::new();
+    let mut accounts = BTreeMap::new();
+    for (address, account) in state {
+        let leaves: BTreeMap<_, _> = account
+            .storage
+            .iter()
+            .map(|(slot, value)| (keccak256(B256::from(*slot)), alloy_rlp::encode(value)))
+            .collect();
+        let targets = if full {
+            leaves.keys().copied().collect()
+        } else {
+            touched
+                .get(address)
+                .into_iter()
+                .flatten()
+                .map(|slot| keccak256(B256::from(*slot)))
+                .collect()
+        };
+        let (storage_root, storage_nodes) = build_trie(&leaves, &targets);
+        nodes.extend(storage_nodes);
+
+        let account = TrieAccount {
+            nonce: account.nonce,
+            balance: account.balance,
+            storage_root,
+            code_hash: KECCAK256_EMPTY,
+        };
+        accounts.insert(keccak256(address), alloy_rlp::encode(account));
+    }
+
+    let targets = if full {
+        accounts.keys().copied().collect()
+    } else {
+        touched.keys().map(keccak256).collect()
+    };
+    let (state_root, state_nodes) = build_trie(&accounts, &targets);
+    nodes.extend(state_nodes);
+
+    (state_root, ExecutionWitness { state: nodes, codes: vec![], keys: vec![], headers: vec![] })
+}
+
+/// Applies the updates to the state.
+fn apply(state: &mut TestState, updates: &BTreeMap<Address, TestUpdate>) {
+    for (address, update) in updates {
+        let Some((nonce, balance)) = update.account else {
+            state.remove(address);
+            continue;
+        };
+        let account = state.entry(*address).or_default();
+        if update.wiped {
+            account.storage.clear();
+        }
+        for (slot, value) in &update.storage {
+            if value.is_zero() {
+                account.storage.remove(slot);
+            } else {
+                account.storage.insert(*slot, *value);
+            }
+        }
+        account.nonce = nonce;
+        account.balance = balance;
+    }
+}
+
+fn hashed_post_state(updates: &BTreeMap<Address, TestUpdate>) -> HashedPostState {
+    let mut post_state = HashedPostState::default();
+    for (address, update) in updates {
+        let hashed_address = keccak256(address);
+        let account =
+            update.account.map(|(nonce, balance)| Account { nonce, balance, bytecode_hash: None });
+        post_state.accounts.insert(hashed_address, account);
+
+        let storage = HashedStorage::from_iter(
+            update.wiped,
+            update.storage.iter().map(|(slot, value)| (keccak256(B256::from(*slot)), *value)),
+        );
+        if storage.wiped || !storage.storage.is_empty() {
+            post_state.storages.insert(hashed_address, storage);
+        }
+    }
+
+    post_state
+}
+
+/// The observable results of running a trie implementation.
+#[derive(Debug, PartialEq)]
+struct Outcome {
+    accounts: Vec<Option<TrieAccount>>,
+    values: Vec<U256>,
+    state_root: B256,
+}
+
+/// The step in which a trie implementation failed, with the kind of the validation error.
+#[derive(Debug, PartialEq)]
+enum Failure {
+    New(Discriminant<StatelessValidationError>),
+    Account,
+    Storage,
+    StateRoot(Discriminant<StatelessValidationError>),
+}
+
+/// Performs the reads of the touched keys and applies the post state, like block validation does.
+fn run<T: StatelessTrie>(
+    witness: &ExecutionWitness,
+    pre_state_root: B256,
+    touched: &BTreeMap<Address, BTreeSet<U256>>,
+    post_state: HashedPostState,
+) -> Result<Outcome, Failure> {
+    let (mut trie, _) =
+        T::new(witness, pre_state_root).map_err(|err| Failure::New(discriminant(&err)))?;
+
+    let (mut accounts, mut values) = (Vec::new(), Vec::new());
+    for (address, slots) in touched {
+        let account = trie.account(*address).map_err(|_| Failure::Account)?;
+        // storage of nonexistent accounts is never read during execution
+        if account.is_some() {
+            for slot in slots {
+                values.push(trie.storage(*address, *slot).map_err(|_| Failure::Storage)?);
+            }
+        }
+        accounts.push(account);
+    }
+    let state_root = trie
+        .calculate_state_root(post_state)
+        .map_err(|err| Failure::StateRoot(discriminant(&err)))?;
+
+    Ok(Outcome { accounts, values, state_root })
+}
+
+proptest! {
+    #[test]
+    fn sparse_state_matches_reth(pre in pre_state(), updates in updates(), full in any::<bool>()) {
+        let touched: BTreeMap<_, BTreeSet<_>> = updates
+            .iter()
+            .map(|(address, update)| (*address, update.storage.keys().copied().collect()))
+            .collect();
+        let (pre_state_root, witness) = build_witness(&pre, &touched, full);
+        let post_state = hashed_post_state(&updates);
+
+        let zeth = run::<SparseState>(&witness, pre_state_root, &touched, post_state.clone());
+        let reth = run::<StatelessSparseTrie>(&witness, pre_state_root, &touched, post_state);
+        prop_assert_eq!(&zeth, &reth);
+
+        let mut post = pre;
+        apply(&mut post, &updates);
+        let (expected_root, _) = build_witness(&post, &BTreeMap::new(), false);
+        match zeth {
+            Ok(outcome) => prop_assert_eq!(outcome.state_root, expected_root),
+            // only the state root calculation may fail, if the witness lacks the nodes that are
+            // required to update the tries
+            Err(failure) => {
+                prop_assert!(!full, "validation with the complete state must not fail");
+                prop_assert_eq!(
+                    failure,
+                    Failure::StateRoot(discriminant(
+                        &StatelessValidationError::StatelessStateRootCalculationFailed
+                    ))
+                );
+            }
+        }
+    }
+}
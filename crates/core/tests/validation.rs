@@ -0,0 +1,108 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Differential test of [validate_block] against `stateless_validation` of reth.
+//!
+//! The block validation of zeth follows the one of reth step by step, but operates on an indexed
+//! witness. Both must accept the valid block and reject the same corrupted blocks and witnesses.
+
+mod common;
+
+use alloy_consensus::Header;
+use alloy_primitives::{B256, Bytes, keccak256};
+use common::{block_and_witness, chain_spec};
+use reth_ethereum_primitives::Block;
+use reth_stateless::{ExecutionWitness, validation::stateless_validation};
+use std::mem::discriminant;
+use zeth_core::{EthEvmConfig, validate_block};
+
+/// Validates the block with both implementations and checks that their results agree.
+fn assert_same_result(block: Block, witness: ExecutionWitness) {
+    let chain_spec = chain_spec();
+    let config = EthEvmConfig::new(chain_spec.clone());
+
+    let expected = stateless_validation(block.clone(), witness.clone(), chain_spec, config.clone());
+    let result = validate_block(block, witness, config);
+    match (&result, &expected) {
+        (Ok(hash), Ok(expected_hash)) => assert_eq!(hash, expected_hash),
+        (Err(err), Err(expected_err)) => assert_eq!(
+            discriminant(err),
+            discriminant(expected_err),
+            "got {err}, expected {expected_err}"
+        ),
+        _ => panic!("got {result:?}, expected {expected:?}"),
+    }
+}
+
+#[test]
+fn valid_block() {
+    let (block, witness) = block_and_witness();
+    let block_hash = block.hash_slow();
+    assert_same_result(block.clone(), witness.clone());
+
+    let config = EthEvmConfig::new(chain_spec());
+    assert_eq!(validate_block(block, witness, config).unwrap(), block_hash);
+}
+
+#[test]
+fn invalid_header() {
+    let (block, witness) = block_and_witness();
+
+    let mut invalid = block.clone();
+    invalid.header.state_root = B256::repeat_byte(0x01);
+    assert_same_result(invalid, witness.clone());
+
+    let mut invalid = block.clone();
+    invalid.header.receipts_root = B256::repeat_byte(0x01);
+    assert_same_result(invalid, witness.clone());
+
+    let mut invalid = block.clone();
+    invalid.header.gas_used -= 1;
+    assert_same_result(invalid, witness.clone());
+
+    let mut invalid = block;
+    invalid.header.parent_hash = B256::repeat_byte(0x01);
+    assert_same_result(invalid, witness);
+}
+
+#[test]
+fn invalid_ancestors() {
+    let (block, witness) = block_and_witness();
+
+    // the ancestor headers must be RLP encoded without any trailing bytes
+    let mut invalid = witness.clone();
+    let mut parent = invalid.headers[0].to_vec();
+    parent.push(0x00);
+    invalid.headers[0] = parent.into();
+    assert_same_result(block.clone(), invalid);
+
+    let mut invalid = witness;
+    invalid.headers.clear();
+    assert_same_result(block, invalid);
+}
+
+#[test]
+fn incomplete_witness() {
+    let (block, witness) = block_and_witness();
+
+    // without the root node or the code of the pre-state, the block can no longer be validated
+    let parent: Header = alloy_rlp::decode_exact(&witness.headers[0]).unwrap();
+    let mut invalid = witness.clone();
+    invalid.state.retain(|node| keccak256(node) != parent.state_root);
+    assert_same_result(block.clone(), invalid);
+
+    let mut invalid = witness;
+    invalid.codes = vec![Bytes::new()];
+    assert_same_result(block, invalid);
+}
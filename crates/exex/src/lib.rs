@@ -0,0 +1,229 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Execution extension (ExEx) for reth that validates every committed block with zeth and hands
+//! its input to a prover backend.
+
+use alloy_consensus::Header;
+use alloy_primitives::B256;
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use reth_chainspec::{EthChainSpec, Hardforks};
+use reth_ethereum_primitives::{Block, EthPrimitives};
+use reth_evm::eth::spec::EthExecutorSpec;
+use reth_exex::{ExExContext, ExExEvent};
+use reth_node_api::{FullNodeComponents, NodeTypes};
+use reth_primitives_traits::RecoveredBlock;
+use reth_stateless::StatelessInput;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{error, info};
+use zeth_core::EthEvmConfig;
+
+mod witness;
+
+pub use witness::execution_witness;
+
+/// The backend receiving the inputs of the validated blocks, e.g. a proving job queue.
+#[async_trait]
+pub trait ProverBackend: Send + Sync {
+    /// Handles the input of a newly committed block that has been validated successfully.
+    async fn prove(&self, block_hash: B256, input: StatelessInput) -> eyre::Result<()>;
+
+    /// Handles a previously committed block that is no longer part of the canonical chain.
+    async fn revert(&self, block_hash: B256) -> eyre::Result<()>;
+}
+
+/// An event emitted by the [ChannelProver].
+#[derive(Debug)]
+pub enum ProverEvent {
+    /// A block has been committed and validated.
+    Prove { block_hash: B256, input: Box<StatelessInput> },
+    /// A block has been reverted.
+    Revert { block_hash: B256 },
+}
+
+/// A [ProverBackend] forwarding all blocks to a channel, so that they can be proven elsewhere.
+#[derive(Clone, Debug)]
+pub struct ChannelProver {
+    sender: mpsc::UnboundedSender<ProverEvent>,
+}
+
+impl ChannelProver {
+    /// Creates the backend together with the receiver of its events.
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<ProverEvent>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (Self { sender }, receiver)
+    }
+}
+
+#[async_trait]
+impl ProverBackend for ChannelProver {
+    async fn prove(&self, block_hash: B256, input: StatelessInput) -> eyre::Result<()> {
+        self.sender.send(ProverEvent::Prove { block_hash, input: input.into() })?;
+        Ok(())
+    }
+
+    async fn revert(&self, block_hash: B256) -> eyre::Result<()> {
+        self.sender.send(ProverEvent::Revert { block_hash })?;
+        Ok(())
+    }
+}
+
+/// The ExEx validating each committed block with zeth.
+///
+/// The witness is built from the state provider of the node, the block is then validated with
+/// [zeth_core::validate_block] using the given chain specification, which should be the one of
+/// the guest, i.e. from `zeth_chainspec`. Blocks that fail validation are logged and skipped.
+pub struct ZethExEx<Node: FullNodeComponents, C, B> {
+    ctx: ExExContext<Node>,
+    chain_spec: Arc<C>,
+    backend: B,
+}
+
+impl<Node, C, B> ZethExEx<Node, C, B>
+where
+    Node: FullNodeComponents<Types: NodeTypes<Primitives = EthPrimitives>>,
+    C: EthExecutorSpec + EthChainSpec<Header = Header> + Hardforks + 'static,
+    B: ProverBackend,
+{
+    /// Creates the ExEx from its context.
+    pub fn new(ctx: ExExContext<Node>, chain_spec: Arc<C>, backend: B) -> Self {
+        Self { ctx, chain_spec, backend }
+    }
+
+    /// Processes the notifications of the node until the node shuts down.
+    ///
+    /// Reverted blocks, including the old blocks of a reorg, are passed to
+    /// [ProverBackend::revert] from the tip downwards, before the new blocks are validated.
+    pub async fn run(mut self) -> eyre::Result<()> {
+        while let Some(notification) = self.ctx.notifications.try_next().await? {
+            if let Some(reverted) = notification.reverted_chain() {
+                for block in reverted.blocks().values().rev() {
+                    info!(number = block.number, hash = %block.hash(), "Reverting block");
+                    self.backend.revert(block.hash()).await?;
+                }
+            }
+
+            if let Some(committed) = notification.committed_chain() {
+                for block in committed.blocks_iter() {
+                    self.process(block).await?;
+                }
+                self.ctx.events.send(ExExEvent::FinishedHeight(committed.tip().num_hash()))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validates a single block and passes its input to the backend.
+    async fn process(&self, block: &RecoveredBlock<Block>) -> eyre::Result<()> {
+        let block_hash = block.hash();
+        let witness = execution_witness(self.ctx.provider(), self.ctx.evm_config(), block)?;
+        let input = StatelessInput { block: block.clone().into_block(), witness };
+
+        // validation is CPU bound, so it must not block the notification stream
+        let config = EthEvmConfig::new(self.chain_spec.clone());
+        let (block, witness) = (input.block.clone(), input.witness.clone());
+        let result =
+            tokio::task::spawn_blocking(move || zeth_core::validate_block(block, witness, config))
+                .await?;
+        match result {
+            Ok(hash) if hash == block_hash => {
+                info!(number = input.block.number, hash = %block_hash, "Block validated");
+                self.backend.prove(block_hash, input).await
+            }
+            Ok(hash) => {
+                error!(number = input.block.number, %hash, expected = %block_hash, "Hash mismatch");
+                Ok(())
+            }
+            Err(err) => {
+                error!(number = input.block.number, hash = %block_hash, %err, "Validation failed");
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_consensus::{BlockBody, EMPTY_OMMER_ROOT_HASH, EMPTY_ROOT_HASH};
+    use alloy_eips::eip1559::BaseFeeParams;
+    use alloy_genesis::Genesis;
+    use reth_chainspec::{ChainSpec, ChainSpecBuilder};
+    use reth_execution_types::{Chain, ExecutionOutcome};
+    use reth_exex_test_utils::test_exex_context_with_chain_spec;
+    use reth_primitives_traits::{Block as _, SealedHeader};
+
+    /// Returns an empty post-Shanghai block on top of the parent.
+    fn next_block(parent: &SealedHeader) -> RecoveredBlock<Block> {
+        let header = Header {
+            parent_hash: parent.hash(),
+            ommers_hash: EMPTY_OMMER_ROOT_HASH,
+            state_root: parent.state_root,
+            transactions_root: EMPTY_ROOT_HASH,
+            receipts_root: EMPTY_ROOT_HASH,
+            withdrawals_root: Some(EMPTY_ROOT_HASH),
+            number: parent.number + 1,
+            gas_limit: parent.gas_limit,
+            timestamp: parent.timestamp + 12,
+            base_fee_per_gas: parent.next_block_base_fee(BaseFeeParams::ethereum()),
+            ..Default::default()
+        };
+        let body = BlockBody { withdrawals: Some(Default::default()), ..Default::default() };
+        Block::new(header, body).try_into_recovered().unwrap()
+    }
+
+    fn chain(block: RecoveredBlock<Block>) -> Chain {
+        let outcome = ExecutionOutcome { first_block: block.number, ..Default::default() };
+        Chain::new([block], outcome, None)
+    }
+
+    #[tokio::test]
+    async fn commit_and_revert() -> eyre::Result<()> {
+        let genesis = Genesis { gas_limit: 30_000_000, ..Default::default() };
+        let chain_spec: Arc<ChainSpec> =
+            ChainSpecBuilder::mainnet().genesis(genesis).shanghai_activated().build().into();
+        let (ctx, mut handle) = test_exex_context_with_chain_spec(chain_spec.clone()).await?;
+
+        let (backend, mut events) = ChannelProver::new();
+        let exex = ZethExEx::new(ctx, chain_spec.clone(), backend);
+        let exex = tokio::spawn(exex.run());
+
+        let block = next_block(&chain_spec.sealed_genesis_header());
+        let block_hash = block.hash();
+
+        handle.send_notification_chain_committed(chain(block.clone())).await?;
+        match events.recv().await {
+            Some(ProverEvent::Prove { block_hash: hash, input }) => {
+                assert_eq!(hash, block_hash);
+                assert_eq!(input.block.hash_slow(), block_hash);
+            }
+            event => panic!("unexpected event: {event:?}"),
+        }
+
+        let num_hash = block.num_hash();
+        handle.send_notification_chain_reverted(chain(block)).await?;
+        match events.recv().await {
+            Some(ProverEvent::Revert { block_hash: hash }) => assert_eq!(hash, block_hash),
+            event => panic!("unexpected event: {event:?}"),
+        }
+        // the notifications are processed in order, so the commit must have been acknowledged
+        handle.assert_event_finished_height(num_hash)?;
+
+        exex.abort();
+        Ok(())
+    }
+}
@@ -0,0 +1,57 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy_consensus::{BlockHeader, Header};
+use reth_ethereum_primitives::{Block, EthPrimitives};
+use reth_evm::{ConfigureEvm, execute::Executor};
+use reth_primitives_traits::RecoveredBlock;
+use reth_provider::{HeaderProvider, StateProviderFactory};
+use reth_revm::{database::StateProviderDatabase, witness::ExecutionWitnessRecord};
+use reth_stateless::ExecutionWitness;
+
+/// Builds the [ExecutionWitness] of the block directly from the state of the node.
+///
+/// The block is re-executed on top of the state of its parent, recording all accessed accounts,
+/// storage slots, bytecodes and block hashes. This is equivalent to `debug_executionWitness`, but
+/// does not require the RPC server.
+pub fn execution_witness<P, E>(
+    provider: &P,
+    evm_config: &E,
+    block: &RecoveredBlock<Block>,
+) -> eyre::Result<ExecutionWitness>
+where
+    P: StateProviderFactory + HeaderProvider<Header = Header>,
+    E: ConfigureEvm<Primitives = EthPrimitives>,
+{
+    let state_provider = provider.history_by_block_hash(block.parent_hash())?;
+
+    let mut record = ExecutionWitnessRecord::default();
+    evm_config
+        .executor(StateProviderDatabase::new(&state_provider))
+        .execute_with_state_closure(block, |state| record.record_executed_state(state))?;
+    let ExecutionWitnessRecord { hashed_state, codes, keys, lowest_block_number } = record;
+
+    let state = state_provider.witness(Default::default(), hashed_state)?;
+
+    // the headers from the oldest accessed block hash up to the parent
+    let number = block.number();
+    let lowest = lowest_block_number.unwrap_or(number.saturating_sub(1));
+    let headers = provider
+        .headers_range(lowest..number)?
+        .iter()
+        .map(|h| alloy_rlp::encode(h).into())
+        .collect();
+
+    Ok(ExecutionWitness { state, codes, keys, headers })
+}
@@ -14,28 +14,49 @@
 
 use alloy::{
     eips::BlockId,
-    primitives::B256,
+    primitives::{Address, B256, Bytes, U256},
     providers::{Provider, ProviderBuilder},
 };
+use alloy_chains::NamedChain;
 use anyhow::{Context, ensure};
 use clap::{Parser, Subcommand};
-use reth_stateless::StatelessInput;
+use op_alloy_network::Optimism;
+use reth_stateless::{ExecutionWitness, StatelessInput};
+use risc0_zkvm::{Receipt, SessionInfo, compute_image_id};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use std::{
     cmp::PartialEq,
     fs::{self, File},
     io::{BufReader, BufWriter},
+    num::NonZeroUsize,
     path::{Path, PathBuf},
     sync::Arc,
 };
-use zeth_host::{BlockProcessor, to_zkvm_input_bytes};
+use zeth_core::{
+    call::{CallJournal, CallRequest},
+    chunk::{AggregationInput, AggregationJournal, ChunkInput, ChunkJournal},
+    header_chain::HeaderChainJournal,
+    input::InputFormat,
+    optimism::{OpBlock, OpStatelessInput},
+    storage_proof::StorageProofJournal,
+};
+use zeth_host::{
+    BlockProcessor, chunk_image_id,
+    image_id::{GUEST_CHAINS, Manifest, current_release, embedded_elf, load_guest_elf},
+    input_size,
+    optimism::OpBlockProcessor,
+    payload::NewPayload,
+    prove_chunk, prove_chunk_aggregation, value_size,
+    witness::{WitnessSource, WitnessSourceKind},
+};
 
-/// Simple CLI to create Ethereum block execution proofs.
+/// Simple CLI to create Ethereum and OP-stack block execution proofs.
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Cli {
-    /// URL of the Ethereum RPC endpoint to connect to.
+    /// URL of the Ethereum or OP-stack RPC endpoint to connect to.
     #[arg(long, env)]
-    eth_rpc_url: String,
+    eth_rpc_url: Option<String>,
 
     /// Block number, tag, or hash (e.g., "latest", "0x1565483") to execute.
     #[arg(long, global = true, default_value = "latest")]
@@ -45,6 +66,25 @@ struct Cli {
     #[arg(long, global = true, default_value = "./cache")]
     cache_dir: PathBuf,
 
+    /// Source of the execution witness. The `file` source loads `witness_<block_hash>.json` from
+    /// the cache folder.
+    #[arg(long, global = true, value_enum, default_value_t = WitnessSourceKind::Rpc)]
+    witness_source: WitnessSourceKind,
+
+    /// Guest ELF to use instead of the one embedded at build time, e.g. a release binary. Its
+    /// image ID must match the manifest of this release.
+    #[arg(long, global = true)]
+    guest_elf: Option<PathBuf>,
+
+    /// Use a `--guest-elf` even if its image ID does not match the manifest of this release.
+    #[arg(long, global = true, requires = "guest_elf")]
+    allow_unknown_guest_elf: bool,
+
+    /// JSON file with the params of an `engine_newPayload` call, or the complete request, to use
+    /// as the block instead of `--block`.
+    #[arg(long, global = true)]
+    payload: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -54,8 +94,43 @@ enum Commands {
     /// Validate the block and generate a RISC Zero proof.
     Prove(ProveCommand),
 
+    /// Validate the block and prove it in chunks of transactions, aggregating the chunk proofs into
+    /// a single proof.
+    ProveChunked(ProveChunkedCommand),
+
+    /// Validate the block and split it into chunks of transactions, writing the inputs of all
+    /// chunks and of their aggregation to the cache folder.
+    Split(SplitCommand),
+
+    /// Prove a single chunk written by `split` and write its receipt to the cache folder. Requires
+    /// `--block` to be a block hash, but no RPC endpoint.
+    ProveChunk(ProveChunkCommand),
+
+    /// Aggregate the chunk receipts written by `prove-chunk` into a single proof of the block.
+    /// Requires `--block` to be a block hash, but no RPC endpoint.
+    Aggregate(AggregateCommand),
+
     /// Validate the block on the host machine, without proving.
     Validate,
+
+    /// Validate the block and execute the guest without proving, reporting the input sizes and
+    /// cycle counts for each input format.
+    Execute,
+
+    /// Execute a call against the state of the block and optionally prove its result.
+    Call(CallCommand),
+
+    /// Verify the chain of headers from `--from` up to the block without executing the blocks and
+    /// optionally prove it.
+    HeaderChain(HeaderChainCommand),
+
+    /// Verify EIP-1186 account and storage proofs against the state root of the block and
+    /// optionally prove the values.
+    StorageProof(StorageProofCommand),
+
+    /// Inspect the image IDs of the guest programs.
+    #[command(subcommand)]
+    ImageId(ImageIdCommand),
 }
 
 #[derive(Parser, Debug, PartialEq, Eq)]
@@ -65,6 +140,148 @@ struct ProveCommand {
     segment_po2: Option<u32>,
 }
 
+#[derive(Parser, Debug, PartialEq, Eq)]
+struct ProveChunkedCommand {
+    /// Maximum number of transactions per chunk.
+    #[arg(long, default_value_t = NonZeroUsize::new(100).unwrap())]
+    chunk_size: NonZeroUsize,
+
+    /// Maximum number of chunks proven at the same time.
+    #[arg(long, default_value_t = 1)]
+    concurrency: usize,
+
+    /// Optional segment limit po2
+    #[arg(long, env)]
+    segment_po2: Option<u32>,
+}
+
+#[derive(Parser, Debug, PartialEq, Eq)]
+struct SplitCommand {
+    /// Maximum number of transactions per chunk.
+    #[arg(long, default_value_t = NonZeroUsize::new(100).unwrap())]
+    chunk_size: NonZeroUsize,
+}
+
+#[derive(Parser, Debug, PartialEq, Eq)]
+struct ProveChunkCommand {
+    /// Index of the chunk to prove.
+    #[arg(long)]
+    index: usize,
+
+    /// Optional segment limit po2
+    #[arg(long, env)]
+    segment_po2: Option<u32>,
+}
+
+#[derive(Parser, Debug, PartialEq, Eq)]
+struct AggregateCommand {
+    /// Optional segment limit po2
+    #[arg(long, env)]
+    segment_po2: Option<u32>,
+}
+
+#[derive(Parser, Debug, PartialEq, Eq)]
+struct CallCommand {
+    /// Address of the called contract.
+    #[arg(long)]
+    to: Address,
+
+    /// Hex encoded calldata.
+    #[arg(long, default_value = "0x")]
+    data: Bytes,
+
+    /// Sender of the call.
+    #[arg(long, default_value_t = Address::ZERO)]
+    from: Address,
+
+    /// Value sent with the call, in wei.
+    #[arg(long, default_value_t = U256::ZERO)]
+    value: U256,
+
+    /// Generate a RISC Zero proof of the call result.
+    #[arg(long)]
+    prove: bool,
+
+    /// Optional segment limit po2
+    #[arg(long, env)]
+    segment_po2: Option<u32>,
+}
+
+#[derive(Parser, Debug, PartialEq, Eq)]
+struct HeaderChainCommand {
+    /// Number of the first block of the chain.
+    #[arg(long)]
+    from: u64,
+
+    /// Also validate each header against its parent, e.g. the base fee and gas limit bounds.
+    #[arg(long)]
+    validate_headers: bool,
+
+    /// Generate a RISC Zero proof of the header chain.
+    #[arg(long)]
+    prove: bool,
+
+    /// Optional segment limit po2
+    #[arg(long, env)]
+    segment_po2: Option<u32>,
+}
+
+#[derive(Parser, Debug, PartialEq, Eq)]
+struct StorageProofCommand {
+    /// Account to query, optionally followed by a storage key, e.g. "0xdAC1...1ec7:0x2". Can be
+    /// repeated to query several accounts and keys.
+    #[arg(long = "query", value_parser = parse_query, required = true)]
+    queries: Vec<(Address, Option<B256>)>,
+
+    /// Generate a RISC Zero proof of the proven values.
+    #[arg(long)]
+    prove: bool,
+
+    /// Optional segment limit po2
+    #[arg(long, env)]
+    segment_po2: Option<u32>,
+}
+
+/// Parses an address optionally followed by a colon and a storage key, given as a number.
+fn parse_query(s: &str) -> Result<(Address, Option<B256>), String> {
+    let (address, key) = match s.split_once(':') {
+        Some((address, key)) => {
+            let key = key.parse::<U256>().map_err(|err| format!("invalid storage key: {err}"))?;
+            (address, Some(B256::from(key)))
+        }
+        None => (s, None),
+    };
+    let address = address.parse().map_err(|err| format!("invalid address: {err}"))?;
+
+    Ok((address, key))
+}
+
+#[derive(Subcommand, Debug, PartialEq, Eq)]
+enum ImageIdCommand {
+    /// Compare the embedded and supplied guest ELFs against the manifest of expected image IDs.
+    Check(ImageIdCheckCommand),
+
+    /// Print the shipped manifest with the image IDs of the embedded guest ELFs set for the
+    /// release of this CLI. This is used to update `image-ids.json` from the Docker builds of a
+    /// release.
+    Manifest,
+}
+
+#[derive(Parser, Debug, PartialEq, Eq)]
+struct ImageIdCheckCommand {
+    /// Only check the guest of this chain. Required when checking a supplied `--guest-elf`.
+    #[arg(long)]
+    chain: Option<NamedChain>,
+
+    /// Release tag to check against [default: the version of this CLI]
+    #[arg(long)]
+    release: Option<String>,
+
+    /// Manifest file to use instead of the one shipped with this CLI.
+    #[arg(long)]
+    manifest: Option<PathBuf>,
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt::init();
@@ -74,38 +291,460 @@ async fn main() -> anyhow::Result<()> {
 
     let cli = Cli::parse();
 
+    if let Commands::ImageId(ImageIdCommand::Check(check)) = &cli.command {
+        return image_id_check(check, cli.guest_elf.as_deref());
+    }
+    if let Commands::ImageId(ImageIdCommand::Manifest) = &cli.command {
+        let mut manifest = Manifest::embedded()?;
+        manifest.insert_embedded(&current_release())?;
+        println!("{}", serde_json::to_string_pretty(&manifest)?);
+        return Ok(());
+    }
+    if let Commands::ProveChunk(cmd) = &cli.command {
+        return run_prove_chunk(&cli.cache_dir, cli.block, cmd).await;
+    }
+    if let Commands::Aggregate(cmd) = &cli.command {
+        return run_aggregate(&cli.cache_dir, cli.block, cmd).await;
+    }
+    let eth_rpc_url = cli.eth_rpc_url.clone().context("--eth-rpc-url is required")?;
+
     // ensure the cache directory exists
     fs::create_dir_all(&cli.cache_dir).context("failed to create cache directory")?;
 
     // set up the provider and processor
-    let provider = ProviderBuilder::new().connect(&cli.eth_rpc_url).await?;
-    let processor = BlockProcessor::new(Arc::new(provider)).await?;
+    let provider = ProviderBuilder::new().connect(&eth_rpc_url).await?;
+    let chain_id = provider.get_chain_id().await.context("eth_chainId failed")?;
+    if NamedChain::try_from(chain_id).is_ok_and(|chain| chain.is_optimism()) {
+        return run_op(cli, &eth_rpc_url).await;
+    }
+    let mut processor = BlockProcessor::new(Arc::new(provider)).await?;
+    println!("Current chain: {}", processor.chain());
+
+    if let Some(path) = &cli.guest_elf {
+        let elf = load_guest_elf(path, processor.chain(), cli.allow_unknown_guest_elf)
+            .context("refusing to use the guest ELF, pass --allow-unknown-guest-elf to override")?;
+        processor = processor.with_guest_elf(elf);
+    }
+
+    if let Commands::Call(call) = cli.command {
+        return run_call(&processor, cli.block, call).await;
+    }
+    if let Commands::HeaderChain(header_chain) = cli.command {
+        return run_header_chain(&processor, cli.block, header_chain).await;
+    }
+    if let Commands::StorageProof(storage_proof) = cli.command {
+        return run_storage_proof(&processor, cli.block, storage_proof).await;
+    }
+
+    let source = processor.witness_source(cli.witness_source, &cli.cache_dir);
+    let input = match &cli.payload {
+        Some(path) => get_payload_input(&processor, path, &cli.cache_dir, source.as_ref()).await?,
+        None => get_cached_input(&processor, cli.block, &cli.cache_dir, source.as_ref()).await?,
+    };
+    let block_hash = input.block.hash_slow();
+
+    println!(
+        "Input for block {} ({}): {:.3} MB",
+        input.block.number,
+        block_hash,
+        input_size(&input.block, &input.witness, InputFormat::default())? as f64 / 1e6
+    );
+
+    // always validate
+    processor.validate(input.clone()).context("host validation failed")?;
+    println!("Host validation successful");
+
+    match cli.command {
+        Commands::Execute => {
+            report_cycles(
+                block_hash,
+                |format| input_size(&input.block, &input.witness, format),
+                async |format| processor.execute(input.clone(), format).await,
+            )
+            .await?;
+        }
+        Commands::Prove(ProveCommand { segment_po2 }) => {
+            let (receipt, image_id) =
+                processor.prove(input, segment_po2).await.context("proving failed")?;
+            receipt.verify(image_id).context("proof verification failed")?;
+
+            let proven_hash =
+                B256::try_from(receipt.journal.as_ref()).context("failed to decode journal")?;
+            ensure!(proven_hash == block_hash, "journal output mismatch");
+        }
+        Commands::ProveChunked(ProveChunkedCommand { chunk_size, concurrency, segment_po2 }) => {
+            let (receipt, image_id) = processor
+                .prove_chunked(input, chunk_size, concurrency, segment_po2)
+                .await
+                .context("proving failed")?;
+            receipt.verify(image_id).context("proof verification failed")?;
+            check_aggregation_journal(&receipt, chain_id, block_hash)?;
+        }
+        Commands::Split(SplitCommand { chunk_size }) => {
+            let chunked = processor.split_chunks(input, chunk_size).await?;
+            let chunks = chunked.chunks.len();
+            for (index, (input, witness)) in chunked.chunks.into_iter().enumerate() {
+                let chunk = ChunkFile { chain_id, input, witness };
+                write_json(&chunk_file(&cli.cache_dir, block_hash, index), &chunk)?;
+            }
+            let aggregation = AggregationFile { chain_id, chunks, input: chunked.aggregation };
+            write_json(&aggregation_file(&cli.cache_dir, block_hash), &aggregation)?;
+            println!("Split block {block_hash} into {chunks} chunks in {:?}", cli.cache_dir);
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Runs the commands for an OP-stack chain.
+async fn run_op(cli: Cli, rpc_url: &str) -> anyhow::Result<()> {
+    ensure!(
+        !matches!(
+            cli.command,
+            Commands::Call(_)
+                | Commands::ProveChunked(_)
+                | Commands::Split(_)
+                | Commands::HeaderChain(_)
+                | Commands::StorageProof(_)
+        ),
+        "command is not supported for OP-stack chains"
+    );
+    ensure!(cli.payload.is_none(), "--payload is not supported for OP-stack chains");
+
+    let provider = ProviderBuilder::new().network::<Optimism>().connect(rpc_url).await?;
+    let mut processor = OpBlockProcessor::new(Arc::new(provider)).await?;
     println!("Current chain: {}", processor.chain());
 
-    let input = get_cached_input(&processor, cli.block, &cli.cache_dir).await?;
+    if let Some(path) = &cli.guest_elf {
+        let elf = load_guest_elf(path, processor.chain(), cli.allow_unknown_guest_elf)
+            .context("refusing to use the guest ELF, pass --allow-unknown-guest-elf to override")?;
+        processor = processor.with_guest_elf(elf);
+    }
+
+    let source = processor.witness_source(cli.witness_source, &cli.cache_dir)?;
+    let input = get_cached_op_input(&processor, cli.block, &cli.cache_dir, source.as_ref()).await?;
     let block_hash = input.block.hash_slow();
 
     println!(
         "Input for block {} ({}): {:.3} MB",
         input.block.number,
         block_hash,
-        to_zkvm_input_bytes(&input)?.len() as f64 / 1e6
+        input_size(&input.block, &input.witness, InputFormat::default())? as f64 / 1e6
     );
 
     // always validate
     processor.validate(input.clone()).context("host validation failed")?;
     println!("Host validation successful");
 
-    // create proof if requested
-    if let Commands::Prove(ProveCommand { segment_po2 }) = cli.command {
+    match cli.command {
+        Commands::Execute => {
+            report_cycles(
+                block_hash,
+                |format| input_size(&input.block, &input.witness, format),
+                async |format| processor.execute(input.clone(), format).await,
+            )
+            .await?;
+        }
+        Commands::Prove(ProveCommand { segment_po2 }) => {
+            let (receipt, image_id) =
+                processor.prove(input, segment_po2).await.context("proving failed")?;
+            receipt.verify(image_id).context("proof verification failed")?;
+
+            let proven_hash =
+                B256::try_from(receipt.journal.as_ref()).context("failed to decode journal")?;
+            ensure!(proven_hash == block_hash, "journal output mismatch");
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Executes the call against the state of the block and proves it, if requested.
+async fn run_call<P: Provider + 'static>(
+    processor: &BlockProcessor<P>,
+    block_id: BlockId,
+    call: CallCommand,
+) -> anyhow::Result<()> {
+    let request = CallRequest { from: call.from, to: call.to, value: call.value, data: call.data };
+    let input = processor.create_call_input(block_id, request).await?;
+    println!("Call input: {:.3} MB", value_size(&input)? as f64 / 1e6);
+
+    let journal = processor.validate_call(input.clone()).context("host execution failed")?;
+    println!("Call against block {} returned: {}", journal.block_hash, journal.output);
+
+    if call.prove {
+        let (receipt, image_id) =
+            processor.prove_call(input, call.segment_po2).await.context("proving failed")?;
+        receipt.verify(image_id).context("proof verification failed")?;
+
+        let proven =
+            CallJournal::decode(&receipt.journal.bytes).context("failed to decode journal")?;
+        ensure!(proven == journal, "journal output mismatch");
+        println!("Call proven with image ID {image_id}");
+    }
+
+    Ok(())
+}
+
+/// Fetches the headers from `--from` up to the block, verifies their chain and proves it, if
+/// requested.
+async fn run_header_chain<P: Provider + 'static>(
+    processor: &BlockProcessor<P>,
+    block_id: BlockId,
+    cmd: HeaderChainCommand,
+) -> anyhow::Result<()> {
+    let last = processor
+        .provider()
+        .get_block(block_id)
+        .await?
+        .with_context(|| format!("block {block_id} not found"))?
+        .header
+        .number;
+    ensure!(cmd.from <= last, "--from must not be after block {last}");
+
+    let input = processor.create_header_chain_input(cmd.from..=last, cmd.validate_headers).await?;
+    println!("Header chain input: {:.3} MB", value_size(&input)? as f64 / 1e6);
+
+    let journal =
+        processor.validate_header_chain(input.clone()).context("host validation failed")?;
+    println!(
+        "Verified {} headers from {} to {}",
+        journal.count, journal.first_hash, journal.last_hash
+    );
+
+    if cmd.prove {
         let (receipt, image_id) =
-            processor.prove(input, segment_po2).await.context("proving failed")?;
+            processor.prove_header_chain(input, cmd.segment_po2).await.context("proving failed")?;
+        receipt.verify(image_id).context("proof verification failed")?;
+
+        let proven = HeaderChainJournal::decode(&receipt.journal.bytes)
+            .context("failed to decode journal")?;
+        ensure!(proven == journal, "journal output mismatch");
+        println!("Header chain proven with image ID {image_id}");
+    }
+
+    Ok(())
+}
+
+/// Verifies the proofs of the queried accounts and storage keys and proves them, if requested.
+async fn run_storage_proof<P: Provider + 'static>(
+    processor: &BlockProcessor<P>,
+    block_id: BlockId,
+    cmd: StorageProofCommand,
+) -> anyhow::Result<()> {
+    // group the keys by account, keeping the order of the first occurrence
+    let mut queries: Vec<(Address, Vec<B256>)> = Vec::new();
+    for (address, key) in cmd.queries {
+        match queries.iter_mut().find(|(a, _)| *a == address) {
+            Some((_, keys)) => keys.extend(key),
+            None => queries.push((address, key.into_iter().collect())),
+        }
+    }
+
+    let input = processor.create_storage_proof_input(block_id, queries).await?;
+    println!("Storage proof input: {:.3} MB", value_size(&input)? as f64 / 1e6);
+
+    let journal =
+        processor.validate_storage_proofs(input.clone()).context("host validation failed")?;
+    println!("Proofs against block {}:", journal.block_hash);
+    for account in &journal.accounts {
+        println!(
+            "  {}: nonce {}, balance {}, code hash {}",
+            account.address, account.nonce, account.balance, account.code_hash
+        );
+        for (key, value) in &account.storage {
+            println!("    {key}: {value}");
+        }
+    }
+
+    if cmd.prove {
+        let (receipt, image_id) = processor
+            .prove_storage_proofs(input, cmd.segment_po2)
+            .await
+            .context("proving failed")?;
         receipt.verify(image_id).context("proof verification failed")?;
 
+        let proven = StorageProofJournal::decode(&receipt.journal.bytes)
+            .context("failed to decode journal")?;
+        ensure!(proven == journal, "journal output mismatch");
+        println!("Storage proofs proven with image ID {image_id}");
+    }
+
+    Ok(())
+}
+
+/// The input of a single chunk, as written by the `split` command.
+#[derive(Serialize, Deserialize)]
+struct ChunkFile {
+    chain_id: u64,
+    input: ChunkInput,
+    witness: ExecutionWitness,
+}
+
+/// The input of the aggregation of all chunks, as written by the `split` command.
+#[derive(Serialize, Deserialize)]
+struct AggregationFile {
+    chain_id: u64,
+    chunks: usize,
+    input: AggregationInput,
+}
+
+fn chunk_file(cache_dir: &Path, block_hash: B256, index: usize) -> PathBuf {
+    cache_dir.join(format!("chunk_{block_hash}_{index}.json"))
+}
+
+fn chunk_receipt_file(cache_dir: &Path, block_hash: B256, index: usize) -> PathBuf {
+    cache_dir.join(format!("chunk_receipt_{block_hash}_{index}.json"))
+}
+
+fn aggregation_file(cache_dir: &Path, block_hash: B256) -> PathBuf {
+    cache_dir.join(format!("aggregation_{block_hash}.json"))
+}
+
+/// Proves a chunk written by the `split` command and writes its receipt to the cache.
+async fn run_prove_chunk(
+    cache_dir: &Path,
+    block_id: BlockId,
+    cmd: &ProveChunkCommand,
+) -> anyhow::Result<()> {
+    let block_hash = block_id.as_block_hash().context("--block must be a block hash")?;
+    let chunk: ChunkFile = read_json(&chunk_file(cache_dir, block_hash, cmd.index))?;
+
+    let (receipt, image_id) =
+        prove_chunk(chunk.chain_id, chunk.input, chunk.witness, cmd.segment_po2)
+            .await
+            .context("proving failed")?;
+    receipt.verify(image_id).context("proof verification failed")?;
+    let journal: ChunkJournal = receipt.journal.decode().context("failed to decode journal")?;
+    ensure!(journal.block_hash == block_hash, "journal output mismatch");
+
+    write_json(&chunk_receipt_file(cache_dir, block_hash, cmd.index), &receipt)?;
+    println!("Chunk {} of block {block_hash} proven with image ID {image_id}", cmd.index);
+
+    Ok(())
+}
+
+/// Aggregates the receipts of all chunks written by the `prove-chunk` command.
+async fn run_aggregate(
+    cache_dir: &Path,
+    block_id: BlockId,
+    cmd: &AggregateCommand,
+) -> anyhow::Result<()> {
+    let block_hash = block_id.as_block_hash().context("--block must be a block hash")?;
+    let aggregation: AggregationFile = read_json(&aggregation_file(cache_dir, block_hash))?;
+    let chunk_receipts = (0..aggregation.chunks)
+        .map(|index| read_json(&chunk_receipt_file(cache_dir, block_hash, index)))
+        .collect::<anyhow::Result<Vec<Receipt>>>()?;
+
+    let (receipt, image_id) = prove_chunk_aggregation(
+        aggregation.chain_id,
+        aggregation.input,
+        chunk_receipts,
+        cmd.segment_po2,
+    )
+    .await
+    .context("proving failed")?;
+    receipt.verify(image_id).context("proof verification failed")?;
+    check_aggregation_journal(&receipt, aggregation.chain_id, block_hash)?;
+    println!("Block {block_hash} proven with image ID {image_id}");
+
+    Ok(())
+}
+
+/// Checks that the aggregation journal attests to the block and that it was aggregated from
+/// proofs of the embedded chunk guest.
+fn check_aggregation_journal(
+    receipt: &Receipt,
+    chain_id: u64,
+    block_hash: B256,
+) -> anyhow::Result<()> {
+    let journal =
+        AggregationJournal::decode(&receipt.journal.bytes).context("failed to decode journal")?;
+    let chunk_image_id = B256::from_slice(chunk_image_id()?.as_bytes());
+    ensure!(journal.verify(chain_id, chunk_image_id, block_hash), "journal output mismatch");
+
+    Ok(())
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> anyhow::Result<T> {
+    let f = File::open(path).with_context(|| format!("failed to open {path:?}"))?;
+    serde_json::from_reader(BufReader::new(f)).with_context(|| format!("failed to read {path:?}"))
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> anyhow::Result<()> {
+    let f = File::create(path).with_context(|| format!("failed to create {path:?}"))?;
+    serde_json::to_writer(BufWriter::new(f), value)
+        .with_context(|| format!("failed to write {path:?}"))
+}
+
+/// Executes the guest with every input format and prints the input sizes and cycle counts.
+async fn report_cycles(
+    block_hash: B256,
+    size: impl Fn(InputFormat) -> anyhow::Result<usize>,
+    execute: impl AsyncFn(InputFormat) -> anyhow::Result<SessionInfo>,
+) -> anyhow::Result<()> {
+    let change = |cycles: u64, baseline: u64| (cycles as f64 / baseline as f64 - 1.0) * 100.0;
+    let mut baseline = None;
+    for format in InputFormat::ALL {
+        let info = execute(format).await.context("execution failed")?;
         let proven_hash =
-            B256::try_from(receipt.journal.as_ref()).context("failed to decode journal")?;
+            B256::try_from(info.journal.bytes.as_slice()).context("failed to decode journal")?;
         ensure!(proven_hash == block_hash, "journal output mismatch");
+
+        let megabytes = size(format)? as f64 / 1e6;
+        let segments = info.segments.len();
+        let user_cycles: u64 = info.segments.iter().map(|segment| segment.cycles as u64).sum();
+        let total_cycles: u64 = info.segments.iter().map(|segment| 1 << segment.po2).sum();
+        // the total cycles are padded to the segment size, so smaller savings only show in the
+        // user cycles
+        let (baseline_user, baseline_total) = *baseline.get_or_insert((user_cycles, total_cycles));
+        println!(
+            "{format:?} input ({megabytes:.3} MB): {segments} segments, {user_cycles} user cycles ({:+.1}%), {total_cycles} total cycles ({:+.1}%)",
+            change(user_cycles, baseline_user),
+            change(total_cycles, baseline_total)
+        );
+    }
+
+    Ok(())
+}
+
+/// Compares the image IDs of the embedded and the supplied guest ELFs against the manifest.
+fn image_id_check(cmd: &ImageIdCheckCommand, guest_elf: Option<&Path>) -> anyhow::Result<()> {
+    let manifest = match &cmd.manifest {
+        Some(path) => Manifest::load(path)?,
+        None => Manifest::embedded()?,
+    };
+    let release = cmd.release.clone().unwrap_or_else(current_release);
+    let chains = match cmd.chain {
+        Some(chain) => vec![chain],
+        None => GUEST_CHAINS.to_vec(),
+    };
+
+    let mut images = Vec::new();
+    for chain in chains {
+        let elf = embedded_elf(chain).with_context(|| format!("no guest for chain {chain}"))?;
+        images.push((chain, "embedded", compute_image_id(elf)?));
+    }
+    if let Some(path) = guest_elf {
+        let chain = cmd.chain.context("--chain is required to check a supplied guest ELF")?;
+        let elf = fs::read(path).with_context(|| format!("failed to read guest ELF {path:?}"))?;
+        images.push((chain, "supplied", compute_image_id(&elf)?));
+    }
+
+    println!("Checking image IDs against release {release}:");
+    let mut all_match = true;
+    for (chain, kind, image_id) in images {
+        let status = match manifest.matches(&release, chain, &image_id) {
+            Some(true) => "OK",
+            Some(false) => "MISMATCH",
+            None => "MISSING IN MANIFEST",
+        };
+        all_match &= status == "OK";
+        println!("{:<16} {kind:<8} {image_id} {status}", chain.as_str());
     }
+    ensure!(all_match, "image ID check failed");
 
     Ok(())
 }
@@ -114,6 +753,7 @@ async fn get_cached_input<P: Provider>(
     processor: &BlockProcessor<P>,
     block_id: BlockId,
     cache_dir: &Path,
+    source: &dyn WitnessSource,
 ) -> anyhow::Result<StatelessInput> {
     // First, get the block header to determine the canonical hash for caching.
     let header = processor
@@ -124,22 +764,87 @@ async fn get_cached_input<P: Provider>(
         .header;
 
     let cache_file = cache_dir.join(format!("input_{}.json", header.hash));
-    let input: StatelessInput = if cache_file.exists() {
-        println!("Cache hit for block {}. Loading from file: {:?}", header.hash, &cache_file);
-        let f = File::open(&cache_file).context("failed to open file")?;
-        serde_json::from_reader(BufReader::new(f)).context("failed to read file")?
-    } else {
-        println!("Cache miss for block {}. Fetching from RPC.", header.hash);
-        let (input, _) = processor.create_input(header.hash).await?;
-
-        // Save the newly fetched input to the cache.
-        println!("Writing new input to cache: {:?}", &cache_file);
-        let f = File::create(&cache_file).context("failed to create file")?;
-        serde_json::to_writer(BufWriter::new(f), &input).context("failed to write file")?;
-
-        input
+    let input: StatelessInput = load_or_create(&cache_file, header.hash, async || {
+        Ok(processor.create_input(header.hash, source).await?.0)
+    })
+    .await?;
+    ensure!(input.block.hash_slow() == header.hash);
+
+    Ok(input)
+}
+
+async fn get_payload_input<P: Provider>(
+    processor: &BlockProcessor<P>,
+    path: &Path,
+    cache_dir: &Path,
+    source: &dyn WitnessSource,
+) -> anyhow::Result<StatelessInput> {
+    let f = File::open(path).with_context(|| format!("failed to open payload {path:?}"))?;
+    let value: serde_json::Value =
+        serde_json::from_reader(BufReader::new(f)).context("failed to read payload")?;
+    // accept both the params and the complete JSON-RPC request
+    let params = match value {
+        serde_json::Value::Object(mut request) => request.remove("params").unwrap_or_default(),
+        params => params,
     };
+    let params = serde_json::from_value(params).context("payload params must be an array")?;
+    let payload = NewPayload::from_params(params)?;
+    let block_hash = payload.block_hash();
+
+    let cache_file = cache_dir.join(format!("input_{block_hash}.json"));
+    let input: StatelessInput = load_or_create(&cache_file, block_hash, async || {
+        Ok(processor.create_input_from_payload(payload, source).await?.0)
+    })
+    .await?;
+    ensure!(input.block.hash_slow() == block_hash);
+
+    Ok(input)
+}
+
+async fn get_cached_op_input<P: Provider<Optimism>>(
+    processor: &OpBlockProcessor<P>,
+    block_id: BlockId,
+    cache_dir: &Path,
+    source: &dyn WitnessSource<OpBlock>,
+) -> anyhow::Result<OpStatelessInput> {
+    // First, get the block header to determine the canonical hash for caching.
+    let header = processor
+        .provider()
+        .get_block(block_id)
+        .await?
+        .with_context(|| format!("block {block_id} not found"))?
+        .header;
+
+    // use a different prefix, so that OP-stack inputs are never mistaken for Ethereum inputs
+    let cache_file = cache_dir.join(format!("op_input_{}.json", header.hash));
+    let input: OpStatelessInput = load_or_create(&cache_file, header.hash, async || {
+        Ok(processor.create_input(header.hash, source).await?.0)
+    })
+    .await?;
     ensure!(input.block.hash_slow() == header.hash);
 
     Ok(input)
 }
+
+/// Loads the input from the cache file, or creates it and writes it to the cache.
+async fn load_or_create<T: Serialize + DeserializeOwned>(
+    cache_file: &Path,
+    block_hash: B256,
+    create: impl AsyncFnOnce() -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    if cache_file.exists() {
+        println!("Cache hit for block {block_hash}. Loading from file: {cache_file:?}");
+        let f = File::open(cache_file).context("failed to open file")?;
+        return serde_json::from_reader(BufReader::new(f)).context("failed to read file");
+    }
+
+    println!("Cache miss for block {block_hash}. Fetching from RPC.");
+    let input = create().await?;
+
+    // Save the newly fetched input to the cache.
+    println!("Writing new input to cache: {cache_file:?}");
+    let f = File::create(cache_file).context("failed to create file")?;
+    serde_json::to_writer(BufWriter::new(f), &input).context("failed to write file")?;
+
+    Ok(input)
+}
@@ -0,0 +1,310 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use actix_web::{App, HttpResponse, HttpServer, Responder, http::StatusCode, web};
+use alloy::{
+    eips::BlockId,
+    primitives::B256,
+    providers::{DynProvider, Provider, ProviderBuilder},
+};
+use anyhow::{Context, ensure};
+use clap::Parser;
+use reth_stateless::StatelessInput;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{path::PathBuf, sync::Arc};
+use tokio::sync::{Mutex, mpsc::UnboundedReceiver};
+use tracing::{error, info, instrument};
+use tracing_actix_web::TracingLogger;
+use zeth_host::{
+    BlockProcessor,
+    image_id::load_guest_elf,
+    jobs::{Job, JobQueue, JobStatus, Journal, ProofBundle},
+    witness::{WitnessSource, WitnessSourceKind},
+};
+
+/// Proving server that creates Ethereum block execution proofs on request.
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// URL of the Ethereum RPC endpoint to connect to.
+    #[arg(long, env)]
+    eth_rpc_url: String,
+
+    /// The network address and port to bind the server to.
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    bind_address: String,
+
+    /// Directory to store the job queue, inputs and receipts.
+    #[arg(long, default_value = "./jobs")]
+    data_dir: PathBuf,
+
+    /// Number of jobs that are proven concurrently.
+    #[arg(long, default_value_t = 1)]
+    workers: usize,
+
+    /// Optional segment limit po2
+    #[arg(long, env)]
+    segment_po2: Option<u32>,
+
+    /// Guest ELF to use instead of the one embedded at build time, e.g. a release binary. Its
+    /// image ID must match the manifest of this release.
+    #[arg(long)]
+    guest_elf: Option<PathBuf>,
+
+    /// Use a `--guest-elf` even if its image ID does not match the manifest of this release.
+    #[arg(long, requires = "guest_elf")]
+    allow_unknown_guest_elf: bool,
+
+    /// Source of the execution witness. The `file` source loads `witness_<block_hash>.json` from
+    /// the `inputs` folder of the data directory.
+    #[arg(long, value_enum, default_value_t = WitnessSourceKind::Rpc)]
+    witness_source: WitnessSourceKind,
+}
+
+/// The application state shared across all handlers.
+struct AppState {
+    processor: BlockProcessor<DynProvider>,
+    witness_source: Box<dyn WitnessSource>,
+    queue: Arc<JobQueue>,
+}
+
+/// Request to prove a block fetched from the RPC endpoint.
+#[derive(Debug, Deserialize)]
+struct SubmitBlockRequest {
+    /// Block number, tag, or hash.
+    block: BlockId,
+}
+
+/// A job together with its decoded journal, if the job has succeeded.
+#[derive(Debug, Serialize)]
+struct JobResponse {
+    #[serde(flatten)]
+    job: Job,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    journal: Option<Journal>,
+}
+
+fn error_response(status: StatusCode, err: impl std::fmt::Display) -> HttpResponse {
+    HttpResponse::build(status).json(json!({ "error": err.to_string() }))
+}
+
+/// Submits a proving job for a block fetched from the RPC endpoint.
+#[instrument(skip_all, fields(block = %request.block))]
+async fn submit_block(
+    request: web::Json<SubmitBlockRequest>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    // resolve the block ID to its canonical hash first, so that identical requests are
+    // deduplicated before any input is fetched
+    let header = match data.processor.provider().get_block(request.block).await {
+        Ok(Some(block)) => block.header,
+        Ok(None) => {
+            return error_response(
+                StatusCode::NOT_FOUND,
+                format!("block {} not found", request.block),
+            );
+        }
+        Err(e) => return error_response(StatusCode::BAD_GATEWAY, e),
+    };
+    // create the job before fetching the input, so that concurrent requests for the same block
+    // neither fetch the input twice nor overwrite each other
+    match data.queue.reserve(header.hash, header.number) {
+        Ok((_, true)) => {}
+        Ok((job, false)) => return HttpResponse::Ok().json(job),
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+
+    let input = match data.processor.create_input(header.hash, data.witness_source.as_ref()).await {
+        Ok((input, _)) => input,
+        Err(e) => {
+            error!(error = format!("{e:#}"), "Failed to create input");
+            let status = JobStatus::Failed { error: format!("failed to create input: {e:#}") };
+            if let Err(e) = data.queue.set_status(&header.hash, status) {
+                error!(error = format!("{e:#}"), "Failed to update job");
+            }
+            return error_response(StatusCode::BAD_GATEWAY, format!("{e:#}"));
+        }
+    };
+    match data.queue.enqueue(&input) {
+        Ok(job) => HttpResponse::Accepted().json(job),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+/// Submits a proving job for an uploaded input file.
+async fn submit_input(
+    input: web::Json<StatelessInput>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    match data.queue.submit(&input) {
+        Ok(job) => HttpResponse::Accepted().json(job),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+/// Returns the status of a job.
+async fn job_status(path: web::Path<B256>, data: web::Data<AppState>) -> impl Responder {
+    let block_hash = path.into_inner();
+    let Some(job) = data.queue.get(&block_hash) else {
+        return error_response(StatusCode::NOT_FOUND, "job not found");
+    };
+    let journal = match job.status {
+        JobStatus::Succeeded => data.queue.bundle(&block_hash).ok().map(|bundle| bundle.journal),
+        _ => None,
+    };
+
+    HttpResponse::Ok().json(JobResponse { job, journal })
+}
+
+/// Returns the receipt and decoded journal of a successful job.
+async fn job_receipt(path: web::Path<B256>, data: web::Data<AppState>) -> impl Responder {
+    let block_hash = path.into_inner();
+    match data.queue.get(&block_hash).map(|job| job.status) {
+        Some(JobStatus::Succeeded) => {}
+        Some(_) => {
+            return error_response(StatusCode::CONFLICT, "job not succeeded");
+        }
+        None => return error_response(StatusCode::NOT_FOUND, "job not found"),
+    }
+    match data.queue.bundle(&block_hash) {
+        Ok(bundle) => HttpResponse::Ok().json(bundle),
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+/// Continuously takes jobs from the queue, then validates and proves them.
+async fn worker(
+    id: usize,
+    processor: BlockProcessor<DynProvider>,
+    queue: Arc<JobQueue>,
+    receiver: Arc<Mutex<UnboundedReceiver<B256>>>,
+    segment_po2: Option<u32>,
+) {
+    loop {
+        let Some(block_hash) = receiver.lock().await.recv().await else {
+            break;
+        };
+        // skip jobs that have been queued more than once or are already claimed by another worker
+        match queue.claim(&block_hash) {
+            Ok(Some(_)) => {}
+            Ok(None) => continue,
+            Err(e) => {
+                error!(error = format!("{e:#}"), "Failed to update job");
+                continue;
+            }
+        }
+        info!(worker = id, %block_hash, "Starting job");
+
+        let status = match prove_job(&processor, &queue, block_hash, segment_po2).await {
+            Ok(()) => JobStatus::Succeeded,
+            Err(e) => {
+                error!(worker = id, %block_hash, error = format!("{e:#}"), "Job failed");
+                JobStatus::Failed { error: format!("{e:#}") }
+            }
+        };
+        info!(worker = id, %block_hash, ?status, "Finished job");
+        if let Err(e) = queue.set_status(&block_hash, status) {
+            error!(error = format!("{e:#}"), "Failed to update job");
+        }
+    }
+}
+
+async fn prove_job(
+    processor: &BlockProcessor<DynProvider>,
+    queue: &JobQueue,
+    block_hash: B256,
+    segment_po2: Option<u32>,
+) -> anyhow::Result<()> {
+    let input = queue.input(&block_hash)?;
+    ensure!(input.block.hash_slow() == block_hash, "input does not match job");
+
+    // the host validation executes the whole block, so it must not block the async runtime
+    let validator = processor.clone();
+    let input =
+        tokio::task::spawn_blocking(move || validator.validate(input.clone()).map(|_| input))
+            .await?
+            .context("host validation failed")?;
+    let (receipt, image_id) =
+        processor.prove(input, segment_po2).await.context("proving failed")?;
+    receipt.verify(image_id).context("proof verification failed")?;
+
+    let proven_hash =
+        B256::try_from(receipt.journal.as_ref()).context("failed to decode journal")?;
+    ensure!(proven_hash == block_hash, "journal output mismatch");
+
+    let bundle = ProofBundle {
+        image_id: B256::from_slice(image_id.as_bytes()),
+        journal: Journal { block_hash: proven_hash },
+        receipt,
+    };
+    queue.store_bundle(&block_hash, &bundle)
+}
+
+#[actix_web::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt::init();
+
+    // This is a hack to ensure that `blst` gets linked into this binary.
+    let _ = unsafe { blst::blst_p1_sizeof() };
+
+    let args = Args::parse();
+    ensure!(args.workers > 0, "at least one worker is required");
+
+    let provider = ProviderBuilder::new().connect(&args.eth_rpc_url).await?.erased();
+    let mut processor = BlockProcessor::new(provider).await?;
+    info!("Current chain: {}", processor.chain());
+    if let Some(path) = &args.guest_elf {
+        let elf = load_guest_elf(path, processor.chain(), args.allow_unknown_guest_elf)
+            .context("refusing to use the guest ELF, pass --allow-unknown-guest-elf to override")?;
+        processor = processor.with_guest_elf(elf);
+    }
+
+    let (queue, receiver) = JobQueue::open(&args.data_dir).context("failed to open job queue")?;
+    let queue = Arc::new(queue);
+    let receiver = Arc::new(Mutex::new(receiver));
+
+    for id in 0..args.workers {
+        actix_web::rt::spawn(worker(
+            id,
+            processor.clone(),
+            queue.clone(),
+            receiver.clone(),
+            args.segment_po2,
+        ));
+    }
+
+    let witness_source =
+        processor.witness_source(args.witness_source, &args.data_dir.join("inputs"));
+    let app_state = web::Data::new(AppState { processor, witness_source, queue });
+
+    info!(bind_address = %args.bind_address, workers = args.workers, "Starting proving server");
+
+    HttpServer::new(move || {
+        App::new()
+            .app_data(app_state.clone())
+            // inputs of large blocks can easily exceed the default limit
+            .app_data(web::JsonConfig::default().limit(1 << 30))
+            .wrap(TracingLogger::default())
+            .route("/jobs", web::post().to(submit_block))
+            .route("/jobs/input", web::post().to(submit_input))
+            .route("/jobs/{block_hash}", web::get().to(job_status))
+            .route("/jobs/{block_hash}/receipt", web::get().to(job_receipt))
+    })
+    .bind(args.bind_address)?
+    .run()
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,144 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Expected image IDs of the released guest programs.
+//!
+//! The manifest maps each release tag to the image IDs of the Docker-built guest binaries of that
+//! release, indexed by chain name, e.g. `{"v0.3.0": {"mainnet": "<image id>"}}`. Image IDs are
+//! stored as hex strings, the same format that is used in the names of the release assets.
+
+use alloy_chains::NamedChain;
+use anyhow::{Context, Result, bail};
+use guests::{
+    BASE_ELF, BASE_SEPOLIA_ELF, HOLESKY_ELF, MAINNET_ELF, OPTIMISM_ELF, OPTIMISM_SEPOLIA_ELF,
+    SEPOLIA_ELF,
+};
+use risc0_zkvm::{Digest, compute_image_id};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path};
+use tracing::{info, warn};
+
+/// The manifest shipped with this version of the host.
+const EMBEDDED_MANIFEST: &str = include_str!("../image-ids.json");
+
+/// All chains for which a guest program is available.
+pub const GUEST_CHAINS: [NamedChain; 7] = [
+    NamedChain::Mainnet,
+    NamedChain::Sepolia,
+    NamedChain::Holesky,
+    NamedChain::Optimism,
+    NamedChain::OptimismSepolia,
+    NamedChain::Base,
+    NamedChain::BaseSepolia,
+];
+
+/// Returns the release tag corresponding to the version of this crate.
+pub fn current_release() -> String {
+    format!("v{}", env!("CARGO_PKG_VERSION"))
+}
+
+/// Returns the guest ELF embedded at build time for the given chain.
+pub fn embedded_elf(chain: NamedChain) -> Option<&'static [u8]> {
+    match chain {
+        NamedChain::Mainnet => Some(MAINNET_ELF),
+        NamedChain::Sepolia => Some(SEPOLIA_ELF),
+        NamedChain::Holesky => Some(HOLESKY_ELF),
+        NamedChain::Optimism => Some(OPTIMISM_ELF),
+        NamedChain::OptimismSepolia => Some(OPTIMISM_SEPOLIA_ELF),
+        NamedChain::Base => Some(BASE_ELF),
+        NamedChain::BaseSepolia => Some(BASE_SEPOLIA_ELF),
+        _ => None,
+    }
+}
+
+/// Expected image IDs per release and chain.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Manifest(BTreeMap<String, BTreeMap<String, String>>);
+
+impl Manifest {
+    /// Returns the manifest that was shipped with this crate.
+    pub fn embedded() -> Result<Self> {
+        serde_json::from_str(EMBEDDED_MANIFEST).context("invalid embedded image ID manifest")
+    }
+
+    /// Loads a manifest from the given file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path).with_context(|| format!("failed to read {path:?}"))?;
+        serde_json::from_str(&json).with_context(|| format!("invalid manifest {path:?}"))
+    }
+
+    /// Returns the expected image ID for the given release and chain.
+    pub fn expected(&self, release: &str, chain: NamedChain) -> Option<&str> {
+        self.0.get(release)?.get(chain.as_str()).map(String::as_str)
+    }
+
+    /// Returns whether the image ID matches the expected one for the given release and chain.
+    ///
+    /// This returns `None`, if the manifest contains no image ID for that release and chain.
+    pub fn matches(&self, release: &str, chain: NamedChain, image_id: &Digest) -> Option<bool> {
+        let expected = self.expected(release, chain)?;
+        let expected = expected.strip_prefix("0x").unwrap_or(expected);
+
+        Some(expected.eq_ignore_ascii_case(&image_id.to_string()))
+    }
+
+    /// Sets the expected image IDs of the given release to the ones of the embedded guest ELFs.
+    ///
+    /// This must only be used with the reproducible Docker builds of a release.
+    pub fn insert_embedded(&mut self, release: &str) -> Result<()> {
+        let mut image_ids = BTreeMap::new();
+        for chain in GUEST_CHAINS {
+            let elf = embedded_elf(chain).with_context(|| format!("no guest for chain {chain}"))?;
+            image_ids.insert(chain.as_str().to_string(), compute_image_id(elf)?.to_string());
+        }
+        self.0.insert(release.to_string(), image_ids);
+
+        Ok(())
+    }
+}
+
+/// Checks the image ID of a guest for the chain against the embedded manifest of this release.
+///
+/// An error is returned, if the image ID does not match or the manifest has no entry for it.
+pub fn check_release(chain: NamedChain, image_id: &Digest) -> Result<()> {
+    let release = current_release();
+    match Manifest::embedded()?.matches(&release, chain, image_id) {
+        Some(true) => Ok(()),
+        Some(false) => {
+            bail!("image ID {image_id} does not match the {chain} guest of release {release}")
+        }
+        None => bail!("no image ID for the {chain} guest of release {release} in the manifest"),
+    }
+}
+
+/// Loads a guest ELF for the chain from a file, e.g. a binary of an official release.
+///
+/// The image ID of the ELF must match the manifest of this release, unless `allow_unknown` is set.
+/// The ELF is leaked, as it is used for the lifetime of the process.
+pub fn load_guest_elf(
+    path: &Path,
+    chain: NamedChain,
+    allow_unknown: bool,
+) -> Result<&'static [u8]> {
+    let elf = fs::read(path).with_context(|| format!("failed to read guest ELF {path:?}"))?;
+    let image_id = compute_image_id(&elf).context("failed to compute image id")?;
+    match check_release(chain, &image_id) {
+        Ok(()) => info!(?path, %image_id, "Using guest ELF"),
+        Err(e) if allow_unknown => warn!(?path, error = %e, "Using unknown guest ELF"),
+        Err(e) => return Err(e.context(format!("unknown guest ELF {path:?}"))),
+    }
+
+    Ok(elf.leak())
+}
@@ -0,0 +1,369 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A persistent, deduplicating queue of proving jobs.
+//!
+//! Every job is identified by the hash of the block it proves, so that identical requests are
+//! always mapped to the same job. All state is kept as plain files in a single directory:
+//! - `jobs/<hash>.json`: the job metadata and status,
+//! - `inputs/input_<hash>.json`: the [StatelessInput], using the same format as the CLI cache,
+//! - `receipts/<hash>.json`: the resulting [ProofBundle].
+
+use alloy::primitives::B256;
+use anyhow::{Context, Result};
+use reth_stateless::StatelessInput;
+use risc0_zkvm::Receipt;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::mpsc;
+
+/// A single proving job.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Job {
+    /// Hash of the block to prove, this also serves as the job ID.
+    pub block_hash: B256,
+    /// Number of the block to prove.
+    pub block_number: u64,
+    /// Current status of the job.
+    #[serde(flatten)]
+    pub status: JobStatus,
+    /// UNIX timestamp of when the job was first submitted.
+    pub created_at: u64,
+    /// UNIX timestamp of the last status change.
+    pub updated_at: u64,
+}
+
+/// The status of a [Job].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum JobStatus {
+    /// The input of the job is being fetched.
+    Preparing,
+    /// The job is waiting for a free worker.
+    Queued,
+    /// The job is currently being validated and proven.
+    Running,
+    /// The proof has been generated successfully.
+    Succeeded,
+    /// Validation or proving failed.
+    Failed { error: String },
+}
+
+/// The decoded journal of the stateless-client guest.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Journal {
+    /// Hash of the validated block.
+    pub block_hash: B256,
+}
+
+/// The result of a successful [Job].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProofBundle {
+    /// Image ID of the guest program that generated the proof.
+    pub image_id: B256,
+    /// Decoded journal of the receipt.
+    pub journal: Journal,
+    /// The RISC Zero receipt.
+    pub receipt: Receipt,
+}
+
+/// A persistent job queue backed by the local file system.
+pub struct JobQueue {
+    dir: PathBuf,
+    jobs: Mutex<HashMap<B256, Job>>,
+    sender: mpsc::UnboundedSender<B256>,
+}
+
+impl JobQueue {
+    /// Opens the job queue located in `dir`, creating it if it does not exist.
+    ///
+    /// All unfinished jobs from a previous run are queued again and will be returned by the
+    /// receiver.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<(Self, mpsc::UnboundedReceiver<B256>)> {
+        let dir = dir.into();
+        for sub_dir in ["jobs", "inputs", "receipts"] {
+            fs::create_dir_all(dir.join(sub_dir))
+                .with_context(|| format!("failed to create {sub_dir} directory"))?;
+        }
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        let mut jobs = HashMap::new();
+        for entry in fs::read_dir(dir.join("jobs")).context("failed to read jobs directory")? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let mut job: Job =
+                read_json(&path).with_context(|| format!("failed to load job {path:?}"))?;
+            // jobs that were interrupted by a restart must be started from scratch, jobs without
+            // a complete input must be submitted again
+            match job.status {
+                JobStatus::Queued | JobStatus::Running => {
+                    job.status = JobStatus::Queued;
+                    sender.send(job.block_hash)?;
+                }
+                JobStatus::Preparing => {
+                    let error = "interrupted while preparing the input".to_string();
+                    job.status = JobStatus::Failed { error };
+                }
+                JobStatus::Succeeded | JobStatus::Failed { .. } => {}
+            }
+            jobs.insert(job.block_hash, job);
+        }
+
+        Ok((Self { dir, jobs: Mutex::new(jobs), sender }, receiver))
+    }
+
+    /// Returns the job for the given block hash, if it exists.
+    pub fn get(&self, block_hash: &B256) -> Option<Job> {
+        self.jobs.lock().unwrap().get(block_hash).cloned()
+    }
+
+    /// Returns the job for the given block hash, if it exists and did not fail.
+    pub fn get_active(&self, block_hash: &B256) -> Option<Job> {
+        self.get(block_hash).filter(|job| !matches!(job.status, JobStatus::Failed { .. }))
+    }
+
+    /// Creates a job for the given block in the [JobStatus::Preparing] state.
+    ///
+    /// If a job for the same block already exists and has not failed, no new job is created and
+    /// the existing one is returned instead, together with `false`. Failed jobs are reset. This
+    /// is used to deduplicate requests before fetching the input, which must then be passed to
+    /// [JobQueue::enqueue].
+    pub fn reserve(&self, block_hash: B256, block_number: u64) -> Result<(Job, bool)> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let active =
+            jobs.get(&block_hash).filter(|job| !matches!(job.status, JobStatus::Failed { .. }));
+        if let Some(job) = active {
+            return Ok((job.clone(), false));
+        }
+
+        let now = unix_timestamp();
+        let job = jobs.entry(block_hash).or_insert_with(|| Job {
+            block_hash,
+            block_number,
+            status: JobStatus::Preparing,
+            created_at: now,
+            updated_at: now,
+        });
+        job.status = JobStatus::Preparing;
+        job.updated_at = now;
+        let job = job.clone();
+        self.persist(&job)?;
+
+        Ok((job, true))
+    }
+
+    /// Stores the input of a job reserved with [JobQueue::reserve] and queues the job.
+    ///
+    /// The input of a previous, failed attempt is overwritten.
+    pub fn enqueue(&self, input: &StatelessInput) -> Result<Job> {
+        let block_hash = input.block.hash_slow();
+        write_json(&self.input_path(&block_hash), input).context("failed to write input")?;
+
+        let job = self.transition(&block_hash, &JobStatus::Preparing, JobStatus::Queued)?;
+        let job = job.with_context(|| format!("job {block_hash} is not being prepared"))?;
+        self.sender.send(block_hash).context("job queue closed")?;
+
+        Ok(job)
+    }
+
+    /// Submits a new job for the given input.
+    ///
+    /// If a job for the same block already exists and has not failed, no new job is created and
+    /// the existing one is returned instead. Failed jobs are queued again with the new input.
+    pub fn submit(&self, input: &StatelessInput) -> Result<Job> {
+        let (job, created) = self.reserve(input.block.hash_slow(), input.block.number)?;
+        if !created {
+            return Ok(job);
+        }
+        self.enqueue(input)
+    }
+
+    /// Claims a queued job for execution by setting it to [JobStatus::Running].
+    ///
+    /// Returns `None` if the job is not queued, e.g. because it has already been claimed.
+    pub fn claim(&self, block_hash: &B256) -> Result<Option<Job>> {
+        self.transition(block_hash, &JobStatus::Queued, JobStatus::Running)
+    }
+
+    /// Updates the status of an existing job.
+    pub fn set_status(&self, block_hash: &B256, status: JobStatus) -> Result<Job> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.get_mut(block_hash).with_context(|| format!("unknown job {block_hash}"))?;
+        job.status = status;
+        job.updated_at = unix_timestamp();
+        self.persist(job)?;
+
+        Ok(job.clone())
+    }
+
+    /// Atomically updates the status of an existing job, if it currently is `from`.
+    fn transition(
+        &self,
+        block_hash: &B256,
+        from: &JobStatus,
+        to: JobStatus,
+    ) -> Result<Option<Job>> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.get_mut(block_hash).with_context(|| format!("unknown job {block_hash}"))?;
+        if &job.status != from {
+            return Ok(None);
+        }
+        job.status = to;
+        job.updated_at = unix_timestamp();
+        self.persist(job)?;
+
+        Ok(Some(job.clone()))
+    }
+
+    /// Loads the input of the given job.
+    pub fn input(&self, block_hash: &B256) -> Result<StatelessInput> {
+        read_json(&self.input_path(block_hash)).context("failed to read input")
+    }
+
+    /// Stores the proof bundle of the given job.
+    pub fn store_bundle(&self, block_hash: &B256, bundle: &ProofBundle) -> Result<()> {
+        write_json(&self.bundle_path(block_hash), bundle).context("failed to write receipt")
+    }
+
+    /// Loads the proof bundle of the given job.
+    pub fn bundle(&self, block_hash: &B256) -> Result<ProofBundle> {
+        read_json(&self.bundle_path(block_hash)).context("failed to read receipt")
+    }
+
+    /// Writes the job to disk, this must be called while holding the lock of the jobs.
+    fn persist(&self, job: &Job) -> Result<()> {
+        let path = self.dir.join("jobs").join(format!("{}.json", job.block_hash));
+        write_json(&path, job).context("failed to write job")
+    }
+
+    fn input_path(&self, block_hash: &B256) -> PathBuf {
+        self.dir.join("inputs").join(format!("input_{block_hash}.json"))
+    }
+
+    fn bundle_path(&self, block_hash: &B256) -> PathBuf {
+        self.dir.join("receipts").join(format!("{block_hash}.json"))
+    }
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let f = File::open(path)?;
+    Ok(serde_json::from_reader(BufReader::new(f))?)
+}
+
+/// Writes the value to a temporary file first, so that a crash never leaves a partial file.
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let tmp_path = path.with_extension("json.tmp");
+    let mut writer = BufWriter::new(File::create(&tmp_path)?);
+    serde_json::to_writer(&mut writer, value)?;
+    writer.flush()?;
+    fs::rename(&tmp_path, path)?;
+
+    Ok(())
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_ethereum_primitives::Block;
+    use reth_stateless::ExecutionWitness;
+
+    fn input(number: u64) -> StatelessInput {
+        let mut block = Block::default();
+        block.header.number = number;
+        StatelessInput { block, witness: ExecutionWitness::default() }
+    }
+
+    #[test]
+    fn submit_deduplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let (queue, mut receiver) = JobQueue::open(dir.path()).unwrap();
+        let input = input(1);
+        let block_hash = input.block.hash_slow();
+
+        let job = queue.submit(&input).unwrap();
+        assert_eq!(job.status, JobStatus::Queued);
+        assert_eq!(queue.submit(&input).unwrap().created_at, job.created_at);
+        assert_eq!(receiver.try_recv().unwrap(), block_hash);
+        assert!(receiver.try_recv().is_err());
+
+        // a job can only be claimed once and is not reset while it is running
+        assert!(queue.claim(&block_hash).unwrap().is_some());
+        assert!(queue.claim(&block_hash).unwrap().is_none());
+        let (job, created) = queue.reserve(block_hash, 1).unwrap();
+        assert!(!created);
+        assert_eq!(job.status, JobStatus::Running);
+    }
+
+    #[test]
+    fn resubmit_failed() {
+        let dir = tempfile::tempdir().unwrap();
+        let (queue, mut receiver) = JobQueue::open(dir.path()).unwrap();
+        let block_hash = input(1).block.hash_slow();
+
+        let (_, created) = queue.reserve(block_hash, 1).unwrap();
+        assert!(created);
+        queue.set_status(&block_hash, JobStatus::Failed { error: "failed".into() }).unwrap();
+
+        // the input of a failed job is replaced on resubmission
+        let mut input = input(1);
+        input.witness.codes.push(vec![0x00].into());
+        assert_eq!(queue.submit(&input).unwrap().status, JobStatus::Queued);
+        assert_eq!(queue.input(&block_hash).unwrap().witness.codes, input.witness.codes);
+        assert_eq!(receiver.try_recv().unwrap(), block_hash);
+    }
+
+    #[test]
+    fn reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let (queued, running, preparing, succeeded) = (input(1), input(2), input(3), input(4));
+        {
+            let (queue, _) = JobQueue::open(dir.path()).unwrap();
+            queue.submit(&queued).unwrap();
+            queue.submit(&running).unwrap();
+            queue.claim(&running.block.hash_slow()).unwrap().unwrap();
+            queue.reserve(preparing.block.hash_slow(), 3).unwrap();
+            queue.submit(&succeeded).unwrap();
+            queue.set_status(&succeeded.block.hash_slow(), JobStatus::Succeeded).unwrap();
+        }
+
+        let (queue, mut receiver) = JobQueue::open(dir.path()).unwrap();
+        let status = |input: &StatelessInput| queue.get(&input.block.hash_slow()).unwrap().status;
+        assert_eq!(status(&queued), JobStatus::Queued);
+        assert_eq!(status(&running), JobStatus::Queued);
+        assert!(matches!(status(&preparing), JobStatus::Failed { .. }));
+        assert_eq!(status(&succeeded), JobStatus::Succeeded);
+
+        let mut requeued = vec![receiver.try_recv().unwrap(), receiver.try_recv().unwrap()];
+        requeued.sort();
+        let mut expected = vec![queued.block.hash_slow(), running.block.hash_slow()];
+        expected.sort();
+        assert_eq!(requeued, expected);
+        assert!(receiver.try_recv().is_err());
+        assert_eq!(queue.input(&running.block.hash_slow()).unwrap().block, running.block);
+    }
+}
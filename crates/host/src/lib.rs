@@ -14,33 +14,70 @@
 
 use alloy::{
     eips::BlockId,
-    primitives::B256,
-    providers::{Provider, ext::DebugApi},
-    rpc::types::debug::ExecutionWitness,
+    primitives::{Address, B256},
+    providers::Provider,
+    rpc::types::Block as RpcBlock,
 };
 use alloy_chains::NamedChain;
+use alloy_consensus::Header;
 use anyhow::{Context, Result, bail};
-use guests::{HOLESKY_ELF, MAINNET_ELF, SEPOLIA_ELF};
-use reth_chainspec::{ChainSpec, EthChainSpec};
-use reth_stateless::StatelessInput;
-use risc0_zkvm::{Digest, ExecutorEnvBuilder, Receipt, compute_image_id, default_prover};
-use std::sync::Arc;
+use futures::{StreamExt, TryStreamExt};
+use guests::{
+    CALL_ELF, CHUNK_AGGREGATION_ELF, CHUNK_ELF, HEADER_CHAIN_ELF, PROPOSAL_ELF, STORAGE_PROOF_ELF,
+};
+use payload::NewPayload;
+use reth_chainspec::EthChainSpec;
+use reth_ethereum_primitives::Block;
+use reth_stateless::{ExecutionWitness, StatelessInput};
+use risc0_zkvm::{
+    Digest, ExecutorEnv, ExecutorEnvBuilder, Receipt, SessionInfo, compute_image_id,
+    default_executor, default_prover,
+};
+use serde::Serialize;
+use std::{num::NonZeroUsize, ops::RangeInclusive, path::Path, sync::Arc};
+use witness::{
+    FileWitnessSource, PreflightWitnessSource, RpcWitnessSource, WitnessSource, WitnessSourceKind,
+};
+use zeth_chainspec::ChainSpec;
+use zeth_core::{
+    call::{CallInput, CallJournal, CallRequest},
+    chunk::{AggregationInput, ChunkInput, ChunkedBlock, split_block},
+    header_chain::{HeaderChainInput, HeaderChainJournal},
+    input::{InputFormat, InputWriter, write_input},
+    proposal::BlockProposal,
+    storage_proof::{AccountProof, StorageProof, StorageProofInput, StorageProofJournal},
+};
+
+pub mod image_id;
+pub mod jobs;
+pub mod optimism;
+pub mod payload;
+pub mod witness;
+
+/// Maximum number of requests sent in a single JSON-RPC batch when fetching headers.
+const HEADER_BATCH_SIZE: u64 = 100;
 
 /// Processes Ethereum blocks, including creating inputs, validating, and proving.
 pub struct BlockProcessor<P> {
     /// The provider for fetching data from the Ethereum network.
     provider: Arc<P>,
-    /// The chain specification.
+    /// The chain specification, this is the same instance that is used by the guest.
     chain_spec: Arc<ChainSpec>,
+    /// Guest ELF to use instead of the embedded one.
+    guest_elf: Option<&'static [u8]>,
 }
 
 impl<P> Clone for BlockProcessor<P> {
     fn clone(&self) -> Self {
-        Self { provider: Arc::clone(&self.provider), chain_spec: Arc::clone(&self.chain_spec) }
+        Self {
+            provider: Arc::clone(&self.provider),
+            chain_spec: Arc::clone(&self.chain_spec),
+            guest_elf: self.guest_elf,
+        }
     }
 }
 
-impl<P: Provider + DebugApi> BlockProcessor<P> {
+impl<P: Provider> BlockProcessor<P> {
     /// Creates a new BlockProcessor.
     ///
     /// This will make a network call to determine the chain ID and select the appropriate chain
@@ -49,14 +86,23 @@ impl<P: Provider + DebugApi> BlockProcessor<P> {
         let chain_id = provider.get_chain_id().await.context("eth_chainId failed")?;
         let chain = chain_id.try_into().context("invalid chain ID")?;
         let chain_spec = match chain {
-            NamedChain::Mainnet => reth_chainspec::MAINNET.clone(),
-            NamedChain::Sepolia => reth_chainspec::SEPOLIA.clone(),
-            NamedChain::Holesky => reth_chainspec::HOLESKY.clone(),
-            NamedChain::Hoodi => reth_chainspec::HOODI.clone(),
+            NamedChain::Mainnet => zeth_chainspec::MAINNET.clone(),
+            NamedChain::Sepolia => zeth_chainspec::SEPOLIA.clone(),
+            NamedChain::Holesky => zeth_chainspec::HOLESKY.clone(),
+            NamedChain::Hoodi => zeth_chainspec::HOODI.clone(),
             chain => bail!("unsupported chain: {chain}"),
         };
 
-        Ok(Self { provider: provider.into(), chain_spec })
+        Ok(Self { provider: provider.into(), chain_spec, guest_elf: None })
+    }
+
+    /// Uses the given guest ELF for proving instead of the one embedded at build time.
+    ///
+    /// The ELF must be built for the chain of the provider, e.g. a binary of an official release.
+    /// An ELF loaded at runtime is typically leaked, as it is used for the lifetime of the process.
+    pub fn with_guest_elf(mut self, elf: &'static [u8]) -> Self {
+        self.guest_elf = Some(elf);
+        self
     }
 
     /// Returns the underlying provider.
@@ -64,6 +110,11 @@ impl<P: Provider + DebugApi> BlockProcessor<P> {
         &self.provider
     }
 
+    /// Returns the chain specification.
+    pub fn chain_spec(&self) -> &Arc<ChainSpec> {
+        &self.chain_spec
+    }
+
     /// Returns the named chain identifier.
     pub fn chain(&self) -> NamedChain {
         // This unwrap is safe because the constructor ensures a valid named chain.
@@ -71,20 +122,37 @@ impl<P: Provider + DebugApi> BlockProcessor<P> {
     }
 
     /// Returns the guest program ELF and its corresponding image ID for the current chain.
+    ///
+    /// This is either the ELF supplied with [BlockProcessor::with_guest_elf] or the one embedded
+    /// at build time.
     pub fn elf(&self) -> Result<(&'static [u8], Digest)> {
-        let elf = match self.chain() {
-            NamedChain::Mainnet => MAINNET_ELF,
-            NamedChain::Sepolia => SEPOLIA_ELF,
-            NamedChain::Holesky => HOLESKY_ELF,
-            chain => bail!("unsupported chain for proving: {chain}"),
-        };
-        let image_id = compute_image_id(elf).context("failed to compute image id")?;
+        load_elf(self.guest_elf, self.chain())
+    }
 
-        Ok((elf, image_id))
+    /// Returns the [WitnessSource] of the given kind.
+    ///
+    /// The `dir` is only used by [WitnessSourceKind::File] to locate the witness files.
+    pub fn witness_source(&self, kind: WitnessSourceKind, dir: &Path) -> Box<dyn WitnessSource>
+    where
+        P: 'static,
+    {
+        match kind {
+            WitnessSourceKind::Rpc => Box::new(RpcWitnessSource::new(self.provider.clone())),
+            WitnessSourceKind::Preflight => Box::new(PreflightWitnessSource::new(
+                self.provider.clone(),
+                self.chain_spec.clone(),
+            )),
+            WitnessSourceKind::File => Box::new(FileWitnessSource::new(dir)),
+        }
     }
 
-    /// Fetches the necessary data from the RPC endpoint to create the StatelessInput.
-    pub async fn create_input(&self, block: impl Into<BlockId>) -> Result<(StatelessInput, B256)> {
+    /// Fetches the block from the RPC endpoint and the witness from the given source to create the
+    /// StatelessInput.
+    pub async fn create_input(
+        &self,
+        block: impl Into<BlockId>,
+        source: &dyn WitnessSource,
+    ) -> Result<(StatelessInput, B256)> {
         let block_id = block.into();
         let rpc_block = self
             .provider
@@ -92,21 +160,31 @@ impl<P: Provider + DebugApi> BlockProcessor<P> {
             .full()
             .await?
             .with_context(|| format!("block {block_id} not found"))?;
-        let witness = self.provider.debug_execution_witness(rpc_block.number().into()).await?;
         let block_hash = rpc_block.header.hash_slow();
+        let block: Block = rpc_block.into();
+        let witness = source.witness(&block, block_hash).await?;
 
-        Ok((
-            StatelessInput {
-                block: rpc_block.into(),
-                witness: ExecutionWitness {
-                    state: witness.state,
-                    codes: witness.codes,
-                    keys: vec![], // keys are not used
-                    headers: witness.headers,
-                },
-            },
-            block_hash,
-        ))
+        Ok((StatelessInput { block, witness }, block_hash))
+    }
+
+    /// Converts the Engine API payload into a block and fetches its witness from the given source
+    /// to create the StatelessInput.
+    ///
+    /// The RPC witness source queries the witness by block hash, so it only works once the node
+    /// knows the payload. The preflight source executes the payload itself on top of the state of
+    /// its parent, but still needs the node to know the block, if the payload creates or removes
+    /// accounts or storage slots. To prove any payload before it is published, its witness must be
+    /// supplied with [WitnessSourceKind::File].
+    pub async fn create_input_from_payload(
+        &self,
+        payload: NewPayload,
+        source: &dyn WitnessSource,
+    ) -> Result<(StatelessInput, B256)> {
+        let block_hash = payload.block_hash();
+        let block = payload.try_into_block()?;
+        let witness = source.witness(&block, block_hash).await?;
+
+        Ok((StatelessInput { block, witness }, block_hash))
     }
 
     /// Validates the block execution on the host machine.
@@ -126,29 +204,465 @@ impl<P: Provider + DebugApi> BlockProcessor<P> {
         po2: Option<u32>,
     ) -> Result<(Receipt, Digest)> {
         let (elf, image_id) = self.elf()?;
+        let receipt = prove_block(elf, input.block, input.witness, po2).await?;
+
+        Ok((receipt, image_id))
+    }
+
+    /// Executes the guest program without proving, passing the input in the given format.
+    ///
+    /// This is useful to measure the cycle count of a block.
+    pub async fn execute(&self, input: StatelessInput, format: InputFormat) -> Result<SessionInfo> {
+        let (elf, _) = self.elf()?;
+        execute_block(elf, input.block, input.witness, format).await
+    }
+
+    /// Executes the call against the state of the given block using the preflight of
+    /// `zeth-rpc-proxy` and creates the input of the call guest.
+    pub async fn create_call_input(
+        &self,
+        block: impl Into<BlockId>,
+        request: CallRequest,
+    ) -> Result<CallInput>
+    where
+        P: 'static,
+    {
+        let evm_config = zeth_core::EthEvmConfig::new(self.chain_spec.clone());
+        zeth_rpc_proxy::call_input(evm_config, &self.provider, block.into(), request).await
+    }
+
+    /// Executes the call on the host machine, using only the data of the input.
+    pub fn validate_call(&self, input: CallInput) -> Result<CallJournal> {
+        let config = zeth_core::EthEvmConfig::new(self.chain_spec.clone());
+        let journal = zeth_core::call::execute_call(input, config)?;
+
+        Ok(journal)
+    }
+
+    /// Generates a RISC Zero proof of the call using the call guest.
+    ///
+    /// The call guest is shared by all chains and ignores the ELF of
+    /// [BlockProcessor::with_guest_elf].
+    pub async fn prove_call(
+        &self,
+        input: CallInput,
+        po2: Option<u32>,
+    ) -> Result<(Receipt, Digest)> {
+        prove_shared(CALL_ELF, po2, move |env_builder| {
+            env_builder.write(&input)?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Executes the proposed block on the host machine and returns the resulting header.
+    ///
+    /// The witness is the one of the proposed block, see [Self::prove_proposal].
+    pub fn validate_proposal(
+        &self,
+        proposal: BlockProposal,
+        witness: ExecutionWitness,
+    ) -> Result<Header> {
+        let config = zeth_core::EthEvmConfig::new(self.chain_spec.clone());
+        let header = zeth_core::proposal::execute_proposal(proposal, witness.into(), config)?;
+
+        Ok(header)
+    }
+
+    /// Generates a RISC Zero proof of the header resulting from the proposed block.
+    ///
+    /// As the block is not sealed, its witness cannot be fetched with `debug_executionWitness` or
+    /// the preflight, which both need the block to be known to the node. Instead, the builder
+    /// records it while executing the transactions on the state of the parent, e.g. with
+    /// `zeth_exex::execution_witness` on a reth node, using the header from
+    /// [zeth_core::proposal::execute_proposal] or any header with the same parent, number,
+    /// timestamp and attributes.
+    pub async fn prove_proposal(
+        &self,
+        proposal: BlockProposal,
+        witness: ExecutionWitness,
+        po2: Option<u32>,
+    ) -> Result<(Receipt, Digest)> {
+        let chain_id = self.chain_spec.chain_id();
+        let witness = without_keys(witness);
+        prove_shared(PROPOSAL_ELF, po2, move |env_builder| {
+            env_builder.write(&chain_id)?;
+            write_input(&mut EnvWriter(env_builder), &proposal, &witness, InputFormat::default())
+        })
+        .await
+    }
 
-        // prove in a blocking thread using the default prover
-        let info = tokio::task::spawn_blocking(move || {
-            let mut env_builder = ExecutorEnvBuilder::default();
-            if let Some(po2) = po2 {
-                env_builder.segment_limit_po2(po2);
+    /// Splits the block into chunks of at most `chunk_size` transactions and creates the inputs of
+    /// all chunks and of their aggregation by executing the block on the host.
+    pub async fn split_chunks(
+        &self,
+        input: StatelessInput,
+        chunk_size: NonZeroUsize,
+    ) -> Result<ChunkedBlock> {
+        let config = zeth_core::EthEvmConfig::new(self.chain_spec.clone());
+        let witness = without_keys(input.witness);
+        let chunked = tokio::task::spawn_blocking(move || {
+            split_block(input.block, witness, config, chunk_size)
+        })
+        .await
+        .context("chunking task panicked")??;
+
+        Ok(chunked)
+    }
+
+    /// Proves the block in chunks of at most `chunk_size` transactions and aggregates the chunk
+    /// proofs into a single proof of the block.
+    ///
+    /// Since the chunks do not depend on each other's proofs, up to `concurrency` chunks are
+    /// proven at the same time.
+    pub async fn prove_chunked(
+        &self,
+        input: StatelessInput,
+        chunk_size: NonZeroUsize,
+        concurrency: usize,
+        po2: Option<u32>,
+    ) -> Result<(Receipt, Digest)> {
+        let chunked = self.split_chunks(input, chunk_size).await?;
+        let chain_id = self.chain_spec.chain_id();
+
+        let chunk_receipts = futures::stream::iter(chunked.chunks)
+            .map(|(chunk, witness)| prove_chunk(chain_id, chunk, witness, po2))
+            .buffered(concurrency.max(1))
+            .map_ok(|(receipt, _)| receipt)
+            .try_collect()
+            .await?;
+
+        prove_chunk_aggregation(chain_id, chunked.aggregation, chunk_receipts, po2).await
+    }
+
+    /// Fetches the headers of the given range of blocks and creates the input of the header
+    /// chain guest.
+    ///
+    /// The headers are requested in JSON-RPC batches of `eth_getBlockByNumber` calls, so that long
+    /// chains only take a few round trips.
+    pub async fn create_header_chain_input(
+        &self,
+        blocks: RangeInclusive<u64>,
+        validate: bool,
+    ) -> Result<HeaderChainInput> {
+        let mut headers = Vec::with_capacity(blocks.clone().count());
+        for start in blocks.clone().step_by(HEADER_BATCH_SIZE as usize) {
+            let end = (start + HEADER_BATCH_SIZE - 1).min(*blocks.end());
+
+            let mut batch = self.provider.client().new_batch();
+            let waiters = (start..=end)
+                .map(|number| {
+                    let params = (alloy::eips::BlockNumberOrTag::Number(number), false);
+                    batch.add_call::<_, Option<RpcBlock>>("eth_getBlockByNumber", &params)
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            batch.send().await.context("eth_getBlockByNumber batch failed")?;
+
+            for (number, waiter) in (start..=end).zip(waiters) {
+                let block = waiter.await?.with_context(|| format!("block {number} not found"))?;
+                headers.push(alloy_rlp::encode(&block.header.inner).into());
             }
-            let env = env_builder.write(&input)?.build()?;
-            default_prover().prove(env, elf)
+        }
+
+        Ok(HeaderChainInput { chain_id: self.chain_spec.chain_id(), headers, validate })
+    }
+
+    /// Verifies the header chain on the host machine.
+    pub fn validate_header_chain(&self, input: HeaderChainInput) -> Result<HeaderChainJournal> {
+        let journal =
+            zeth_core::header_chain::validate_header_chain(input, self.chain_spec.clone())?;
+
+        Ok(journal)
+    }
+
+    /// Generates a RISC Zero proof of the header chain using the header chain guest.
+    pub async fn prove_header_chain(
+        &self,
+        input: HeaderChainInput,
+        po2: Option<u32>,
+    ) -> Result<(Receipt, Digest)> {
+        prove_shared(HEADER_CHAIN_ELF, po2, move |env_builder| {
+            env_builder.write(&input)?;
+            Ok(())
         })
         .await
-        .context("proving task panicked")??;
+    }
+
+    /// Fetches the EIP-1186 proofs of the given accounts and storage keys at the given block and
+    /// creates the input of the storage proof guest.
+    pub async fn create_storage_proof_input(
+        &self,
+        block: impl Into<BlockId>,
+        queries: Vec<(Address, Vec<B256>)>,
+    ) -> Result<StorageProofInput> {
+        let block_id = block.into();
+        let rpc_block = self
+            .provider
+            .get_block(block_id)
+            .await?
+            .with_context(|| format!("block {block_id} not found"))?;
+        let block_hash = rpc_block.header.hash;
+
+        let proofs = queries.into_iter().map(async |(address, keys)| {
+            let proof = self
+                .provider
+                .get_proof(address, keys)
+                .hash(block_hash)
+                .await
+                .context("eth_getProof failed")?;
+            let storage = proof
+                .storage_proof
+                .into_iter()
+                .map(|slot| StorageProof { key: slot.key.as_b256(), proof: slot.proof })
+                .collect();
+            Ok::<_, anyhow::Error>(AccountProof { address, proof: proof.account_proof, storage })
+        });
+        let accounts = futures::future::try_join_all(proofs).await?;
+
+        Ok(StorageProofInput {
+            header: alloy_rlp::encode(&rpc_block.header.inner).into(),
+            accounts,
+        })
+    }
+
+    /// Verifies the account and storage proofs on the host machine.
+    pub fn validate_storage_proofs(&self, input: StorageProofInput) -> Result<StorageProofJournal> {
+        let journal = zeth_core::storage_proof::verify_storage_proofs(input)?;
+
+        Ok(journal)
+    }
+
+    /// Generates a RISC Zero proof of the account and storage values using the storage proof
+    /// guest.
+    pub async fn prove_storage_proofs(
+        &self,
+        input: StorageProofInput,
+        po2: Option<u32>,
+    ) -> Result<(Receipt, Digest)> {
+        prove_shared(STORAGE_PROOF_ELF, po2, move |env_builder| {
+            env_builder.write(&input)?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// Proves a single chunk of a block, as created by [BlockProcessor::split_chunks].
+pub async fn prove_chunk(
+    chain_id: u64,
+    chunk: ChunkInput,
+    witness: ExecutionWitness,
+    po2: Option<u32>,
+) -> Result<(Receipt, Digest)> {
+    prove_shared(CHUNK_ELF, po2, move |env_builder| {
+        env_builder.write(&chain_id)?;
+        write_input(&mut EnvWriter(env_builder), &chunk, &witness, InputFormat::default())
+    })
+    .await
+}
+
+/// Proves the aggregation of the chunks of a block from the receipts of all chunks.
+pub async fn prove_chunk_aggregation(
+    chain_id: u64,
+    aggregation: AggregationInput,
+    chunk_receipts: Vec<Receipt>,
+    po2: Option<u32>,
+) -> Result<(Receipt, Digest)> {
+    let chunk_image_id = chunk_image_id()?;
+    prove_shared(CHUNK_AGGREGATION_ELF, po2, move |env_builder| {
+        for receipt in chunk_receipts {
+            env_builder.add_assumption(receipt);
+        }
+        env_builder.write(&chain_id)?.write(&chunk_image_id)?.write(&aggregation)?;
+        Ok(())
+    })
+    .await
+}
+
+/// Returns the image ID of the chunk guest.
+///
+/// The aggregation guest accepts proofs of any chunk guest, so the image ID committed to its
+/// journal must always be checked against this one.
+pub fn chunk_image_id() -> Result<Digest> {
+    compute_image_id(CHUNK_ELF).context("failed to compute image id")
+}
+
+/// Proves a guest shared by all chains, writing its input with the given closure.
+async fn prove_shared(
+    elf: &'static [u8],
+    po2: Option<u32>,
+    write: impl FnOnce(&mut ExecutorEnvBuilder<'static>) -> Result<()> + Send + 'static,
+) -> Result<(Receipt, Digest)> {
+    let image_id = compute_image_id(elf).context("failed to compute image id")?;
+    let info = tokio::task::spawn_blocking(move || {
+        let mut env_builder = ExecutorEnv::builder();
+        if let Some(po2) = po2 {
+            env_builder.segment_limit_po2(po2);
+        }
+        write(&mut env_builder)?;
+        default_prover().prove(env_builder.build()?, elf)
+    })
+    .await
+    .context("proving task panicked")??;
 
-        Ok((info.receipt, image_id))
+    Ok((info.receipt, image_id))
+}
+
+/// Returns the supplied guest ELF, or the one embedded for the chain, and its image ID.
+fn load_elf(
+    guest_elf: Option<&'static [u8]>,
+    chain: NamedChain,
+) -> Result<(&'static [u8], Digest)> {
+    let elf = match guest_elf.or_else(|| image_id::embedded_elf(chain)) {
+        Some(elf) => elf,
+        None => bail!("unsupported chain for proving: {chain}"),
+    };
+    let image_id = compute_image_id(elf).context("failed to compute image id")?;
+
+    Ok((elf, image_id))
+}
+
+/// Proves the guest with the given block and witness on a blocking thread.
+async fn prove_block<B: Serialize + Send + 'static>(
+    elf: &'static [u8],
+    block: B,
+    witness: ExecutionWitness,
+    po2: Option<u32>,
+) -> Result<Receipt> {
+    let witness = without_keys(witness);
+    // prove in a blocking thread using the default prover
+    let info = tokio::task::spawn_blocking(move || {
+        let env = build_env(&block, &witness, InputFormat::default(), po2)?;
+        default_prover().prove(env, elf)
+    })
+    .await
+    .context("proving task panicked")??;
+
+    Ok(info.receipt)
+}
+
+/// Executes the guest with the given block and witness on a blocking thread.
+async fn execute_block<B: Serialize + Send + 'static>(
+    elf: &'static [u8],
+    block: B,
+    witness: ExecutionWitness,
+    format: InputFormat,
+) -> Result<SessionInfo> {
+    let witness = without_keys(witness);
+    let info = tokio::task::spawn_blocking(move || {
+        let env = build_env(&block, &witness, format, None)?;
+        default_executor().execute(env, elf)
+    })
+    .await
+    .context("execution task panicked")??;
+
+    Ok(info)
+}
+
+/// Removes the key preimages from the witness.
+///
+/// They are kept in the cached inputs for debugging, but the guest does not need them.
+fn without_keys(mut witness: ExecutionWitness) -> ExecutionWitness {
+    witness.keys = vec![];
+    witness
+}
+
+/// Writes values to the standard input of the guest.
+struct EnvWriter<'a, 'b>(&'a mut ExecutorEnvBuilder<'b>);
+
+impl InputWriter for EnvWriter<'_, '_> {
+    type Error = anyhow::Error;
+
+    fn write<T: Serialize>(&mut self, value: &T) -> Result<()> {
+        self.0.write(value)?;
+        Ok(())
     }
 }
 
+/// Builds the executor environment for the guest with the input in the given format.
+fn build_env<B: Serialize>(
+    block: &B,
+    witness: &ExecutionWitness,
+    format: InputFormat,
+    po2: Option<u32>,
+) -> Result<ExecutorEnv<'static>> {
+    let mut env_builder = ExecutorEnv::builder();
+    if let Some(po2) = po2 {
+        env_builder.segment_limit_po2(po2);
+    }
+    write_input(&mut EnvWriter(&mut env_builder), block, witness, format)?;
+
+    env_builder.build()
+}
+
 /// Serializes the StatelessInput into a byte slice suitable for the RISC Zero ZKVM.
 ///
-/// The ZKVM guest expects aligned words, and this function handles the conversion
-/// from a struct to a raw byte vector.
+/// The input is written with [write_input] in the [InputFormat::Buffered] format, including the
+/// leading format, so that it can be read by the current guests.
+#[deprecated(note = "use `zeth_core::input::write_input` to write the guest input instead")]
 pub fn to_zkvm_input_bytes(input: &StatelessInput) -> Result<Vec<u8>> {
-    let words = risc0_zkvm::serde::to_vec(input)?;
-    let bytes = bytemuck::cast_slice(words.as_slice());
-    Ok(bytes.to_vec())
+    let mut bytes = InputBytes::default();
+    write_input(
+        &mut bytes,
+        &input.block,
+        &without_keys(input.witness.clone()),
+        InputFormat::Buffered,
+    )?;
+
+    Ok(bytes.0)
+}
+
+/// Collects the bytes written to the standard input of the guest.
+#[derive(Debug, Default)]
+struct InputBytes(Vec<u8>);
+
+impl InputWriter for InputBytes {
+    type Error = anyhow::Error;
+
+    fn write<T: Serialize>(&mut self, value: &T) -> Result<()> {
+        let words = risc0_zkvm::serde::to_vec(value)?;
+        self.0.extend(words.iter().flat_map(|word| word.to_le_bytes()));
+        Ok(())
+    }
+}
+
+/// Counts the bytes written to the standard input of the guest, without keeping them.
+#[derive(Debug, Default)]
+pub struct InputSize(pub usize);
+
+impl InputWriter for InputSize {
+    type Error = anyhow::Error;
+
+    fn write<T: Serialize>(&mut self, value: &T) -> Result<()> {
+        // the executor environment serializes every value into words
+        self.0 += risc0_zkvm::serde::to_vec(value)?.len() * size_of::<u32>();
+        Ok(())
+    }
+}
+
+/// Returns the number of bytes of the guest input of the block and its witness in the given
+/// format, as written by [write_input].
+///
+/// The key preimages of the witness are not counted, as they are never sent to the guest.
+pub fn input_size<B: Serialize>(
+    block: &B,
+    witness: &ExecutionWitness,
+    format: InputFormat,
+) -> Result<usize> {
+    let mut size = InputSize::default();
+    if format == InputFormat::Buffered && !witness.keys.is_empty() {
+        write_input(&mut size, block, &without_keys(witness.clone()), format)?;
+    } else {
+        write_input(&mut size, block, witness, format)?;
+    }
+
+    Ok(size.0)
+}
+
+/// Returns the number of bytes of a guest input that is written as a single value.
+pub fn value_size<T: Serialize>(value: &T) -> Result<usize> {
+    let mut size = InputSize::default();
+    size.write(value)?;
+
+    Ok(size.0)
 }
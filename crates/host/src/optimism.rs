@@ -0,0 +1,173 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Processing of OP-stack L2 blocks.
+
+use crate::{
+    execute_block, load_elf, prove_block,
+    witness::{FileWitnessSource, RpcWitnessSource, WitnessSource, WitnessSourceKind},
+};
+use alloy::{eips::BlockId, primitives::B256, providers::Provider};
+use alloy_chains::NamedChain;
+use anyhow::{Context, Result, bail};
+use op_alloy_network::Optimism;
+use reth_chainspec::EthChainSpec;
+use risc0_zkvm::{Digest, Receipt, SessionInfo};
+use std::{path::Path, sync::Arc};
+use zeth_chainspec::optimism::OpChainSpec;
+use zeth_core::{
+    input::InputFormat,
+    optimism::{OpBlock, OpEvmConfig, OpStatelessInput},
+};
+
+/// Processes OP-stack blocks, including creating inputs, validating, and proving.
+///
+/// This is the counterpart of [BlockProcessor](crate::BlockProcessor) for OP-stack chains.
+pub struct OpBlockProcessor<P> {
+    /// The provider for fetching data from the L2 network.
+    provider: Arc<P>,
+    /// The chain specification, this is the same instance that is used by the guest.
+    chain_spec: Arc<OpChainSpec>,
+    /// Guest ELF to use instead of the embedded one.
+    guest_elf: Option<&'static [u8]>,
+}
+
+impl<P> Clone for OpBlockProcessor<P> {
+    fn clone(&self) -> Self {
+        Self {
+            provider: Arc::clone(&self.provider),
+            chain_spec: Arc::clone(&self.chain_spec),
+            guest_elf: self.guest_elf,
+        }
+    }
+}
+
+impl<P: Provider<Optimism>> OpBlockProcessor<P> {
+    /// Creates a new OpBlockProcessor.
+    ///
+    /// This will make a network call to determine the chain ID and select the appropriate chain
+    /// specification.
+    pub async fn new(provider: P) -> Result<Self> {
+        let chain_id = provider.get_chain_id().await.context("eth_chainId failed")?;
+        let chain = chain_id.try_into().context("invalid chain ID")?;
+        let chain_spec = match chain {
+            NamedChain::Optimism => zeth_chainspec::optimism::OP_MAINNET.clone(),
+            NamedChain::OptimismSepolia => zeth_chainspec::optimism::OP_SEPOLIA.clone(),
+            NamedChain::Base => zeth_chainspec::optimism::BASE_MAINNET.clone(),
+            NamedChain::BaseSepolia => zeth_chainspec::optimism::BASE_SEPOLIA.clone(),
+            chain => bail!("unsupported chain: {chain}"),
+        };
+
+        Ok(Self { provider: provider.into(), chain_spec, guest_elf: None })
+    }
+
+    /// Uses the given guest ELF for proving instead of the one embedded at build time.
+    pub fn with_guest_elf(mut self, elf: &'static [u8]) -> Self {
+        self.guest_elf = Some(elf);
+        self
+    }
+
+    /// Returns the underlying provider.
+    pub fn provider(&self) -> &P {
+        &self.provider
+    }
+
+    /// Returns the chain specification.
+    pub fn chain_spec(&self) -> &Arc<OpChainSpec> {
+        &self.chain_spec
+    }
+
+    /// Returns the named chain identifier.
+    pub fn chain(&self) -> NamedChain {
+        // This unwrap is safe because the constructor ensures a valid named chain.
+        self.chain_spec.chain().named().unwrap()
+    }
+
+    /// Returns the guest program ELF and its corresponding image ID for the current chain.
+    pub fn elf(&self) -> Result<(&'static [u8], Digest)> {
+        load_elf(self.guest_elf, self.chain())
+    }
+
+    /// Returns the [WitnessSource] of the given kind.
+    ///
+    /// The preflight of `zeth-rpc-proxy` is not supported for OP-stack chains.
+    pub fn witness_source(
+        &self,
+        kind: WitnessSourceKind,
+        dir: &Path,
+    ) -> Result<Box<dyn WitnessSource<OpBlock>>>
+    where
+        P: 'static,
+    {
+        match kind {
+            WitnessSourceKind::Rpc => Ok(Box::new(RpcWitnessSource::new(self.provider.clone()))),
+            WitnessSourceKind::File => Ok(Box::new(FileWitnessSource::new(dir))),
+            WitnessSourceKind::Preflight => bail!("preflight is not supported for OP-stack chains"),
+        }
+    }
+
+    /// Fetches the block from the RPC endpoint and the witness from the given source to create the
+    /// OpStatelessInput.
+    pub async fn create_input(
+        &self,
+        block: impl Into<BlockId>,
+        source: &dyn WitnessSource<OpBlock>,
+    ) -> Result<(OpStatelessInput, B256)> {
+        let block_id = block.into();
+        let rpc_block = self
+            .provider
+            .get_block(block_id)
+            .full()
+            .await?
+            .with_context(|| format!("block {block_id} not found"))?;
+        let block_hash = rpc_block.header.hash;
+        let block: OpBlock =
+            rpc_block.into_consensus().map_transactions(|tx| tx.inner.into_inner());
+        let witness = source.witness(&block, block_hash).await?;
+
+        Ok((OpStatelessInput { block, witness }, block_hash))
+    }
+
+    /// Validates the block execution on the host machine.
+    pub fn validate(&self, input: OpStatelessInput) -> Result<B256> {
+        let config = OpEvmConfig::optimism(self.chain_spec.clone());
+        let hash = zeth_core::optimism::validate_op_block(input.block, input.witness, config)?;
+
+        Ok(hash)
+    }
+
+    /// Generates a RISC Zero proof of block execution.
+    ///
+    /// This method is computationally intensive and is run on a blocking thread.
+    pub async fn prove(
+        &self,
+        input: OpStatelessInput,
+        po2: Option<u32>,
+    ) -> Result<(Receipt, Digest)> {
+        let (elf, image_id) = self.elf()?;
+        let receipt = prove_block(elf, input.block, input.witness, po2).await?;
+
+        Ok((receipt, image_id))
+    }
+
+    /// Executes the guest program without proving, passing the input in the given format.
+    pub async fn execute(
+        &self,
+        input: OpStatelessInput,
+        format: InputFormat,
+    ) -> Result<SessionInfo> {
+        let (elf, _) = self.elf()?;
+        execute_block(elf, input.block, input.witness, format).await
+    }
+}
@@ -0,0 +1,206 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Conversion of Engine API execution payloads into blocks.
+
+use alloy::{
+    consensus::Transaction,
+    eips::eip7685::Requests,
+    primitives::B256,
+    rpc::types::engine::{
+        CancunPayloadFields, ExecutionPayload, ExecutionPayloadSidecar, PraguePayloadFields,
+    },
+};
+use anyhow::{Context, Result, bail, ensure};
+use reth_ethereum_primitives::Block;
+use serde::{Deserialize, Serialize};
+
+/// The parameters of an `engine_newPayload` call, i.e. the execution payload together with the
+/// fields that are passed alongside it since Cancun and Prague.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewPayload {
+    /// The execution payload of any version.
+    pub execution_payload: ExecutionPayload,
+    /// The versioned hashes of all blobs of the block, required since Cancun.
+    pub expected_blob_versioned_hashes: Option<Vec<B256>>,
+    /// The root of the parent beacon block, required since Cancun.
+    pub parent_beacon_block_root: Option<B256>,
+    /// The execution requests of the block, required since Prague.
+    pub execution_requests: Option<Requests>,
+}
+
+impl NewPayload {
+    /// Parses the JSON `params` array of an `engine_newPayloadV1` to `engine_newPayloadV4` call.
+    pub fn from_params(params: Vec<serde_json::Value>) -> Result<Self> {
+        let mut params = params.into_iter();
+        let mut next = || params.next().unwrap_or_default();
+
+        let execution_payload =
+            serde_json::from_value(next()).context("invalid execution payload")?;
+        let expected_blob_versioned_hashes =
+            serde_json::from_value(next()).context("invalid versioned hashes")?;
+        let parent_beacon_block_root =
+            serde_json::from_value(next()).context("invalid parent beacon block root")?;
+        let execution_requests =
+            serde_json::from_value(next()).context("invalid execution requests")?;
+        ensure!(next().is_null(), "too many parameters");
+
+        Ok(Self {
+            execution_payload,
+            expected_blob_versioned_hashes,
+            parent_beacon_block_root,
+            execution_requests,
+        })
+    }
+
+    /// Returns the hash of the block claimed by the payload.
+    pub fn block_hash(&self) -> B256 {
+        self.execution_payload.block_hash()
+    }
+
+    /// Converts the payload into a block, checking its block hash and the versioned hashes of its
+    /// blob transactions.
+    ///
+    /// Like for `engine_newPayload`, the version of the fields must match, e.g. the execution
+    /// requests can only be given together with the Cancun fields.
+    pub fn try_into_block(self) -> Result<Block> {
+        let sidecar = match (self.parent_beacon_block_root, self.expected_blob_versioned_hashes) {
+            (Some(parent_beacon_block_root), Some(versioned_hashes)) => {
+                let cancun = CancunPayloadFields { parent_beacon_block_root, versioned_hashes };
+                match self.execution_requests {
+                    Some(requests) => {
+                        ExecutionPayloadSidecar::v4(cancun, PraguePayloadFields::new(requests))
+                    }
+                    None => ExecutionPayloadSidecar::v3(cancun),
+                }
+            }
+            (None, None) if self.execution_requests.is_none() => ExecutionPayloadSidecar::none(),
+            _ => bail!("invalid combination of payload fields"),
+        };
+
+        let block_hash = self.execution_payload.block_hash();
+        let block: Block = self
+            .execution_payload
+            .try_into_block_with_sidecar(&sidecar)
+            .context("invalid execution payload")?;
+        ensure!(block.header.hash_slow() == block_hash, "block hash mismatch");
+
+        if let Some(expected) = sidecar.versioned_hashes() {
+            let versioned_hashes: Vec<B256> = block
+                .body
+                .transactions
+                .iter()
+                .filter_map(|tx| tx.blob_versioned_hashes())
+                .flatten()
+                .copied()
+                .collect();
+            ensure!(&versioned_hashes == expected, "blob versioned hashes mismatch");
+        }
+
+        Ok(block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::consensus::{EMPTY_OMMER_ROOT_HASH, EMPTY_ROOT_HASH, Header};
+    use reth_ethereum_primitives::BlockBody;
+
+    /// Returns an empty block with the header fields of the given `engine_newPayload` version.
+    fn block(version: u8) -> Block {
+        let mut header = Header {
+            ommers_hash: EMPTY_OMMER_ROOT_HASH,
+            transactions_root: EMPTY_ROOT_HASH,
+            receipts_root: EMPTY_ROOT_HASH,
+            number: 1,
+            gas_limit: 30_000_000,
+            timestamp: 12,
+            base_fee_per_gas: Some(1),
+            ..Default::default()
+        };
+        let mut body = BlockBody::default();
+        if version >= 2 {
+            header.withdrawals_root = Some(EMPTY_ROOT_HASH);
+            body.withdrawals = Some(Default::default());
+        }
+        if version >= 3 {
+            header.blob_gas_used = Some(0);
+            header.excess_blob_gas = Some(0);
+            header.parent_beacon_block_root = Some(B256::repeat_byte(1));
+        }
+        if version >= 4 {
+            header.requests_hash = Some(Requests::default().requests_hash());
+        }
+
+        Block { header, body }
+    }
+
+    fn new_payload(block: &Block, version: u8) -> NewPayload {
+        let (execution_payload, _) = ExecutionPayload::from_block_slow(block);
+        NewPayload {
+            execution_payload,
+            expected_blob_versioned_hashes: (version >= 3).then(Vec::new),
+            parent_beacon_block_root: block.header.parent_beacon_block_root,
+            execution_requests: (version >= 4).then(Requests::default),
+        }
+    }
+
+    #[test]
+    fn all_versions() {
+        for version in 1..=4 {
+            let block = block(version);
+            let payload = new_payload(&block, version);
+            assert_eq!(payload.block_hash(), block.hash_slow());
+
+            // the payload survives the JSON-RPC params of its version
+            let params = [
+                serde_json::to_value(&payload.execution_payload).unwrap(),
+                serde_json::to_value(&payload.expected_blob_versioned_hashes).unwrap(),
+                serde_json::to_value(payload.parent_beacon_block_root).unwrap(),
+                serde_json::to_value(&payload.execution_requests).unwrap(),
+            ];
+            let params = params.into_iter().take_while(|param| !param.is_null()).collect();
+            assert_eq!(NewPayload::from_params(params).unwrap(), payload);
+
+            assert_eq!(payload.try_into_block().unwrap(), block, "version {version}");
+        }
+    }
+
+    #[test]
+    fn block_hash_mismatch() {
+        let mut payload = new_payload(&block(3), 3);
+        payload.parent_beacon_block_root = Some(B256::repeat_byte(2));
+        let err = payload.try_into_block().unwrap_err();
+        assert_eq!(err.to_string(), "block hash mismatch");
+    }
+
+    #[test]
+    fn versioned_hashes_mismatch() {
+        let mut payload = new_payload(&block(3), 3);
+        payload.expected_blob_versioned_hashes = Some(vec![B256::repeat_byte(2)]);
+        let err = payload.try_into_block().unwrap_err();
+        assert_eq!(err.to_string(), "blob versioned hashes mismatch");
+    }
+
+    #[test]
+    fn invalid_fields() {
+        let block = block(4);
+        let mut payload = new_payload(&block, 4);
+        payload.expected_blob_versioned_hashes = None;
+        payload.parent_beacon_block_root = None;
+        assert!(payload.try_into_block().is_err());
+    }
+}
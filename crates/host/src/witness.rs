@@ -0,0 +1,128 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy::{network::Network, primitives::B256, providers::Provider};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use op_alloy_network::Optimism;
+use reth_ethereum_primitives::Block;
+use reth_stateless::ExecutionWitness;
+use std::{fs::File, io::BufReader, path::PathBuf, sync::Arc};
+use zeth_chainspec::ChainSpec;
+use zeth_core::optimism::OpBlock;
+
+/// A source of [ExecutionWitness] data for a block.
+///
+/// The block type defaults to Ethereum blocks, sources for OP-stack chains use [OpBlock].
+#[async_trait]
+pub trait WitnessSource<B: Sync = Block>: Send + Sync {
+    /// Returns the execution witness for the given block.
+    async fn witness(&self, block: &B, block_hash: B256) -> Result<ExecutionWitness>;
+}
+
+/// The available [WitnessSource] implementations.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum WitnessSourceKind {
+    /// Query `debug_executionWitnessByBlockHash` from the RPC endpoint.
+    #[default]
+    Rpc,
+    /// Generate the witness locally using only standard RPC methods of an archive node.
+    Preflight,
+    /// Load the witness from a file `witness_<block_hash>.json`.
+    File,
+}
+
+/// Witness source using the `debug_executionWitnessByBlockHash` RPC method of the upstream node.
+pub struct RpcWitnessSource<P> {
+    provider: Arc<P>,
+}
+
+impl<P> RpcWitnessSource<P> {
+    pub fn new(provider: Arc<P>) -> Self {
+        Self { provider }
+    }
+}
+
+#[async_trait]
+impl<P: Provider> WitnessSource for RpcWitnessSource<P> {
+    async fn witness(&self, _: &Block, block_hash: B256) -> Result<ExecutionWitness> {
+        debug_execution_witness(self.provider.as_ref(), block_hash).await
+    }
+}
+
+#[async_trait]
+impl<P: Provider<Optimism>> WitnessSource<OpBlock> for RpcWitnessSource<P> {
+    async fn witness(&self, _: &OpBlock, block_hash: B256) -> Result<ExecutionWitness> {
+        debug_execution_witness(self.provider.as_ref(), block_hash).await
+    }
+}
+
+/// Queries `debug_executionWitnessByBlockHash` for the given block.
+///
+/// The witness is requested by hash, so that it always matches the block fetched before, even if
+/// a reorg replaced the block at that height in the meantime.
+async fn debug_execution_witness<N: Network, P: Provider<N>>(
+    provider: &P,
+    block_hash: B256,
+) -> Result<ExecutionWitness> {
+    provider
+        .raw_request("debug_executionWitnessByBlockHash".into(), (block_hash,))
+        .await
+        .context("debug_executionWitnessByBlockHash failed")
+}
+
+/// Witness source that runs the preflight of `zeth-rpc-proxy` in-process.
+///
+/// This only requires standard RPC methods (most notably `eth_getProof`) from an archive node.
+pub struct PreflightWitnessSource<P> {
+    provider: Arc<P>,
+    chain_spec: Arc<ChainSpec>,
+}
+
+impl<P> PreflightWitnessSource<P> {
+    pub fn new(provider: Arc<P>, chain_spec: Arc<ChainSpec>) -> Self {
+        Self { provider, chain_spec }
+    }
+}
+
+#[async_trait]
+impl<P: Provider + 'static> WitnessSource for PreflightWitnessSource<P> {
+    async fn witness(&self, block: &Block, _: B256) -> Result<ExecutionWitness> {
+        // execute the given block, which may differ from the block the node has at that height
+        let evm_config = zeth_core::EthEvmConfig::new(self.chain_spec.clone());
+        zeth_rpc_proxy::block_execution_witness(evm_config, &self.provider, block.clone())
+            .await
+            .context("preflight failed")
+    }
+}
+
+/// Witness source that loads previously stored witnesses from a directory.
+pub struct FileWitnessSource {
+    dir: PathBuf,
+}
+
+impl FileWitnessSource {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl<B: Sync> WitnessSource<B> for FileWitnessSource {
+    async fn witness(&self, _: &B, block_hash: B256) -> Result<ExecutionWitness> {
+        let path = self.dir.join(format!("witness_{block_hash}.json"));
+        let f = File::open(&path).with_context(|| format!("failed to open file {path:?}"))?;
+        serde_json::from_reader(BufReader::new(f)).context("failed to read file")
+    }
+}
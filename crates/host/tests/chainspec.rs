@@ -0,0 +1,290 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Differential test between the chain specs of the guest and the reference specs of reth.
+//!
+//! The fork schedules and fee parameters are compared around every fork activation. In addition,
+//! blocks built on each chain just before and just after the activation of Cancun, as well as all
+//! inputs found in the cache folder (`ZETH_CACHE_DIR`, defaults to `./cache` in the workspace
+//! root), are validated with both specs, and any divergence in the fork schedule, the fee
+//! parameters or the validation result is reported as a failure.
+
+use alloy::{
+    eips::eip4895::Withdrawals,
+    primitives::{Address, B256, Bytes, KECCAK256_EMPTY, TxKind, U256, keccak256},
+    signers::{SignerSync, local::PrivateKeySigner},
+};
+use alloy_consensus::{Header, SignableTransaction, Transaction, TxEip1559};
+use alloy_hardforks::{EthereumHardfork, ForkCondition};
+use alloy_trie::{EMPTY_ROOT_HASH, TrieAccount};
+use reth_chainspec::{EthChainSpec, EthereumHardforks, Hardforks};
+use reth_ethereum_primitives::{Block, BlockBody, TransactionSigned};
+use reth_evm::eth::spec::EthExecutorSpec;
+use reth_stateless::{ExecutionWitness, StatelessInput};
+use risc0_ethereum_trie::Trie;
+use std::{
+    env,
+    fmt::Debug,
+    fs::{self, File},
+    io::BufReader,
+    path::PathBuf,
+    sync::Arc,
+};
+use zeth_core::{
+    EthEvmConfig,
+    proposal::{BlockProposal, ProposalAttributes, execute_proposal},
+    validate_block,
+};
+
+const GAS_LIMIT: u64 = 30_000_000;
+const BASE_FEE: u64 = 1_000_000_000;
+
+/// Builds a valid block of the chain at the given timestamp, containing a single transfer, together
+/// with the witness of its complete pre-state.
+///
+/// Like the blocks of the blockchain tests, the header is completed by executing the transactions.
+fn build_input(chain_spec: &Arc<zeth_chainspec::ChainSpec>, timestamp: u64) -> StatelessInput {
+    let signer = PrivateKeySigner::from_bytes(&B256::with_last_byte(1)).unwrap();
+    let mut state_trie = Trie::default();
+    let account = TrieAccount {
+        nonce: 0,
+        balance: U256::from(10u128.pow(19)),
+        storage_root: EMPTY_ROOT_HASH,
+        code_hash: KECCAK256_EMPTY,
+    };
+    state_trie.insert(keccak256(signer.address()), alloy_rlp::encode(account));
+
+    // the parent belongs to the same fork, so that no transition rules apply
+    let parent_timestamp = timestamp - 12;
+    let cancun = chain_spec.is_cancun_active_at_timestamp(parent_timestamp);
+    let parent = Header {
+        number: 20_000_000,
+        timestamp: parent_timestamp,
+        gas_limit: GAS_LIMIT,
+        gas_used: GAS_LIMIT / 2,
+        base_fee_per_gas: Some(BASE_FEE),
+        state_root: state_trie.hash_slow(),
+        withdrawals_root: Some(EMPTY_ROOT_HASH),
+        blob_gas_used: cancun.then_some(0),
+        excess_blob_gas: cancun.then_some(0),
+        parent_beacon_block_root: cancun.then_some(B256::ZERO),
+        ..Default::default()
+    };
+    let witness = ExecutionWitness {
+        state: state_trie.rlp_nodes().into_iter().collect(),
+        codes: vec![],
+        keys: vec![],
+        headers: vec![alloy_rlp::encode(&parent).into()],
+    };
+
+    let tx = TxEip1559 {
+        chain_id: chain_spec.chain().id(),
+        nonce: 0,
+        gas_limit: 21_000,
+        max_fee_per_gas: 2 * BASE_FEE as u128,
+        max_priority_fee_per_gas: 1,
+        to: TxKind::Call(Address::repeat_byte(0xee)),
+        value: U256::from(1_000),
+        access_list: Default::default(),
+        input: Bytes::new(),
+    };
+    let signature = signer.sign_hash_sync(&tx.signature_hash()).unwrap();
+    let transactions: Vec<TransactionSigned> = vec![tx.into_signed(signature).into()];
+
+    let attributes = ProposalAttributes {
+        timestamp,
+        suggested_fee_recipient: Address::repeat_byte(0xfe),
+        gas_limit: GAS_LIMIT,
+        parent_beacon_block_root: cancun.then_some(B256::repeat_byte(0x01)),
+        withdrawals: Some(Withdrawals::default()),
+        ..Default::default()
+    };
+    let proposal = BlockProposal { parent, attributes, transactions: transactions.clone() };
+    let config = EthEvmConfig::new(chain_spec.clone());
+    let header = execute_proposal(proposal, witness.clone().into(), config.clone()).unwrap();
+
+    let body =
+        BlockBody { transactions, ommers: vec![], withdrawals: Some(Withdrawals::default()) };
+    let input = StatelessInput { block: Block { header, body }, witness };
+    // the block must be valid, otherwise matching errors would hide any divergence
+    validate_block(input.block.clone(), input.witness.clone(), config).unwrap();
+
+    input
+}
+
+/// Builds a block just before and just after the activation of Cancun on each chain.
+fn built_inputs() -> Vec<(String, StatelessInput)> {
+    let chains = [&zeth_chainspec::MAINNET, &zeth_chainspec::SEPOLIA, &zeth_chainspec::HOLESKY];
+
+    let mut inputs = Vec::new();
+    for chain_spec in chains {
+        let ForkCondition::Timestamp(cancun) =
+            chain_spec.ethereum_fork_activation(EthereumHardfork::Cancun)
+        else {
+            panic!("{}: Cancun is not activated by timestamp", chain_spec.chain());
+        };
+        for timestamp in [cancun - 12, cancun + 12] {
+            let name = format!("{} block at {timestamp}", chain_spec.chain());
+            inputs.push((name, build_input(chain_spec, timestamp)));
+        }
+    }
+
+    inputs
+}
+
+fn cached_inputs() -> Vec<(String, StatelessInput)> {
+    let cache_dir = env::var_os("ZETH_CACHE_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../cache"));
+    let Ok(entries) = fs::read_dir(&cache_dir) else {
+        return vec![];
+    };
+
+    let mut inputs = Vec::new();
+    for entry in entries {
+        let path = entry.unwrap().path();
+        let is_input = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("input_") && name.ends_with(".json"));
+        if is_input {
+            let f = File::open(&path).unwrap();
+            let input = serde_json::from_reader(BufReader::new(f)).unwrap();
+            inputs.push((path.display().to_string(), input));
+        }
+    }
+    inputs.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    inputs
+}
+
+fn assert_no_divergence<A, B>(input: &StatelessInput, zeth_spec: &Arc<A>, reth_spec: &Arc<B>)
+where
+    A: EthExecutorSpec + EthChainSpec<Header = Header> + Hardforks + Debug + 'static,
+    B: EthExecutorSpec + EthChainSpec<Header = Header> + Hardforks + Debug + 'static,
+{
+    let (number, timestamp) = (input.block.number, input.block.timestamp);
+    let chain = zeth_spec.chain();
+
+    for fork in EthereumHardfork::VARIANTS {
+        assert_eq!(
+            zeth_spec
+                .ethereum_fork_activation(*fork)
+                .active_at_timestamp_or_number(timestamp, number),
+            reth_spec
+                .ethereum_fork_activation(*fork)
+                .active_at_timestamp_or_number(timestamp, number),
+            "{chain}: activation of {fork} diverges at block {number}"
+        );
+    }
+    assert_eq!(
+        zeth_spec.base_fee_params_at_timestamp(timestamp),
+        reth_spec.base_fee_params_at_timestamp(timestamp),
+        "{chain}: base fee params diverge at block {number}"
+    );
+    assert_eq!(
+        zeth_spec.blob_params_at_timestamp(timestamp),
+        reth_spec.blob_params_at_timestamp(timestamp),
+        "{chain}: blob params diverge at block {number}"
+    );
+
+    // the validation result is only meaningful on the chain the block belongs to
+    let block_chain_id = input.block.body.transactions.iter().find_map(|tx| tx.chain_id());
+    if block_chain_id.is_some_and(|id| id != chain.id())
+        || !zeth_spec.is_paris_active_at_block(number)
+    {
+        return;
+    }
+
+    let zeth_result = validate_block(
+        input.block.clone(),
+        input.witness.clone(),
+        EthEvmConfig::new(zeth_spec.clone()),
+    )
+    .map_err(|err| err.to_string());
+    let reth_result = validate_block(
+        input.block.clone(),
+        input.witness.clone(),
+        EthEvmConfig::new(reth_spec.clone()),
+    )
+    .map_err(|err| err.to_string());
+    assert_eq!(zeth_result, reth_result, "{chain}: validation diverges at block {number}");
+}
+
+/// Compares the fork schedule and the fee parameters just before, at and just after each fork
+/// activation of the reth spec.
+fn assert_same_schedule<A, B>(zeth_spec: &Arc<A>, reth_spec: &Arc<B>)
+where
+    A: EthChainSpec<Header = Header> + EthereumHardforks,
+    B: EthChainSpec<Header = Header> + EthereumHardforks,
+{
+    let chain = zeth_spec.chain();
+    assert_eq!(chain, reth_spec.chain());
+
+    for fork in EthereumHardfork::VARIANTS {
+        let probes = match reth_spec.ethereum_fork_activation(*fork) {
+            ForkCondition::Block(number) => vec![(0, number.saturating_sub(1)), (0, number)],
+            ForkCondition::Timestamp(timestamp) => {
+                vec![(timestamp.saturating_sub(1), u64::MAX), (timestamp, u64::MAX)]
+            }
+            ForkCondition::TTD { activation_block_number, .. } => {
+                vec![(0, activation_block_number.saturating_sub(1)), (0, activation_block_number)]
+            }
+            ForkCondition::Never => vec![(u64::MAX, u64::MAX)],
+        };
+        for (timestamp, number) in probes {
+            for fork in EthereumHardfork::VARIANTS {
+                assert_eq!(
+                    zeth_spec
+                        .ethereum_fork_activation(*fork)
+                        .active_at_timestamp_or_number(timestamp, number),
+                    reth_spec
+                        .ethereum_fork_activation(*fork)
+                        .active_at_timestamp_or_number(timestamp, number),
+                    "{chain}: activation of {fork} diverges at block {number}, time {timestamp}"
+                );
+            }
+            assert_eq!(
+                zeth_spec.base_fee_params_at_timestamp(timestamp),
+                reth_spec.base_fee_params_at_timestamp(timestamp),
+                "{chain}: base fee params diverge at time {timestamp}"
+            );
+            assert_eq!(
+                zeth_spec.blob_params_at_timestamp(timestamp),
+                reth_spec.blob_params_at_timestamp(timestamp),
+                "{chain}: blob params diverge at time {timestamp}"
+            );
+        }
+    }
+}
+
+#[test]
+fn fork_schedules_match_reth_chain_specs() {
+    assert_same_schedule(&zeth_chainspec::MAINNET, &reth_chainspec::MAINNET);
+    assert_same_schedule(&zeth_chainspec::SEPOLIA, &reth_chainspec::SEPOLIA);
+    assert_same_schedule(&zeth_chainspec::HOLESKY, &reth_chainspec::HOLESKY);
+    assert_same_schedule(&zeth_chainspec::HOODI, &reth_chainspec::HOODI);
+}
+
+#[test]
+fn inputs_match_reth_chain_specs() {
+    let inputs = built_inputs().into_iter().chain(cached_inputs());
+    for (name, input) in inputs {
+        println!("checking {name}");
+        assert_no_divergence(&input, &zeth_chainspec::MAINNET, &reth_chainspec::MAINNET);
+        assert_no_divergence(&input, &zeth_chainspec::SEPOLIA, &reth_chainspec::SEPOLIA);
+        assert_no_divergence(&input, &zeth_chainspec::HOLESKY, &reth_chainspec::HOLESKY);
+        assert_no_divergence(&input, &zeth_chainspec::HOODI, &reth_chainspec::HOODI);
+    }
+}
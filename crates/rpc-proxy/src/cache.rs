@@ -0,0 +1,342 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy_primitives::B256;
+use anyhow::{Context, anyhow};
+use futures::{
+    FutureExt,
+    future::{BoxFuture, Shared},
+};
+use lru::LruCache;
+use reth_stateless::ExecutionWitness;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    future::Future,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use tracing::{debug, warn};
+
+type Generation = Shared<BoxFuture<'static, Result<Arc<ExecutionWitness>, Arc<anyhow::Error>>>>;
+
+/// A cache of execution witnesses keyed by block hash.
+///
+/// Witnesses are kept in an in-memory LRU and optionally in a directory as
+/// `witness_<block_hash>.json`, the format of the file witness source of the host. Concurrent
+/// requests for the same block are coalesced into a single generation.
+///
+/// Only the in-memory cache is bounded; files on disk are kept until they are purged.
+pub struct WitnessCache {
+    memory: Mutex<LruCache<B256, Arc<ExecutionWitness>>>,
+    dir: Option<PathBuf>,
+    inflight: Mutex<HashMap<B256, Generation>>,
+    stats: Mutex<CacheStats>,
+}
+
+/// Statistics of a [WitnessCache].
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    /// Requests answered from memory.
+    pub memory_hits: u64,
+    /// Requests answered from disk.
+    pub disk_hits: u64,
+    /// Requests that joined a generation already in progress.
+    pub coalesced: u64,
+    /// Generations that have been started.
+    pub generated: u64,
+}
+
+/// The current state of a [WitnessCache].
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStatus {
+    /// The maximum number of witnesses kept in memory.
+    pub capacity: usize,
+    /// The blocks in memory, from the most to the least recently used.
+    pub blocks: Vec<B256>,
+    /// The blocks whose witness is currently being generated.
+    pub inflight: Vec<B256>,
+    /// The directory of the on-disk cache, if any.
+    pub dir: Option<PathBuf>,
+    #[serde(flatten)]
+    pub stats: CacheStats,
+}
+
+impl WitnessCache {
+    /// Creates a cache keeping up to `capacity` witnesses in memory and, if `dir` is given, all
+    /// witnesses on disk.
+    pub fn new(capacity: NonZeroUsize, dir: Option<PathBuf>) -> anyhow::Result<Self> {
+        if let Some(dir) = &dir {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create cache dir {}", dir.display()))?;
+        }
+        Ok(Self {
+            memory: Mutex::new(LruCache::new(capacity)),
+            dir,
+            inflight: Mutex::default(),
+            stats: Mutex::default(),
+        })
+    }
+
+    /// Returns the cached witness of the block, or generates it with `generate`.
+    ///
+    /// If a generation for the same block is already in progress, its result is awaited instead.
+    pub async fn get_or_generate<F>(
+        &self,
+        block_hash: B256,
+        generate: F,
+    ) -> Result<Arc<ExecutionWitness>, Arc<anyhow::Error>>
+    where
+        F: Future<Output = anyhow::Result<ExecutionWitness>> + Send + 'static,
+    {
+        if let Some(witness) = self.memory.lock().unwrap().get(&block_hash) {
+            self.stats.lock().unwrap().memory_hits += 1;
+            return Ok(witness.clone());
+        }
+        if let Some(witness) = self.load(block_hash).await {
+            self.stats.lock().unwrap().disk_hits += 1;
+            self.memory.lock().unwrap().put(block_hash, witness.clone());
+            return Ok(witness);
+        }
+
+        let generation = {
+            let mut inflight = self.inflight.lock().unwrap();
+            match inflight.get(&block_hash) {
+                Some(generation) => {
+                    self.stats.lock().unwrap().coalesced += 1;
+                    generation.clone()
+                }
+                None => {
+                    self.stats.lock().unwrap().generated += 1;
+                    let generation = generate.map(|r| r.map(Arc::new).map_err(Arc::new));
+                    let generation = generation.boxed().shared();
+                    inflight.insert(block_hash, generation.clone());
+                    generation
+                }
+            }
+        };
+
+        let result = generation.clone().await;
+        let removed = {
+            let mut inflight = self.inflight.lock().unwrap();
+            // only the first waiter removes the generation, a later one must not be touched, and a
+            // generation that has been purged in the meantime must not be cached
+            match inflight.get(&block_hash) {
+                Some(current) if current.ptr_eq(&generation) => inflight.remove(&block_hash),
+                _ => None,
+            }
+        };
+        if let (Some(_), Ok(witness)) = (removed, &result) {
+            self.memory.lock().unwrap().put(block_hash, witness.clone());
+            self.store(block_hash, witness).await;
+        }
+
+        result
+    }
+
+    /// Returns the current state of the cache.
+    pub fn status(&self) -> CacheStatus {
+        let memory = self.memory.lock().unwrap();
+        CacheStatus {
+            capacity: memory.cap().get(),
+            blocks: memory.iter().map(|(hash, _)| *hash).collect(),
+            inflight: self.inflight.lock().unwrap().keys().copied().collect(),
+            dir: self.dir.clone(),
+            stats: self.stats.lock().unwrap().clone(),
+        }
+    }
+
+    /// Removes the witness of the given block, or of all blocks, from memory and disk, and
+    /// returns the number of removed witnesses.
+    ///
+    /// Generations in progress still complete for their waiters, but their witness is not cached.
+    pub async fn purge(&self, block_hash: Option<B256>) -> anyhow::Result<usize> {
+        {
+            let mut inflight = self.inflight.lock().unwrap();
+            match block_hash {
+                Some(hash) => drop(inflight.remove(&hash)),
+                None => inflight.clear(),
+            }
+        }
+        let mut hashes: Vec<B256> = {
+            let mut memory = self.memory.lock().unwrap();
+            match block_hash {
+                Some(hash) => memory.pop(&hash).map(|_| hash).into_iter().collect(),
+                None => {
+                    let hashes = memory.iter().map(|(hash, _)| *hash).collect();
+                    memory.clear();
+                    hashes
+                }
+            }
+        };
+        let Some(dir) = &self.dir else {
+            return Ok(hashes.len());
+        };
+
+        let paths = match block_hash {
+            Some(hash) => vec![witness_path(dir, hash)],
+            None => {
+                let mut paths = Vec::new();
+                let mut entries = tokio::fs::read_dir(dir).await?;
+                while let Some(entry) = entries.next_entry().await? {
+                    paths.push(entry.path());
+                }
+                paths
+            }
+        };
+        for path in paths {
+            let Some(hash) = witness_hash(&path) else { continue };
+            match tokio::fs::remove_file(&path).await {
+                Ok(()) => hashes.push(hash),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(anyhow!(e).context(format!("failed to remove {path:?}"))),
+            }
+        }
+        hashes.sort_unstable();
+        hashes.dedup();
+
+        Ok(hashes.len())
+    }
+
+    async fn load(&self, block_hash: B256) -> Option<Arc<ExecutionWitness>> {
+        let path = witness_path(self.dir.as_ref()?, block_hash);
+        let json = tokio::fs::read(&path).await.ok()?;
+        match serde_json::from_slice(&json) {
+            Ok(witness) => Some(Arc::new(witness)),
+            Err(e) => {
+                warn!(?path, error = %e, "Ignoring invalid cached witness");
+                None
+            }
+        }
+    }
+
+    async fn store(&self, block_hash: B256, witness: &ExecutionWitness) {
+        let Some(dir) = &self.dir else { return };
+        let path = witness_path(dir, block_hash);
+        // write to a temporary file first, so that a crash never leaves a truncated witness
+        let tmp_path = path.with_extension("json.tmp");
+        let result = async {
+            tokio::fs::write(&tmp_path, serde_json::to_vec(witness)?).await?;
+            tokio::fs::rename(&tmp_path, &path).await?;
+            anyhow::Ok(())
+        };
+        match result.await {
+            Ok(()) => debug!(?path, "Witness stored"),
+            Err(e) => warn!(?path, error = %e, "Failed to store witness"),
+        }
+    }
+}
+
+fn witness_path(dir: &Path, block_hash: B256) -> PathBuf {
+    dir.join(format!("witness_{block_hash}.json"))
+}
+
+/// Returns the block hash of a witness file name.
+fn witness_hash(path: &Path) -> Option<B256> {
+    let name = path.file_name()?.to_str()?;
+    name.strip_prefix("witness_")?.strip_suffix(".json")?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn witness() -> ExecutionWitness {
+        ExecutionWitness { state: vec![], codes: vec![], keys: vec![], headers: vec![] }
+    }
+
+    #[tokio::test]
+    async fn coalesce_and_purge() {
+        let cache = WitnessCache::new(NonZeroUsize::new(2).unwrap(), None).unwrap();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let generate = || {
+            let calls = calls.clone();
+            async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                tokio::task::yield_now().await;
+                Ok(witness())
+            }
+        };
+
+        let hash = B256::repeat_byte(1);
+        let (a, b) = tokio::join!(
+            cache.get_or_generate(hash, generate()),
+            cache.get_or_generate(hash, generate())
+        );
+        assert!(a.is_ok() && b.is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        cache.get_or_generate(hash, generate()).await.unwrap();
+        let status = cache.status();
+        assert_eq!(status.blocks, vec![hash]);
+        assert!(status.inflight.is_empty());
+        assert_eq!(
+            (status.stats.generated, status.stats.coalesced, status.stats.memory_hits),
+            (1, 1, 1)
+        );
+
+        assert_eq!(cache.purge(Some(hash)).await.unwrap(), 1);
+        assert!(cache.status().blocks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn purge_inflight() {
+        let cache = WitnessCache::new(NonZeroUsize::new(2).unwrap(), None).unwrap();
+        let hash = B256::repeat_byte(4);
+        let (started_tx, started_rx) = tokio::sync::oneshot::channel();
+        let (finish_tx, finish_rx) = tokio::sync::oneshot::channel::<()>();
+        let generate = async move {
+            started_tx.send(()).unwrap();
+            finish_rx.await?;
+            Ok(witness())
+        };
+
+        let purge = async {
+            started_rx.await.unwrap();
+            assert_eq!(cache.status().inflight, vec![hash]);
+            cache.purge(Some(hash)).await.unwrap();
+            assert!(cache.status().inflight.is_empty());
+            finish_tx.send(()).unwrap();
+        };
+        let (result, ()) = tokio::join!(cache.get_or_generate(hash, generate), purge);
+
+        // the waiter gets its witness, but the purged generation is not cached
+        assert!(result.is_ok());
+        assert!(cache.status().blocks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn errors_are_not_cached() {
+        let cache = WitnessCache::new(NonZeroUsize::new(2).unwrap(), None).unwrap();
+        let hash = B256::repeat_byte(2);
+
+        let result = cache.get_or_generate(hash, async { Err(anyhow!("failed")) }).await;
+        assert!(result.is_err());
+        assert!(cache.status().blocks.is_empty());
+
+        cache.get_or_generate(hash, async { Ok(witness()) }).await.unwrap();
+        assert_eq!(cache.status().stats.generated, 2);
+    }
+
+    #[test]
+    fn witness_file_name() {
+        let hash = B256::repeat_byte(3);
+        assert_eq!(witness_hash(&witness_path(Path::new("cache"), hash)), Some(hash));
+        assert_eq!(witness_hash(Path::new("input_0x00.json")), None);
+    }
+}
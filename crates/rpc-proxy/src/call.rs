@@ -0,0 +1,83 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::db::{PreflightDb, ProviderConfig, ProviderDb};
+use alloy::{eips::BlockId, network::Ethereum, primitives::Bytes, providers::Provider};
+use anyhow::{Context, Result};
+use reth_chainspec::EthChainSpec;
+use reth_evm::ConfigureEvm;
+use std::collections::HashSet;
+use tracing::{Span, debug};
+use zeth_chainspec::ChainSpec;
+use zeth_core::{
+    EthEvmConfig,
+    call::{CallInput, CallRequest, transact_call},
+};
+
+/// Executes the call against the state of the given block and collects the input to prove it.
+///
+/// The returned input contains the proofs of all accounts and storage slots accessed by the call
+/// as well as the executed bytecodes.
+pub async fn call_input<P>(
+    evm_config: EthEvmConfig<ChainSpec>,
+    provider: &P,
+    block_id: BlockId,
+    request: CallRequest,
+) -> Result<CallInput>
+where
+    P: Provider<Ethereum> + Clone + Send + Sync + 'static,
+{
+    debug!(%block_id, "Fetching block header");
+    let rpc_block = provider
+        .get_block(block_id)
+        .await
+        .context("eth_getBlock failed")?
+        .with_context(|| format!("Block {block_id} not found"))?;
+    let block_hash = rpc_block.header.hash;
+    let header = rpc_block.header.into_consensus();
+
+    let chain_id = evm_config.chain_spec().chain_id();
+
+    let mut db =
+        PreflightDb::new(ProviderDb::new(provider.clone(), ProviderConfig::default(), block_hash));
+
+    debug!(%block_hash, "Executing call on dedicated thread");
+    let current_span = Span::current();
+
+    let (output, mut db, header, request) = tokio::task::spawn_blocking(move || {
+        current_span.in_scope(|| {
+            let output = transact_call(&evm_config, &header, &mut db, &request);
+            (output, db, header, request)
+        })
+    })
+    .await?;
+    let output = output.context("call failed")?;
+    debug!(%output, "Call executed");
+
+    debug!("Building state proofs");
+    let (state_trie, storage_tries) = db.state_proof().await?;
+    let mut state: HashSet<Bytes> = HashSet::new();
+    state.extend(state_trie.rlp_nodes());
+    for storage_trie in storage_tries.values() {
+        state.extend(storage_trie.rlp_nodes());
+    }
+
+    Ok(CallInput {
+        chain_id,
+        header: alloy::rlp::encode(&header).into(),
+        state: state.into_iter().collect(),
+        codes: db.contracts().values().cloned().collect(),
+        request,
+    })
+}
@@ -16,5 +16,5 @@ mod preflight;
 pub(crate) mod provider;
 
 pub use preflight::PreflightDb;
-pub(crate) use provider::ProviderConfig;
 pub use provider::ProviderDb;
+pub(crate) use provider::{ProviderConfig, RpcCalls, calls_since, count_call};
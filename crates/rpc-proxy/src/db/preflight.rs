@@ -12,7 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::db::{ProviderDb, provider};
+use crate::{
+    db::{
+        ProviderDb,
+        provider::{self, RpcCalls},
+    },
+    rpc::DebugApi,
+};
 use alloy::{
     consensus::BlockHeader,
     eips::eip2930::{AccessList, AccessListItem},
@@ -29,6 +35,7 @@ use alloy_primitives::{
 };
 use alloy_trie::{EMPTY_ROOT_HASH, TrieAccount as StateAccount};
 use anyhow::{Context, Result, ensure};
+use futures::{StreamExt, TryStreamExt, stream};
 use itertools::Itertools;
 use revm::{
     Database as RevmDatabase,
@@ -40,6 +47,7 @@ use std::{
     fmt::{self, Debug},
     hash::{BuildHasher, Hash},
 };
+use tracing::{debug, warn};
 
 /// A simple revm [RevmDatabase] wrapper that records all DB queries.
 #[derive(Clone, Default)]
@@ -48,6 +56,8 @@ pub struct PreflightDb<D> {
     contracts: B256HashMap<Bytes>,
     block_hash_numbers: HashSet<BlockNumber>,
 
+    /// Bytecodes reported by the `prestateTracer`, which may not all be referenced.
+    prefetched_codes: B256HashMap<Bytes>,
     code_addresses: B256Map<Address>,
     proofs: AccountProofs,
     inner: D,
@@ -94,6 +104,7 @@ impl<D> PreflightDb<D> {
             accounts: Default::default(),
             contracts: Default::default(),
             block_hash_numbers: Default::default(),
+            prefetched_codes: Default::default(),
             code_addresses: Default::default(),
             proofs: Default::default(),
             inner: db,
@@ -104,21 +115,83 @@ impl<D> PreflightDb<D> {
     pub(crate) fn contracts(&self) -> &B256HashMap<Bytes> {
         &self.contracts
     }
+
+    /// Returns the preimages of all accessed trie keys in the format of reth, i.e. each accessed
+    /// address followed by its accessed storage slots.
+    pub(crate) fn keys(&self) -> Vec<Bytes> {
+        let mut keys = Vec::new();
+        for (address, slots) in self.accounts.iter().sorted_by_key(|(address, _)| **address) {
+            keys.push(Bytes::copy_from_slice(address.as_slice()));
+            keys.extend(slots.iter().sorted().map(|slot| Bytes::copy_from_slice(slot.as_slice())));
+        }
+        keys
+    }
 }
 
 impl<N: Network, P: Provider<N>> PreflightDb<ProviderDb<N, P>> {
     /// Fetches all the EIP-1186 storage proofs from the `access_list` and stores them in the DB.
     pub(crate) async fn add_access_list(&mut self, access_list: &AccessList) -> Result<()> {
-        for AccessListItem { address, storage_keys } in &access_list.0 {
-            if let Some(keys) = self.proofs.missing_proof(address, storage_keys) {
-                let proof = self.inner.get_proof(*address, keys).await?;
-                self.proofs.add(proof).context("invalid eth_getProof response")?;
+        let queries = access_list
+            .0
+            .iter()
+            .map(|AccessListItem { address, storage_keys }| (address, storage_keys));
+        self.fetch_proofs(queries).await
+    }
+
+    /// Prefetches the proofs of all accounts and storage slots accessed by the transactions of
+    /// the block, so that they do not have to be fetched one by one during execution.
+    ///
+    /// The accesses are determined with the `prestateTracer`. If the tracer is not available,
+    /// nothing is prefetched and all data is fetched on demand.
+    pub(crate) async fn prefetch(&mut self, block_hash: B256) -> Result<()> {
+        self.inner.record_call("debug_traceBlockByHash");
+        let prestate = match self.inner.provider().get_block_prestate(block_hash).await {
+            Ok(prestate) => prestate,
+            Err(err) => {
+                warn!(error = format!("{err:#}"), "Prefetching failed, fetching on demand");
+                return Ok(());
             }
+        };
+        debug!(accounts = prestate.accounts.len(), "Block prestate traced");
+
+        self.prefetched_codes
+            .extend(prestate.codes.into_iter().map(|code| (keccak256(&code), code)));
+        self.fetch_proofs(prestate.accounts.iter()).await
+    }
+
+    /// Fetches the missing proofs of the given accounts and storage keys concurrently.
+    async fn fetch_proofs<'a, K>(
+        &mut self,
+        queries: impl IntoIterator<Item = (&'a Address, K)>,
+    ) -> Result<()>
+    where
+        K: IntoIterator<Item = &'a StorageKey>,
+    {
+        let missing: Vec<_> = queries
+            .into_iter()
+            .filter_map(|(address, keys)| {
+                Some((*address, self.proofs.missing_proof(address, keys)?))
+            })
+            .collect();
+
+        let inner = &self.inner;
+        let proofs: Vec<_> = stream::iter(missing)
+            .map(|(address, keys)| inner.get_proof(address, keys))
+            .buffer_unordered(inner.config().max_concurrent_proofs)
+            .try_collect()
+            .await?;
+        for proof in proofs {
+            self.proofs.add(proof).context("invalid eth_getProof response")?;
         }
 
         Ok(())
     }
 
+    /// Returns the number of RPC calls made by the underlying [ProviderDb] so far.
+    pub(crate) fn rpc_calls(&self) -> RpcCalls {
+        self.inner.rpc_calls()
+    }
+
     /// Returns the chain of ancestor headers starting from `start_hash`.
     ///
     /// This trace continues until it reaches a block number lower than the minimum
@@ -133,6 +206,7 @@ impl<N: Network, P: Provider<N>> PreflightDb<ProviderDb<N, P>> {
         let mut min_number: Option<u64> = None;
 
         loop {
+            self.inner.record_call("eth_getBlockByHash");
             let rpc_block = provider
                 .get_block_by_hash(current_hash)
                 .await
@@ -258,9 +332,10 @@ impl<N: Network, P: Provider<N>> RevmDatabase for PreflightDb<ProviderDb<N, P>>
     }
 
     fn code_by_hash(&mut self, hash: B256) -> Result<Bytecode, Self::Error> {
-        let code = match self.code_addresses.get(&hash) {
-            None => self.inner.code_by_hash(hash)?,
-            Some(address) => self.inner.get_code_at(*address)?,
+        let code = match (self.prefetched_codes.get(&hash), self.code_addresses.get(&hash)) {
+            (Some(code), _) => Bytecode::new_raw(code.clone()),
+            (None, None) => self.inner.code_by_hash(hash)?,
+            (None, Some(address)) => self.inner.get_code_at(*address)?,
         };
         self.contracts.insert(hash, code.original_bytes());
 
@@ -392,3 +467,155 @@ fn decode_account(proof_response: &EIP1186AccountProofResponse) -> Result<Option
         Some(rlp) => Ok(Some(decode_exact(rlp)?)),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::ProviderConfig;
+    use alloy::{
+        network::Ethereum, providers::ProviderBuilder, rpc::types::EIP1186StorageProof,
+        transports::mock::Asserter,
+    };
+    use alloy_primitives::bytes;
+    use serde_json::json;
+
+    const CODE: Bytes = bytes!("6000");
+
+    /// Returns the `eth_getProof` response of an account with [CODE] and the given storage.
+    fn account_proof(address: Address, slots: &[(B256, U256)]) -> EIP1186AccountProofResponse {
+        let mut storage_trie = Trie::default();
+        for (key, value) in slots {
+            storage_trie.insert(keccak256(key), alloy::rlp::encode(value));
+        }
+        let storage_nodes: Vec<Bytes> = storage_trie.rlp_nodes().into_iter().collect();
+
+        let account = StateAccount {
+            nonce: 1,
+            balance: U256::ZERO,
+            storage_root: storage_trie.hash_slow(),
+            code_hash: keccak256(&CODE),
+        };
+        let mut state_trie = Trie::default();
+        state_trie.insert(keccak256(address), alloy::rlp::encode(&account));
+
+        EIP1186AccountProofResponse {
+            address,
+            balance: account.balance,
+            code_hash: account.code_hash,
+            nonce: account.nonce,
+            storage_hash: account.storage_root,
+            account_proof: state_trie.rlp_nodes().into_iter().collect(),
+            storage_proof: slots
+                .iter()
+                .map(|(key, value)| EIP1186StorageProof {
+                    key: (*key).into(),
+                    value: *value,
+                    proof: storage_nodes.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Runs the preflight of a block that reads the code and two slots of a single account and
+    /// returns the RPC calls made, with or without a node supporting the `prestateTracer`.
+    async fn preflight_calls(tracer: bool) -> RpcCalls {
+        let address = Address::repeat_byte(1);
+        let slots = [(B256::repeat_byte(1), U256::from(1)), (B256::repeat_byte(2), U256::from(2))];
+        let proof = account_proof(address, &slots);
+
+        let asserter = Asserter::new();
+        if tracer {
+            let storage: serde_json::Map<_, _> = slots
+                .iter()
+                .map(|(key, value)| (key.to_string(), json!(B256::from(*value))))
+                .collect();
+            asserter.push_success(&json!([
+                { "result": { address.to_string(): { "code": CODE, "storage": storage } } }
+            ]));
+            asserter.push_success(&proof);
+        } else {
+            asserter.push_failure_msg("the method debug_traceBlockByHash does not exist");
+            // the account, its code and its slots are queried one by one during the execution
+            asserter.push_success(&EIP1186AccountProofResponse {
+                storage_proof: vec![],
+                ..proof.clone()
+            });
+            asserter.push_success(&CODE);
+            for (_, value) in &slots {
+                asserter.push_success(value);
+            }
+            // the proofs of the slots are only fetched when building the pre-state proofs
+            asserter.push_success(&proof);
+        }
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter);
+        let inner = ProviderDb::<Ethereum, _>::new(provider, ProviderConfig::default(), B256::ZERO);
+        let mut db = PreflightDb::new(inner);
+        db.prefetch(B256::ZERO).await.unwrap();
+
+        // the database blocks on the provider, so it must be accessed outside the runtime
+        let mut db = tokio::task::spawn_blocking(move || {
+            let info = db.basic(address).unwrap().unwrap();
+            assert_eq!(db.code_by_hash(info.code_hash).unwrap().original_bytes(), CODE);
+            for (key, value) in slots {
+                assert_eq!(db.storage(address, U256::from_be_bytes(key.0)).unwrap(), value);
+            }
+            db
+        })
+        .await
+        .unwrap();
+        db.state_proof().await.unwrap();
+
+        db.rpc_calls()
+    }
+
+    #[test]
+    fn keys_in_reth_format() {
+        let (a, b) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let (x, y) = (B256::repeat_byte(1), B256::repeat_byte(2));
+        let mut db = PreflightDb::<()>::default();
+        db.accounts.entry(b).or_default();
+        db.accounts.entry(a).or_default().extend([y, x]);
+
+        let expected: Vec<Bytes> =
+            vec![a.to_vec().into(), x.to_vec().into(), y.to_vec().into(), b.to_vec().into()];
+        assert_eq!(db.keys(), expected);
+    }
+
+    #[tokio::test]
+    async fn prefetch_without_tracer() {
+        let asserter = Asserter::new();
+        asserter.push_failure_msg("the method debug_traceBlockByHash does not exist");
+        asserter.push_success(&serde_json::json!({ "result": {} }));
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter);
+        let inner = ProviderDb::<Ethereum, _>::new(provider, ProviderConfig::default(), B256::ZERO);
+        let mut db = PreflightDb::new(inner);
+
+        // neither an error nor an unexpected response fails the preflight, nothing is prefetched
+        db.prefetch(B256::ZERO).await.unwrap();
+        db.prefetch(B256::ZERO).await.unwrap();
+        assert!(db.proofs.0.is_empty());
+        assert!(db.prefetched_codes.is_empty());
+        assert_eq!(db.rpc_calls(), RpcCalls::from([("debug_traceBlockByHash", 2)]));
+    }
+
+    #[tokio::test]
+    async fn prefetch_saves_calls() {
+        let on_demand = preflight_calls(false).await;
+        assert_eq!(
+            on_demand,
+            RpcCalls::from([
+                ("debug_traceBlockByHash", 1),
+                ("eth_getCode", 1),
+                ("eth_getProof", 2),
+                ("eth_getStorageAt", 2)
+            ])
+        );
+
+        let prefetched = preflight_calls(true).await;
+        assert_eq!(
+            prefetched,
+            RpcCalls::from([("debug_traceBlockByHash", 1), ("eth_getProof", 1)])
+        );
+        assert!(prefetched.values().sum::<u64>() < on_demand.values().sum::<u64>());
+    }
+}
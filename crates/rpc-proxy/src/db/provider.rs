@@ -13,19 +13,25 @@
 // limitations under the License.
 
 use alloy::{
+    consensus::BlockHeader,
     network::{BlockResponse, Network, primitives::HeaderResponse},
     providers::Provider,
     rpc::types::EIP1186AccountProofResponse,
     transports::TransportError,
 };
-use alloy_primitives::{Address, B256, BlockHash, StorageKey, U256, map::B256HashMap};
+use alloy_primitives::{Address, B256, BlockHash, BlockNumber, StorageKey, U256, map::B256HashMap};
 use revm::{
     Database as RevmDatabase,
     database::DBErrorMarker,
     primitives::KECCAK_EMPTY,
     state::{AccountInfo, Bytecode},
 };
-use std::{future::IntoFuture, marker::PhantomData};
+use std::{
+    collections::BTreeMap,
+    future::IntoFuture,
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+};
 use tokio::runtime::Handle;
 use tracing::trace;
 
@@ -42,6 +48,25 @@ pub enum Error {
 
 impl DBErrorMarker for Error {}
 
+/// The number of RPC calls per method.
+pub(crate) type RpcCalls = BTreeMap<&'static str, u64>;
+
+/// Records a call to `method`.
+pub(crate) fn count_call(calls: &mut RpcCalls, method: &'static str) {
+    *calls.entry(method).or_default() += 1;
+}
+
+/// Returns the calls made since the `earlier` snapshot.
+pub(crate) fn calls_since(mut calls: RpcCalls, earlier: &RpcCalls) -> RpcCalls {
+    for (method, count) in earlier {
+        if let Some(current) = calls.get_mut(method) {
+            *current -= count;
+        }
+    }
+    calls.retain(|_, count| *count > 0);
+    calls
+}
+
 /// A [RevmDatabase] backed by an alloy [Provider].
 ///
 /// When accessing the database, it'll use the given provider to fetch the corresponding account's
@@ -61,6 +86,10 @@ pub struct ProviderDb<N: Network, P: Provider<N>> {
     handle: Handle,
     /// Bytecode cache to allow querying bytecode by hash instead of address.
     contracts: B256HashMap<Bytecode>,
+    /// Number, hash and parent hash of the ancestors fetched so far, starting with `block`.
+    ancestors: Vec<(BlockNumber, BlockHash, BlockHash)>,
+    /// The RPC calls made so far, shared by all clones.
+    calls: Arc<Mutex<RpcCalls>>,
 
     phantom: PhantomData<N>,
 }
@@ -71,11 +100,13 @@ pub struct ProviderDb<N: Network, P: Provider<N>> {
 pub(crate) struct ProviderConfig {
     /// Max number of storage keys to request in a single `eth_getProof` call.
     pub eip1186_proof_chunk_size: usize,
+    /// Max number of concurrent `eth_getProof` calls when prefetching.
+    pub max_concurrent_proofs: usize,
 }
 
 impl Default for ProviderConfig {
     fn default() -> Self {
-        Self { eip1186_proof_chunk_size: 1000 }
+        Self { eip1186_proof_chunk_size: 1000, max_concurrent_proofs: 16 }
     }
 }
 
@@ -90,6 +121,8 @@ impl<N: Network, P: Provider<N>> ProviderDb<N, P> {
             block: block_hash,
             handle: Handle::current(),
             contracts: Default::default(),
+            ancestors: Vec::new(),
+            calls: Default::default(),
             phantom: PhantomData,
         }
     }
@@ -104,9 +137,25 @@ impl<N: Network, P: Provider<N>> ProviderDb<N, P> {
         self.block
     }
 
+    /// Returns the [ProviderConfig].
+    pub(crate) fn config(&self) -> &ProviderConfig {
+        &self.provider_config
+    }
+
+    /// Returns the number of RPC calls made so far.
+    pub(crate) fn rpc_calls(&self) -> RpcCalls {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// Records an RPC call to `method`.
+    pub(crate) fn record_call(&self, method: &'static str) {
+        count_call(&mut self.calls.lock().unwrap(), method);
+    }
+
     /// Gets the bytecode located at the corresponding [Address].
     pub(crate) fn get_code_at(&mut self, address: Address) -> Result<Bytecode, Error> {
         trace!(%address, "eth_getCode");
+        self.record_call("eth_getCode");
         let get_code = self.provider.get_code_at(address).hash(self.block);
         let code = self
             .handle
@@ -130,6 +179,7 @@ impl<N: Network, P: Provider<N>> ProviderDb<N, P> {
         mut keys: Vec<StorageKey>,
     ) -> Result<EIP1186AccountProofResponse, Error> {
         trace!(%address, num_keys=keys.len(), "eth_getProof");
+        self.record_call("eth_getProof");
         let block = self.block();
 
         // for certain RPC nodes it seemed beneficial when the keys are in the correct order
@@ -148,6 +198,7 @@ impl<N: Network, P: Provider<N>> ProviderDb<N, P> {
         }
 
         for keys in iter {
+            self.record_call("eth_getProof");
             let proof = self
                 .provider()
                 .get_proof(address, keys.into())
@@ -182,6 +233,9 @@ impl<N: Network, P: Provider<N>> RevmDatabase for ProviderDb<N, P> {
 
     fn basic(&mut self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
         trace!(%address, "getAccountInfo");
+        self.record_call("eth_getTransactionCount");
+        self.record_call("eth_getBalance");
+        self.record_call("eth_getCode");
         let f = async {
             let get_nonce = self.provider.get_transaction_count(address).hash(self.block);
             let get_balance = self.provider.get_balance(address).hash(self.block);
@@ -230,6 +284,7 @@ impl<N: Network, P: Provider<N>> RevmDatabase for ProviderDb<N, P> {
 
     fn storage(&mut self, address: Address, index: U256) -> Result<U256, Self::Error> {
         trace!(%address, %index, "eth_getStorageAt");
+        self.record_call("eth_getStorageAt");
         let storage = self
             .handle
             .block_on(self.provider.get_storage_at(address, index).hash(self.block).into_future())
@@ -239,13 +294,24 @@ impl<N: Network, P: Provider<N>> RevmDatabase for ProviderDb<N, P> {
     }
 
     fn block_hash(&mut self, number: u64) -> Result<B256, Self::Error> {
-        trace!(number, "eth_getBlockByNumber");
-        let block_response = self
-            .handle
-            .block_on(self.provider.get_block_by_number(number.into()).into_future())
-            .map_err(|err| Error::Rpc("eth_getBlockByNumber", err))?;
-        let block = block_response.ok_or(Error::BlockNotFound)?;
+        // follow the parent hashes instead of querying by number, so that the hash is always from
+        // the chain of `self.block`, even if a reorg happens in the meantime
+        while self.ancestors.last().is_none_or(|&(n, ..)| n > number) {
+            let hash = self.ancestors.last().map_or(self.block, |&(.., parent)| parent);
+            trace!(%hash, "eth_getBlockByHash");
+            self.record_call("eth_getBlockByHash");
+            let block_response = self
+                .handle
+                .block_on(self.provider.get_block_by_hash(hash).into_future())
+                .map_err(|err| Error::Rpc("eth_getBlockByHash", err))?;
+            let block = block_response.ok_or(Error::BlockNotFound)?;
+            let header = block.header();
+            self.ancestors.push((header.number(), header.hash(), header.parent_hash()));
+        }
 
-        Ok(block.header().hash())
+        self.ancestors
+            .iter()
+            .find_map(|&(n, hash, _)| (n == number).then_some(hash))
+            .ok_or(Error::BlockNotFound)
     }
 }
@@ -12,9 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod cache;
+mod call;
 mod db;
 mod rpc;
 mod trie;
 mod witness;
 
-pub use witness::execution_witness;
+pub use cache::{CacheStats, CacheStatus, WitnessCache};
+pub use call::call_input;
+pub use witness::{block_execution_witness, execution_witness};
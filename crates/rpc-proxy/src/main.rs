@@ -12,31 +12,40 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use actix_web::{App, HttpResponse, HttpServer, Responder, web};
+use actix_web::{App, HttpResponse, HttpServer, Responder, http::StatusCode, web};
 use alloy::{
-    eips::BlockNumberOrTag,
+    eips::BlockId,
+    primitives::B256,
     providers::{DynProvider, Provider, ProviderBuilder},
     rpc::client::RpcClient,
     transports::layers::RetryBackoffLayer,
 };
 use alloy_chains::NamedChain;
-use anyhow::{Context, bail};
+use anyhow::{Context, bail, ensure};
 use clap::Parser;
 use reqwest::Client;
-use reth_chainspec::{HOLESKY, HOODI, MAINNET, SEPOLIA};
 use reth_evm_ethereum::EthEvmConfig;
+use reth_stateless::ExecutionWitness;
 use serde_json::{Value, json};
-use std::sync::Arc;
+use std::{num::NonZeroUsize, path::PathBuf, sync::Arc};
+use tokio::sync::broadcast;
 use tracing::{debug, error, field, info, instrument};
 use tracing_actix_web::TracingLogger;
-use zeth_rpc_proxy::execution_witness;
+use zeth_chainspec::{ChainSpec, HOLESKY, HOODI, MAINNET, SEPOLIA};
+use zeth_rpc_proxy::{WitnessCache, execution_witness};
+
+mod ws;
 
 /// This struct holds the application state that we want to share across all handlers.
 struct AppState {
     client: Client,
     upstream_url: String,
     provider: DynProvider,
-    evm_config: Arc<EthEvmConfig>,
+    evm_config: Arc<EthEvmConfig<ChainSpec>>,
+    cache: WitnessCache,
+    upstream_ws_url: Option<String>,
+    /// The serialized witness of each new head, if the witness subscription is enabled.
+    witnesses: Option<broadcast::Sender<Arc<String>>>,
 }
 
 #[derive(Parser, Debug)]
@@ -46,6 +55,26 @@ struct Args {
     #[arg(long, env)]
     rpc_url: String,
 
+    /// The upstream websocket URL to proxy websocket connections and subscriptions to.
+    #[arg(long, env)]
+    ws_url: Option<String>,
+
+    /// Push the witness of each new head to the `executionWitness` subscribers.
+    /// Requires `--ws-url`.
+    #[arg(long, requires = "ws_url")]
+    witness_subscription: bool,
+
+    /// The number of witnesses kept in memory.
+    #[arg(long, default_value = "32")]
+    cache_size: NonZeroUsize,
+
+    /// The directory to additionally store all generated witnesses in.
+    ///
+    /// The on-disk cache is not bounded; use `zeth_purgeWitnessCache` or remove old
+    /// `witness_<block_hash>.json` files to reclaim space.
+    #[arg(long)]
+    cache_dir: Option<PathBuf>,
+
     /// The network address and port to bind the server to.
     #[arg(long, default_value = "127.0.0.1:8545")]
     bind_address: String,
@@ -77,6 +106,15 @@ async fn rpc_handler(body: web::Bytes, data: web::Data<AppState>) -> impl Respon
         }
     };
 
+    // A batch is split into local and forwarded requests.
+    let request = match request {
+        Value::Array(entries) => {
+            tracing::Span::current().record("method", "batch");
+            return handle_batch(entries, data).await;
+        }
+        request => request,
+    };
+
     // Extract method, id, and params from the request.
     let method = request.get("method").and_then(Value::as_str);
     let id = request.get("id").cloned().unwrap_or(Value::Null);
@@ -85,8 +123,10 @@ async fn rpc_handler(body: web::Bytes, data: web::Data<AppState>) -> impl Respon
     tracing::Span::current().record("method", method.unwrap_or("unknown"));
 
     match method {
-        // If the method is `debug_executionWitness`, handle it locally.
-        Some("debug_executionWitness") => handle_debug_execution_witness(id, params, data).await,
+        // If the method is `debug_executionWitness` or an admin method, handle it locally.
+        Some(method) if LOCAL_METHODS.contains(&method) => {
+            handle_local(method, id, params, data).await
+        }
         // For all other methods, forward the request to the upstream provider.
         Some(_) => forward_request(&body, &data.client, &data.upstream_url).await,
         // If the method is not specified, return an error.
@@ -101,6 +141,155 @@ async fn rpc_handler(body: web::Bytes, data: web::Data<AppState>) -> impl Respon
     }
 }
 
+/// Handles a JSON-RPC batch, see [batch_responses].
+async fn handle_batch(entries: Vec<Value>, data: web::Data<AppState>) -> HttpResponse {
+    if entries.is_empty() {
+        return HttpResponse::BadRequest().json(empty_batch_response());
+    }
+
+    let responses = batch_responses(entries, &data).await;
+    if responses.is_empty() {
+        // a batch of notifications must not be answered with an empty array
+        return HttpResponse::NoContent().finish();
+    }
+    HttpResponse::Ok().json(responses)
+}
+
+/// Returns the error response to an empty batch.
+fn empty_batch_response() -> Value {
+    error_response(Value::Null, -32600, "Invalid Request: empty batch")
+}
+
+/// Computes the responses to a non-empty JSON-RPC batch.
+///
+/// The entries of [LOCAL_METHODS] are answered locally, while all other entries are
+/// forwarded upstream as a single batch. The responses are returned in the order of the requests,
+/// notifications without an `id` do not get a response.
+async fn batch_responses(entries: Vec<Value>, data: &AppState) -> Vec<Value> {
+    debug!(len = entries.len(), "Handling batch request");
+
+    let mut responses: Vec<Option<Value>> = vec![None; entries.len()];
+    let mut local = Vec::new();
+    let mut upstream = Vec::new();
+    let mut upstream_ids = Vec::new();
+    for (index, mut entry) in entries.into_iter().enumerate() {
+        let id = entry.get("id").cloned();
+        match entry.get("method").and_then(Value::as_str) {
+            Some(method) if LOCAL_METHODS.contains(&method) => {
+                if let Some(id) = id {
+                    let params = entry.get("params").cloned().unwrap_or(Value::Null);
+                    local.push((index, method.to_string(), id, params));
+                }
+            }
+            Some(_) => {
+                // the ids of a batch are not necessarily unique, so the index is sent upstream
+                if let Some(id) = id {
+                    entry["id"] = index.into();
+                    upstream_ids.push((index, id));
+                }
+                upstream.push(entry);
+            }
+            None => {
+                let id = id.unwrap_or(Value::Null);
+                let error = error_response(id, -32600, "Invalid Request: method not found");
+                responses[index] = Some(error);
+            }
+        }
+    }
+
+    let local_responses = futures::future::join_all(local.into_iter().map(
+        |(index, method, id, params)| async move {
+            (index, local_response(&method, id, params, data).await.1)
+        },
+    ));
+    let upstream_responses = async {
+        if upstream.is_empty() {
+            return Ok(vec![]);
+        }
+        forward_batch(&upstream, &data.client, &data.upstream_url).await
+    };
+    let (local_responses, upstream_responses) = tokio::join!(local_responses, upstream_responses);
+
+    for (index, response) in local_responses {
+        responses[index] = Some(response);
+    }
+    assign_upstream_responses(&mut responses, upstream_ids, upstream_responses);
+
+    responses.into_iter().flatten().collect()
+}
+
+/// Forwards a batch of requests to the upstream provider and returns its responses, which may be
+/// in any order.
+async fn forward_batch(
+    batch: &[Value],
+    client: &Client,
+    upstream_url: &str,
+) -> Result<Vec<Value>, String> {
+    debug!(len = batch.len(), "Forwarding batch to upstream");
+    let response = client
+        .post(upstream_url)
+        .json(batch)
+        .send()
+        .await
+        .map_err(|e| format!("Upstream provider error: {e}"))?;
+    let response: Value =
+        response.json().await.map_err(|e| format!("Upstream response body error: {e}"))?;
+
+    match response {
+        Value::Array(responses) => Ok(responses),
+        // some providers answer an unsupported batch with a single error
+        response => Err(response
+            .pointer("/error/message")
+            .and_then(Value::as_str)
+            .unwrap_or("Upstream provider error: invalid batch response")
+            .to_string()),
+    }
+}
+
+/// Assigns the upstream responses to the forwarded requests, which have been sent with their
+/// index as the id, and restores the original ids.
+///
+/// Requests without a matching response, e.g. because the whole batch failed, get an error.
+fn assign_upstream_responses(
+    responses: &mut [Option<Value>],
+    ids: Vec<(usize, Value)>,
+    upstream: Result<Vec<Value>, String>,
+) {
+    let mut upstream = match upstream {
+        Ok(upstream) => upstream,
+        Err(message) => {
+            error!(error = %message, "Batch forwarding failed");
+            for (index, id) in ids {
+                responses[index] = Some(error_response(id, -32001, &message));
+            }
+            return;
+        }
+    };
+
+    for (index, id) in ids {
+        let position = upstream.iter().position(|response| {
+            response.get("id").and_then(Value::as_u64).is_some_and(|i| i == index as u64)
+        });
+        responses[index] = Some(match position {
+            Some(position) => {
+                let mut response = upstream.swap_remove(position);
+                response["id"] = id;
+                response
+            }
+            None => error_response(id, -32603, "Internal error: missing upstream response"),
+        });
+    }
+}
+
+/// Returns a JSON-RPC error response.
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "error": { "code": code, "message": message },
+        "id": id
+    })
+}
+
 /// Forwards the raw request body to the upstream provider and returns the response.
 async fn forward_request(body: &web::Bytes, client: &Client, upstream_url: &str) -> HttpResponse {
     debug!("Forwarding request to upstream");
@@ -151,50 +340,172 @@ async fn forward_request(body: &web::Bytes, client: &Client, upstream_url: &str)
     }
 }
 
-/// This is the custom implementation for the `debug_executionWitness` RPC call.
-async fn handle_debug_execution_witness(
+/// The methods answered by the proxy itself instead of the upstream provider.
+const LOCAL_METHODS: &[&str] = &[
+    "debug_executionWitness",
+    "debug_executionWitnessByBlockHash",
+    "zeth_witnessCacheStatus",
+    "zeth_purgeWitnessCache",
+];
+
+/// Handles one of the [LOCAL_METHODS].
+async fn handle_local(
+    method: &str,
     id: Value,
     params: Value,
     data: web::Data<AppState>,
 ) -> HttpResponse {
-    debug!("Handling 'debug_executionWitness' locally");
+    let (status, response) = local_response(method, id, params, &data).await;
+    HttpResponse::build(status).json(response)
+}
 
-    let params_vec: Vec<BlockNumberOrTag> = match serde_json::from_value(params) {
-        Ok(p) => p,
-        Err(_) => {
-            return HttpResponse::BadRequest().json(json!({
-                "jsonrpc": "2.0",
-                "error": { "code": -32602, "message": "Invalid params" },
-                "id": id
-            }));
+/// Computes the JSON-RPC response of one of the [LOCAL_METHODS], together with the HTTP status
+/// used when the request is not part of a batch.
+async fn local_response(
+    method: &str,
+    id: Value,
+    params: Value,
+    data: &AppState,
+) -> (StatusCode, Value) {
+    match method {
+        "debug_executionWitness" | "debug_executionWitnessByBlockHash" => {
+            execution_witness_response(method, id, params, data).await
+        }
+        "zeth_witnessCacheStatus" => {
+            let status = data.cache.status();
+            (StatusCode::OK, json!({ "jsonrpc": "2.0", "id": id, "result": status }))
+        }
+        "zeth_purgeWitnessCache" => purge_cache_response(id, params, data).await,
+        _ => (StatusCode::NOT_FOUND, error_response(id, -32601, "Method not found")),
+    }
+}
+
+/// Removes the witness of the block hash given as the optional parameter, or all witnesses, from
+/// the cache and returns the number of removed witnesses.
+async fn purge_cache_response(id: Value, params: Value, data: &AppState) -> (StatusCode, Value) {
+    let block_hash = match serde_json::from_value::<Option<Vec<B256>>>(params).as_deref() {
+        Ok(None | Some([])) => None,
+        Ok(Some([hash])) => Some(*hash),
+        _ => {
+            let message = "Invalid params: expected an optional block hash";
+            return (StatusCode::BAD_REQUEST, error_response(id, -32602, message));
         }
     };
-    if params_vec.len() != 1 {
-        return HttpResponse::BadRequest().json(json!({
-            "jsonrpc": "2.0",
-            "error": { "code": -32602, "message": "Invalid params: expected a single BlockNumberOrTag parameter" },
-            "id": id
-        }));
-    }
-
-    let block_id = params_vec[0];
-    match execution_witness(data.evm_config.clone(), &data.provider, block_id).await {
-        Ok(witness) => HttpResponse::Ok().json(json!({
-            "jsonrpc": "2.0",
-            "id": id,
-            "result": witness
-        })),
+
+    match data.cache.purge(block_hash).await {
+        Ok(removed) => (StatusCode::OK, json!({ "jsonrpc": "2.0", "id": id, "result": removed })),
         Err(e) => {
-            error!(error = format!("{e:#}"), "Preflight function failed");
-            HttpResponse::InternalServerError().json(json!({
+            error!(error = format!("{e:#}"), "Failed to purge witness cache");
+            let message = format!("Cache error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, error_response(id, -32000, &message))
+        }
+    }
+}
+
+/// Parses the block parameter of `debug_executionWitness`, which can be a block number, tag, hash
+/// or EIP-1898 object, or of `debug_executionWitnessByBlockHash`, which must be a block hash.
+fn parse_block_param(method: &str, params: Value) -> Result<BlockId, &'static str> {
+    if method == "debug_executionWitnessByBlockHash" {
+        let [block_hash] = serde_json::from_value::<[B256; 1]>(params)
+            .map_err(|_| "Invalid params: expected a single block hash")?;
+        return Ok(block_hash.into());
+    }
+    let [block_id] = serde_json::from_value::<[BlockId; 1]>(params).map_err(
+        |_| "Invalid params: expected a single block number, tag, hash or EIP-1898 object",
+    )?;
+    Ok(block_id)
+}
+
+/// Computes the JSON-RPC response of `debug_executionWitness` and
+/// `debug_executionWitnessByBlockHash`, using the witness cache.
+async fn execution_witness_response(
+    method: &str,
+    id: Value,
+    params: Value,
+    data: &AppState,
+) -> (StatusCode, Value) {
+    debug!("Handling '{method}' locally");
+
+    let block_id = match parse_block_param(method, params) {
+        Ok(block_id) => block_id,
+        Err(message) => return (StatusCode::BAD_REQUEST, error_response(id, -32602, message)),
+    };
+    match cached_execution_witness(data, block_id).await {
+        Ok(witness) => (
+            StatusCode::OK,
+            json!({
                 "jsonrpc": "2.0",
-                "error": { "code": -32000, "message": format!("Preflight error: {}", e) },
-                "id": id
-            }))
+                "id": id,
+                "result": witness.as_ref()
+            }),
+        ),
+        Err(e) => {
+            error!(error = format!("{e:#}"), "Preflight function failed");
+            let message = format!("Preflight error: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, error_response(id, -32000, &message))
         }
     }
 }
 
+/// Returns the witness of the block from the cache, generating it if necessary.
+///
+/// Blocks given by number or tag are first resolved to their hash, which is the key of the cache.
+/// For an EIP-1898 hash with `requireCanonical`, the block must be part of the canonical chain.
+async fn cached_execution_witness(
+    data: &AppState,
+    block_id: BlockId,
+) -> anyhow::Result<Arc<ExecutionWitness>> {
+    let block_hash = match block_id {
+        BlockId::Hash(hash) if hash.require_canonical == Some(true) => {
+            let block_hash = hash.block_hash;
+            let block = data
+                .provider
+                .get_block(block_hash.into())
+                .await
+                .context("eth_getBlock failed")?
+                .with_context(|| format!("Block {block_hash} not found"))?;
+            let number = block.header.number;
+            let canonical = data
+                .provider
+                .get_block(number.into())
+                .await
+                .context("eth_getBlock failed")?
+                .with_context(|| format!("Block {number} not found"))?;
+            ensure!(
+                canonical.header.hash == block_hash,
+                "Block {block_hash} is not canonical, block {number} is {}",
+                canonical.header.hash
+            );
+            block_hash
+        }
+        BlockId::Hash(hash) => hash.block_hash,
+        BlockId::Number(number) => {
+            let block = data
+                .provider
+                .get_block(number.into())
+                .await
+                .context("eth_getBlock failed")?
+                .with_context(|| format!("Block {number} not found"))?;
+            block.header.hash
+        }
+    };
+
+    cached_block_witness(data, block_hash).await
+}
+
+/// Returns the witness of the block with the given hash from the cache, generating it if
+/// necessary.
+async fn cached_block_witness(
+    data: &AppState,
+    block_hash: B256,
+) -> anyhow::Result<Arc<ExecutionWitness>> {
+    let (evm_config, provider) = (data.evm_config.clone(), data.provider.clone());
+    // generate by hash, so that a reorg cannot store the witness of another block under this key
+    let block_id = BlockId::hash(block_hash);
+    let generate = async move { execution_witness(evm_config, &provider, block_id).await };
+    data.cache.get_or_generate(block_hash, generate).await.map_err(|e| anyhow::anyhow!("{e:#}"))
+}
+
 #[actix_web::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt::init();
@@ -211,14 +522,17 @@ async fn main() -> anyhow::Result<()> {
     let chain_id = provider.get_chain_id().await.context("eth_chainId failed")?;
     let chain: NamedChain = chain_id.try_into().context("Invalid chain_id")?;
     let evm_config = match chain {
-        NamedChain::Mainnet => Arc::new(EthEvmConfig::ethereum(MAINNET.clone())),
-        NamedChain::Holesky => Arc::new(EthEvmConfig::ethereum(HOLESKY.clone())),
-        NamedChain::Hoodi => Arc::new(EthEvmConfig::ethereum(HOODI.clone())),
-        NamedChain::Sepolia => Arc::new(EthEvmConfig::ethereum(SEPOLIA.clone())),
+        NamedChain::Mainnet => Arc::new(EthEvmConfig::new(MAINNET.clone())),
+        NamedChain::Holesky => Arc::new(EthEvmConfig::new(HOLESKY.clone())),
+        NamedChain::Hoodi => Arc::new(EthEvmConfig::new(HOODI.clone())),
+        NamedChain::Sepolia => Arc::new(EthEvmConfig::new(SEPOLIA.clone())),
         _ => bail!("Unsupported chain: {chain}"),
     };
     info!("EVM config: {}", chain);
 
+    let cache = WitnessCache::new(args.cache_size, args.cache_dir)?;
+    let witnesses = args.witness_subscription.then(|| broadcast::channel(16).0);
+
     // Create the shared application state.
     // web::Data handles the atomic reference counting for safe sharing across threads.
     let app_state = web::Data::new(AppState {
@@ -226,8 +540,20 @@ async fn main() -> anyhow::Result<()> {
         upstream_url: args.rpc_url,
         provider: provider.erased(),
         evm_config,
+        cache,
+        upstream_ws_url: args.ws_url,
+        witnesses,
     });
 
+    if let (Some(ws_url), Some(_)) = (&app_state.upstream_ws_url, &app_state.witnesses) {
+        let feed = ws::witness_feed(ws_url.clone(), app_state.clone());
+        actix_web::rt::spawn(async move {
+            if let Err(e) = feed.await {
+                error!(error = format!("{e:#}"), "Witness subscription stopped");
+            }
+        });
+    }
+
     info!(
         bind_address = %args.bind_address,
         upstream_url = %app_state.upstream_url,
@@ -239,6 +565,7 @@ async fn main() -> anyhow::Result<()> {
             .app_data(app_state.clone())
             .wrap(TracingLogger::default())
             .route("/", web::post().to(rpc_handler))
+            .route("/", web::get().to(ws::ws_handler))
     })
     .bind(args.bind_address)?
     .run()
@@ -246,3 +573,59 @@ async fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upstream_responses_in_request_order() {
+        let mut responses = vec![None, None, Some(json!("local"))];
+        let ids = vec![(0, json!("a")), (1, json!("a"))];
+        let upstream = vec![
+            json!({ "jsonrpc": "2.0", "id": 1, "result": "0x2" }),
+            json!({ "jsonrpc": "2.0", "id": 0, "error": { "code": 3, "message": "reverted" } }),
+        ];
+        assign_upstream_responses(&mut responses, ids, Ok(upstream));
+
+        assert_eq!(responses[0].as_ref().unwrap()["error"]["code"], 3);
+        assert_eq!(responses[0].as_ref().unwrap()["id"], "a");
+        assert_eq!(responses[1].as_ref().unwrap()["result"], "0x2");
+        assert_eq!(responses[1].as_ref().unwrap()["id"], "a");
+        assert_eq!(responses[2], Some(json!("local")));
+    }
+
+    #[test]
+    fn block_params() {
+        let hash = B256::repeat_byte(1);
+        let method = "debug_executionWitness";
+        assert_eq!(parse_block_param(method, json!(["0x10"])), Ok(BlockId::number(16)));
+        assert_eq!(parse_block_param(method, json!(["latest"])), Ok(BlockId::latest()));
+        assert_eq!(parse_block_param(method, json!([hash])), Ok(BlockId::hash(hash)));
+        assert_eq!(
+            parse_block_param(method, json!([{ "blockHash": hash, "requireCanonical": true }])),
+            Ok(BlockId::hash_canonical(hash))
+        );
+        assert_eq!(
+            parse_block_param(method, json!([{ "blockNumber": "0x10" }])),
+            Ok(BlockId::number(16))
+        );
+        assert!(parse_block_param(method, json!(["0x10", "0x11"])).is_err());
+
+        let method = "debug_executionWitnessByBlockHash";
+        assert_eq!(parse_block_param(method, json!([hash])), Ok(BlockId::hash(hash)));
+        assert!(parse_block_param(method, json!(["latest"])).is_err());
+    }
+
+    #[test]
+    fn missing_upstream_responses() {
+        let mut responses = vec![None, None];
+        let ids = vec![(0, json!(7)), (1, json!(8))];
+        assign_upstream_responses(&mut responses, ids.clone(), Ok(vec![]));
+        assert_eq!(responses[0].as_ref().unwrap()["error"]["code"], -32603);
+
+        assign_upstream_responses(&mut responses, ids, Err("failed".to_string()));
+        assert_eq!(responses[1].as_ref().unwrap()["error"]["message"], "failed");
+        assert_eq!(responses[1].as_ref().unwrap()["id"], 8);
+    }
+}
@@ -13,16 +13,17 @@
 // limitations under the License.
 
 use alloy::{
-    primitives::{B256, U256},
+    primitives::{B256, Bytes, U256},
     providers::{Network, Provider},
     serde::JsonStorageKey,
 };
-use alloy_primitives::{Address, keccak256};
+use alloy_primitives::{Address, keccak256, map::AddressHashMap};
 use anyhow::{Context, ensure};
 use async_trait::async_trait;
 use risc0_ethereum_trie::Nibbles;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
 use tracing::trace;
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
@@ -40,6 +41,30 @@ pub struct StorageRangeQueryResponseEntry {
     pub value: U256,
 }
 
+/// An account in the pre-state of a transaction, as returned by the `prestateTracer`.
+#[derive(Clone, Debug, Default, Deserialize)]
+struct PrestateAccount {
+    #[serde(default)]
+    code: Option<Bytes>,
+    #[serde(default)]
+    storage: HashMap<B256, B256>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct PrestateTraceResult {
+    #[serde(default)]
+    result: HashMap<Address, PrestateAccount>,
+}
+
+/// The accounts accessed by the transactions of a block.
+#[derive(Clone, Debug, Default)]
+pub struct BlockPrestate {
+    /// The accessed accounts and their accessed storage slots.
+    pub accounts: AddressHashMap<HashSet<B256>>,
+    /// The bytecodes of the accessed accounts.
+    pub codes: Vec<Bytes>,
+}
+
 /// An extension trait for Alloy providers that adds custom debug RPC methods.
 #[async_trait]
 pub trait DebugApi<N: Network>: Provider<N> {
@@ -50,6 +75,10 @@ pub trait DebugApi<N: Network>: Provider<N> {
         address: Address,
         prefix: Nibbles,
     ) -> anyhow::Result<B256>;
+
+    /// Fetches all accounts and storage slots accessed by the transactions of the block using
+    /// `debug_traceBlockByHash` with the `prestateTracer`.
+    async fn get_block_prestate(&self, block_hash: B256) -> anyhow::Result<BlockPrestate>;
 }
 
 #[async_trait]
@@ -92,4 +121,59 @@ where
 
         Ok(storage_key)
     }
+
+    async fn get_block_prestate(&self, block_hash: B256) -> anyhow::Result<BlockPrestate> {
+        trace!(%block_hash, "debug_traceBlockByHash");
+
+        let params = (block_hash, json!({ "tracer": "prestateTracer" }));
+        let traces: Vec<PrestateTraceResult> = self
+            .client()
+            .request("debug_traceBlockByHash", params)
+            .await
+            .context("debug_traceBlockByHash failed")?;
+
+        let mut prestate = BlockPrestate::default();
+        for (address, account) in traces.into_iter().flat_map(|trace| trace.result) {
+            prestate.accounts.entry(address).or_default().extend(account.storage.into_keys());
+            prestate.codes.extend(account.code.filter(|code| !code.is_empty()));
+        }
+
+        Ok(prestate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{providers::ProviderBuilder, transports::mock::Asserter};
+    use alloy_primitives::bytes;
+
+    #[tokio::test]
+    async fn block_prestate() {
+        let (a, b) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let (x, y) = (B256::repeat_byte(1), B256::repeat_byte(2));
+        // the accessed slots of all transactions are merged, failed traces have no result
+        let traces: serde_json::Value = serde_json::from_str(&format!(
+            r#"[
+                {{"txHash": "{x}", "result": {{
+                    "{a}": {{"balance": "0x1", "code": "0x6000", "storage": {{"{x}": "{y}"}}}},
+                    "{b}": {{"balance": "0x0", "code": "0x"}}
+                }}}},
+                {{"txHash": "{y}", "result": {{
+                    "{a}": {{"balance": "0x1", "code": "0x6000", "storage": {{"{y}": "{x}"}}}}
+                }}}},
+                {{"txHash": "{y}", "error": "execution timeout"}}
+            ]"#
+        ))
+        .unwrap();
+        let asserter = Asserter::new();
+        asserter.push_success(&traces);
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter);
+
+        let prestate = provider.get_block_prestate(B256::ZERO).await.unwrap();
+        assert_eq!(prestate.accounts.len(), 2);
+        assert_eq!(prestate.accounts[&a], HashSet::from([x, y]));
+        assert!(prestate.accounts[&b].is_empty());
+        assert_eq!(prestate.codes, vec![bytes!("6000"), bytes!("6000")]);
+    }
 }
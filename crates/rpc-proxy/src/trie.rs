@@ -12,7 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::rpc::DebugApi;
+use crate::{
+    db::{RpcCalls, count_call},
+    rpc::DebugApi,
+};
 use alloy::{
     network::Network,
     primitives::{Address, B256, keccak256, map::B256Set},
@@ -29,12 +32,14 @@ pub(crate) async fn handle_removed_account<P, N>(
     block_hash: B256,
     address: Address,
     state_trie: &mut Trie,
+    calls: &mut RpcCalls,
 ) -> Result<()>
 where
     P: Provider<N>,
     N: Network,
 {
     trace!(%address, "Hydrating proof for destroyed account");
+    count_call(calls, "eth_getProof");
     let proof = provider
         .get_proof(address, vec![])
         .hash(block_hash)
@@ -51,12 +56,14 @@ pub(crate) async fn handle_new_account<P, N>(
     block_hash: B256,
     address: Address,
     state_trie: &mut Trie,
+    calls: &mut RpcCalls,
 ) -> Result<()>
 where
     P: Provider<N>,
     N: Network,
 {
     trace!(%address, "Hydrating proof for new account");
+    count_call(calls, "eth_getProof");
     let proof = provider
         .get_proof(address, vec![])
         .hash(block_hash)
@@ -73,6 +80,7 @@ pub(crate) async fn handle_modified_account<P, N>(
     address: Address,
     storage: &StorageWithOriginalValues,
     storage_trie: &mut Trie,
+    calls: &mut RpcCalls,
 ) -> Result<()>
 where
     P: Provider<N>,
@@ -95,6 +103,7 @@ where
     }
 
     trace!(%address, num_keys = keys.len(), "Hydrating proof for new or removed slots");
+    count_call(calls, "eth_getProof");
     let proof =
         provider.get_proof(address, keys).hash(block_hash).await.context("eth_getProof failed")?;
 
@@ -121,12 +130,14 @@ where
 
     let mut missing_storage_keys = B256Set::default();
     for prefix in unresolvable {
+        count_call(calls, "debug_storageRangeAt");
         let storage_key = provider.get_next_storage_key(block_hash, address, prefix).await?;
         missing_storage_keys.insert(storage_key);
     }
 
     if !missing_storage_keys.is_empty() {
         trace!(%address, keys=?missing_storage_keys, "Fetching final proofs for missing storage keys");
+        count_call(calls, "eth_getProof");
         let proof = provider
             .get_proof(address, missing_storage_keys.into_iter().collect())
             .hash(block_hash)
@@ -13,27 +13,31 @@
 // limitations under the License.
 
 use crate::{
-    db::{PreflightDb, ProviderConfig, ProviderDb},
+    db::{PreflightDb, ProviderConfig, ProviderDb, calls_since},
     trie::{handle_modified_account, handle_new_account, handle_removed_account},
 };
 use alloy::{
-    consensus::{BlockHeader, Transaction},
-    eips::BlockNumberOrTag,
+    consensus::{BlockHeader, Sealable, Transaction},
+    eips::BlockId,
     network::{BlockResponse, Network, primitives::HeaderResponse},
     primitives::Bytes,
     providers::Provider,
 };
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, ensure};
 use reth_evm::{ConfigureEvm, execute::Executor};
 use reth_primitives_traits::{Block, BlockBody, NodePrimitives};
 use reth_stateless::ExecutionWitness;
 use std::collections::HashSet;
-use tracing::{Span, debug};
+use tracing::{Span, debug, info};
 
+/// Generates the [ExecutionWitness] of a block using only standard RPC methods.
+///
+/// Once the block has been fetched, all further queries are pinned to its hash or the hashes of
+/// its ancestors, so that a reorg during the generation cannot mix data from different forks.
 pub async fn execution_witness<E, P, N>(
     evm_config: E,
     provider: &P,
-    block_id: BlockNumberOrTag,
+    block_id: BlockId,
 ) -> Result<ExecutionWitness>
 where
     E: ConfigureEvm + 'static,
@@ -48,15 +52,45 @@ where
 {
     debug!(%block_id, "Fetching block data");
     let rpc_block = provider
-        .get_block(block_id.into())
+        .get_block(block_id)
         .full()
         .await
         .context("eth_getBlock failed")?
         .with_context(|| format!("Block {block_id} not found"))?;
     let block_hash = rpc_block.header().hash();
-    let parent_hash = rpc_block.header().parent_hash();
+    if let BlockId::Hash(expected) = block_id {
+        ensure!(
+            block_hash == expected.block_hash,
+            "RPC returned block {block_hash} instead of {}",
+            expected.block_hash
+        );
+    }
 
     let block: <E::Primitives as NodePrimitives>::Block = rpc_block.try_into()?;
+    block_execution_witness(evm_config, provider, block).await
+}
+
+/// Generates the [ExecutionWitness] of the given block using only standard RPC methods.
+///
+/// The block is executed as given on top of the state of its parent, so it does not have to be
+/// fetched from the node. However, the proofs needed to update the state trie are queried at the
+/// hash of the block, so the node must know the block if it creates or removes accounts or
+/// storage slots.
+pub async fn block_execution_witness<E, P, N>(
+    evm_config: E,
+    provider: &P,
+    block: <E::Primitives as NodePrimitives>::Block,
+) -> Result<ExecutionWitness>
+where
+    E: ConfigureEvm + 'static,
+    P: Provider<N> + Clone + Send + Sync + 'static,
+    N: Network,
+    <E::Primitives as NodePrimitives>::BlockHeader: TryFrom<<N as Network>::HeaderResponse>,
+    <<E::Primitives as NodePrimitives>::BlockHeader as TryFrom<<N as Network>::HeaderResponse>>::Error:
+    std::error::Error + Send + Sync + 'static,
+{
+    let block_hash = block.header().hash_slow();
+    let parent_hash = block.header().parent_hash();
     let recovered_block = block.try_into_recovered()?;
 
     let mut db =
@@ -69,6 +103,13 @@ where
         }
     }
 
+    let access_list_calls = db.rpc_calls();
+
+    debug!(%block_hash, "Prefetching accessed accounts");
+    db.prefetch(block_hash).await?;
+    let prefetch_calls = calls_since(db.rpc_calls(), &access_list_calls);
+    let calls_before_execution = db.rpc_calls();
+
     debug!(%block_hash, "Executing block on dedicated thread");
     let current_span = Span::current();
 
@@ -85,28 +126,56 @@ where
     .await?;
     let execution_outcome = execution_result?;
     let mut db = db.unwrap();
+    // calls made on demand while executing, which should be rare if the prefetch succeeded
+    let execution_calls = calls_since(db.rpc_calls(), &calls_before_execution);
+    let calls_before_proofs = db.rpc_calls();
 
     debug!("Building pre-state proofs");
     let (mut state_trie, mut storage_tries) = db.state_proof().await?;
     let ancestors = db.ancestor_proof(parent_hash).await.context("failed to find ancestors")?;
+    // calls to build the pre- and post-state proofs, including the ancestors
+    let mut proof_calls = calls_since(db.rpc_calls(), &calls_before_proofs);
 
     debug!("Building post-state proofs");
     for (addr, account) in execution_outcome.state.state {
         match (account.original_info.is_some(), account.info.is_some()) {
             (false, true) => {
-                handle_new_account(provider, block_hash, addr, &mut state_trie).await?
+                handle_new_account(provider, block_hash, addr, &mut state_trie, &mut proof_calls)
+                    .await?
             }
             (true, false) => {
-                handle_removed_account(provider, block_hash, addr, &mut state_trie).await?
+                handle_removed_account(
+                    provider,
+                    block_hash,
+                    addr,
+                    &mut state_trie,
+                    &mut proof_calls,
+                )
+                .await?
             }
             (true, true) => {
                 let storage = storage_tries.get_mut(&addr).unwrap();
-                handle_modified_account(provider, block_hash, addr, &account.storage, storage)
-                    .await?;
+                handle_modified_account(
+                    provider,
+                    block_hash,
+                    addr,
+                    &account.storage,
+                    storage,
+                    &mut proof_calls,
+                )
+                .await?;
             }
             _ => {}
         }
     }
+    info!(
+        %block_hash,
+        ?access_list_calls,
+        ?prefetch_calls,
+        ?execution_calls,
+        ?proof_calls,
+        "Preflight RPC calls"
+    );
 
     // 5. Assemble the Execution Witness
     let mut state: HashSet<Bytes> = HashSet::new();
@@ -126,7 +195,7 @@ where
     Ok(ExecutionWitness {
         state: state.into_iter().collect(),
         codes: db.contracts().values().cloned().collect(),
-        keys: vec![],
+        keys: db.keys(),
         headers,
     })
 }
@@ -0,0 +1,448 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Websocket endpoint of the proxy.
+//!
+//! Every client connection is paired with its own connection to the upstream websocket, so that
+//! all requests and subscriptions, e.g. `eth_subscribe`, are passed through unchanged. Only
+//! the local methods of the proxy and the `executionWitness` subscription are served locally.
+//! Batches containing local methods are handled like HTTP batches, so their other entries are
+//! forwarded to the HTTP upstream and must not contain subscriptions.
+
+use crate::{
+    AppState, LOCAL_METHODS, batch_responses, cached_block_witness, empty_batch_response,
+    error_response, local_response,
+};
+use actix_web::{HttpRequest, HttpResponse, rt, web};
+use actix_ws::{AggregatedMessage, AggregatedMessageStream, Session};
+use alloy::providers::{Provider, ProviderBuilder, WsConnect};
+use anyhow::Context;
+use futures::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+use tokio::{
+    net::TcpStream,
+    sync::{broadcast, mpsc},
+    task::JoinHandle,
+};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream, connect_async, tungstenite};
+use tracing::{debug, error, info, warn};
+
+/// The name of the local subscription pushing the witness of each new head.
+const WITNESS_SUBSCRIPTION: &str = "executionWitness";
+
+/// Maximum size of a message sent by a client in multiple continuation frames.
+const MAX_CONTINUATION_SIZE: usize = 16 * 1024 * 1024;
+
+/// Initial and maximum delay before reconnecting the `newHeads` subscription of the witness feed.
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// Counter for the IDs of local subscriptions.
+static NEXT_SUBSCRIPTION_ID: AtomicU64 = AtomicU64::new(1);
+
+type Upstream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Upgrades the request to a websocket connection and proxies it to the upstream websocket.
+pub async fn ws_handler(
+    req: HttpRequest,
+    body: web::Payload,
+    data: web::Data<AppState>,
+) -> actix_web::Result<HttpResponse> {
+    let Some(ws_url) = &data.upstream_ws_url else {
+        return Ok(HttpResponse::BadRequest().body("websocket upstream not configured"));
+    };
+    let upstream = match connect_async(ws_url.as_str()).await {
+        Ok((upstream, _)) => upstream,
+        Err(e) => {
+            error!(error = %e, "Failed to connect to upstream websocket");
+            return Ok(HttpResponse::BadGateway().body(format!("Upstream provider error: {e}")));
+        }
+    };
+
+    let (response, session, stream) = actix_ws::handle(&req, body)?;
+    // messages fragmented into continuation frames are reassembled before they are handled
+    let stream = stream.aggregate_continuations().max_continuation_size(MAX_CONTINUATION_SIZE);
+    rt::spawn(run_session(session, stream, upstream, data));
+
+    Ok(response)
+}
+
+/// Passes messages between the client and the upstream until either side closes the connection.
+async fn run_session(
+    mut session: Session,
+    mut client: AggregatedMessageStream,
+    upstream: Upstream,
+    data: web::Data<AppState>,
+) {
+    debug!("Websocket session started");
+    let (mut upstream_tx, mut upstream_rx) = upstream.split();
+    // responses of local requests and notifications of local subscriptions
+    let (local_tx, mut local_rx) = mpsc::unbounded_channel::<String>();
+    let mut subscriptions: HashMap<String, JoinHandle<()>> = HashMap::new();
+
+    loop {
+        tokio::select! {
+            message = client.next() => {
+                let message = match message {
+                    Some(Ok(AggregatedMessage::Text(text))) => {
+                        if handle_locally(&text, &data, &local_tx, &mut subscriptions) {
+                            continue;
+                        }
+                        tungstenite::Message::text(text.to_string())
+                    }
+                    Some(Ok(AggregatedMessage::Binary(bytes))) => {
+                        tungstenite::Message::binary(bytes)
+                    }
+                    Some(Ok(AggregatedMessage::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                    Some(Ok(AggregatedMessage::Pong(_))) => continue,
+                    Some(Ok(AggregatedMessage::Close(_))) | Some(Err(_)) | None => break,
+                };
+                if let Err(e) = upstream_tx.send(message).await {
+                    error!(error = %e, "Failed to forward message to upstream");
+                    break;
+                }
+            }
+            message = upstream_rx.next() => match message {
+                Some(Ok(tungstenite::Message::Text(text))) => {
+                    if session.text(text.as_str().to_owned()).await.is_err() {
+                        break;
+                    }
+                }
+                Some(Ok(tungstenite::Message::Binary(bytes))) => {
+                    if session.binary(bytes).await.is_err() {
+                        break;
+                    }
+                }
+                Some(Ok(tungstenite::Message::Close(_))) | Some(Err(_)) | None => break,
+                // pings of the upstream are answered by tungstenite
+                Some(Ok(_)) => {}
+            },
+            Some(message) = local_rx.recv() => {
+                if session.text(message).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    for task in subscriptions.into_values() {
+        task.abort();
+    }
+    let _ = session.close(None).await;
+    debug!("Websocket session closed");
+}
+
+/// Handles the request locally if it is one of the [LOCAL_METHODS] or concerns a local
+/// subscription. Returns `false` if the request must be forwarded upstream.
+///
+/// Batches are only handled locally if they contain one of the [LOCAL_METHODS].
+fn handle_locally(
+    text: &str,
+    data: &web::Data<AppState>,
+    local_tx: &mpsc::UnboundedSender<String>,
+    subscriptions: &mut HashMap<String, JoinHandle<()>>,
+) -> bool {
+    let request = match serde_json::from_str::<Value>(text) {
+        Ok(Value::Array(entries)) => return handle_batch_locally(entries, data, local_tx),
+        Ok(request) => request,
+        Err(_) => return false,
+    };
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+    let first_param = params.get(0).and_then(Value::as_str);
+
+    let response = match request.get("method").and_then(Value::as_str) {
+        Some(method) if LOCAL_METHODS.contains(&method) => {
+            let (method, data, local_tx) = (method.to_string(), data.clone(), local_tx.clone());
+            rt::spawn(async move {
+                let (_, response) = local_response(&method, id, params, &data).await;
+                let _ = local_tx.send(response.to_string());
+            });
+            return true;
+        }
+        Some("eth_subscribe") if first_param == Some(WITNESS_SUBSCRIPTION) => {
+            match &data.witnesses {
+                Some(witnesses) => {
+                    let subscription =
+                        format!("0x{:x}", NEXT_SUBSCRIPTION_ID.fetch_add(1, Ordering::Relaxed));
+                    let task = forward_witnesses(
+                        subscription.clone(),
+                        witnesses.subscribe(),
+                        local_tx.clone(),
+                    );
+                    subscriptions.insert(subscription.clone(), task);
+                    json!({ "jsonrpc": "2.0", "id": id, "result": subscription })
+                }
+                None => error_response(id, -32601, "executionWitness subscription is not enabled"),
+            }
+        }
+        Some("eth_unsubscribe") => match first_param.and_then(|s| subscriptions.remove(s)) {
+            Some(task) => {
+                task.abort();
+                json!({ "jsonrpc": "2.0", "id": id, "result": true })
+            }
+            None => return false,
+        },
+        _ => return false,
+    };
+
+    let _ = local_tx.send(response.to_string());
+    true
+}
+
+/// Answers the batch like the HTTP endpoint, if it contains one of the [LOCAL_METHODS].
+fn handle_batch_locally(
+    entries: Vec<Value>,
+    data: &web::Data<AppState>,
+    local_tx: &mpsc::UnboundedSender<String>,
+) -> bool {
+    if entries.is_empty() {
+        let _ = local_tx.send(empty_batch_response().to_string());
+        return true;
+    }
+    let is_local = |entry: &Value| {
+        entry.get("method").and_then(Value::as_str).is_some_and(|m| LOCAL_METHODS.contains(&m))
+    };
+    if !entries.iter().any(is_local) {
+        return false;
+    }
+
+    let (data, local_tx) = (data.clone(), local_tx.clone());
+    rt::spawn(async move {
+        let responses = batch_responses(entries, &data).await;
+        // a batch of notifications is not answered at all
+        if !responses.is_empty() {
+            let _ = local_tx.send(Value::Array(responses).to_string());
+        }
+    });
+    true
+}
+
+/// Spawns a task sending a subscription notification for each new witness.
+fn forward_witnesses(
+    subscription: String,
+    mut witnesses: broadcast::Receiver<Arc<String>>,
+    local_tx: mpsc::UnboundedSender<String>,
+) -> JoinHandle<()> {
+    rt::spawn(async move {
+        loop {
+            match witnesses.recv().await {
+                Ok(result) => {
+                    // the result is already serialized, as it is shared by all subscribers
+                    let notification = format!(
+                        r#"{{"jsonrpc":"2.0","method":"eth_subscription","params":{{"subscription":"{subscription}","result":{result}}}}}"#
+                    );
+                    if local_tx.send(notification).is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!(%subscription, skipped, "Witness subscriber is lagging");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+/// Subscribes to the new heads of the upstream and generates the witness of each head, sending
+/// it to all subscribers of the `executionWitness` subscription.
+///
+/// The witnesses are generated through the cache, so that requests for the new head are
+/// coalesced with the subscription. If the connection fails or the subscription is closed, it is
+/// reestablished with an exponential backoff.
+pub async fn witness_feed(ws_url: String, data: web::Data<AppState>) -> anyhow::Result<()> {
+    let sender = data.witnesses.clone().context("witness subscription is not enabled")?;
+
+    let mut delay = MIN_RECONNECT_DELAY;
+    loop {
+        match follow_heads(&ws_url, &data, &sender).await {
+            Ok(heads) => {
+                warn!(heads, "newHeads subscription closed");
+                // only back off further if the subscription did not deliver any heads
+                if heads > 0 {
+                    delay = MIN_RECONNECT_DELAY;
+                }
+            }
+            Err(e) => error!(error = format!("{e:#}"), "newHeads subscription failed"),
+        }
+
+        info!(delay = ?delay, "Reconnecting newHeads subscription");
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+    }
+}
+
+/// Generates the witness of each new head until the subscription is closed and returns the
+/// number of received heads.
+async fn follow_heads(
+    ws_url: &str,
+    data: &AppState,
+    sender: &broadcast::Sender<Arc<String>>,
+) -> anyhow::Result<u64> {
+    let ws_provider = ProviderBuilder::new()
+        .connect_ws(WsConnect::new(ws_url))
+        .await
+        .context("failed to connect to upstream websocket")?;
+    let mut heads =
+        ws_provider.subscribe_blocks().await.context("eth_subscribe failed")?.into_stream();
+    info!("Generating witnesses for new heads");
+
+    let mut count = 0;
+    while let Some(header) = heads.next().await {
+        count += 1;
+        match cached_block_witness(data, header.hash).await {
+            Ok(witness) => {
+                let result = json!({
+                    "blockHash": header.hash,
+                    "blockNumber": header.number,
+                    "witness": witness.as_ref()
+                });
+                debug!(
+                    number = header.number,
+                    subscribers = sender.receiver_count(),
+                    "New witness"
+                );
+                // sending only fails if there are currently no subscribers
+                let _ = sender.send(Arc::new(result.to_string()));
+            }
+            Err(e) => error!(number = header.number, error = format!("{e:#}"), "Preflight failed"),
+        }
+    }
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::Client;
+    use reth_evm_ethereum::EthEvmConfig;
+    use std::num::NonZeroUsize;
+    use zeth_chainspec::MAINNET;
+    use zeth_rpc_proxy::WitnessCache;
+
+    const UPSTREAM_URL: &str = "http://localhost:8545";
+
+    fn app_state(witness_subscription: bool) -> web::Data<AppState> {
+        let provider = ProviderBuilder::new().connect_http(UPSTREAM_URL.parse().unwrap());
+        web::Data::new(AppState {
+            client: Client::new(),
+            upstream_url: UPSTREAM_URL.to_string(),
+            provider: provider.erased(),
+            evm_config: Arc::new(EthEvmConfig::new(MAINNET.clone())),
+            cache: WitnessCache::new(NonZeroUsize::new(1).unwrap(), None).unwrap(),
+            upstream_ws_url: None,
+            witnesses: witness_subscription.then(|| broadcast::channel(16).0),
+        })
+    }
+
+    async fn recv_json(local_rx: &mut mpsc::UnboundedReceiver<String>) -> Value {
+        serde_json::from_str(&local_rx.recv().await.unwrap()).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn forwarded_requests() {
+        let data = app_state(true);
+        let (local_tx, mut local_rx) = mpsc::unbounded_channel();
+        let mut subscriptions = HashMap::new();
+
+        for text in [
+            "not json",
+            r#"{"jsonrpc":"2.0","id":1,"method":"eth_blockNumber"}"#,
+            r#"{"jsonrpc":"2.0","id":1,"method":"eth_subscribe","params":["newHeads"]}"#,
+            r#"{"jsonrpc":"2.0","id":1,"method":"eth_unsubscribe","params":["0x1"]}"#,
+            r#"[{"jsonrpc":"2.0","id":1,"method":"eth_blockNumber"}]"#,
+        ] {
+            assert!(!handle_locally(text, &data, &local_tx, &mut subscriptions), "{text}");
+        }
+        assert!(local_rx.try_recv().is_err());
+    }
+
+    #[actix_web::test]
+    async fn local_requests() {
+        let data = app_state(false);
+        let (local_tx, mut local_rx) = mpsc::unbounded_channel();
+        let mut subscriptions = HashMap::new();
+
+        let request = r#"{"jsonrpc":"2.0","id":1,"method":"zeth_witnessCacheStatus"}"#;
+        assert!(handle_locally(request, &data, &local_tx, &mut subscriptions));
+        let response = recv_json(&mut local_rx).await;
+        assert_eq!(response["id"], 1);
+        assert!(response.get("result").is_some());
+
+        // notifications in a batch are not answered
+        let batch = r#"[
+            {"jsonrpc":"2.0","method":"zeth_witnessCacheStatus"},
+            {"jsonrpc":"2.0","id":2,"method":"zeth_witnessCacheStatus"}
+        ]"#;
+        assert!(handle_locally(batch, &data, &local_tx, &mut subscriptions));
+        let response = recv_json(&mut local_rx).await;
+        assert_eq!(response.as_array().unwrap().len(), 1);
+        assert_eq!(response[0]["id"], 2);
+
+        assert!(handle_locally("[]", &data, &local_tx, &mut subscriptions));
+        assert_eq!(recv_json(&mut local_rx).await["error"]["code"], -32600);
+
+        let request =
+            r#"{"jsonrpc":"2.0","id":3,"method":"eth_subscribe","params":["executionWitness"]}"#;
+        assert!(handle_locally(request, &data, &local_tx, &mut subscriptions));
+        assert_eq!(recv_json(&mut local_rx).await["error"]["code"], -32601);
+        assert!(subscriptions.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn witness_subscription() {
+        let data = app_state(true);
+        let (local_tx, mut local_rx) = mpsc::unbounded_channel();
+        let mut subscriptions = HashMap::new();
+
+        let request =
+            r#"{"jsonrpc":"2.0","id":1,"method":"eth_subscribe","params":["executionWitness"]}"#;
+        assert!(handle_locally(request, &data, &local_tx, &mut subscriptions));
+        let response = recv_json(&mut local_rx).await;
+        let subscription = response["result"].as_str().unwrap().to_string();
+        assert!(subscriptions.contains_key(&subscription));
+
+        let witnesses = data.witnesses.as_ref().unwrap();
+        witnesses.send(Arc::new(r#"{"blockNumber":1}"#.to_string())).unwrap();
+        let notification = recv_json(&mut local_rx).await;
+        assert_eq!(notification["method"], "eth_subscription");
+        assert_eq!(notification["params"]["subscription"], subscription);
+        assert_eq!(notification["params"]["result"]["blockNumber"], 1);
+
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "eth_unsubscribe",
+            "params": [subscription]
+        });
+        assert!(handle_locally(&request.to_string(), &data, &local_tx, &mut subscriptions));
+        assert_eq!(recv_json(&mut local_rx).await["result"], true);
+        assert!(subscriptions.is_empty());
+    }
+}
@@ -0,0 +1,21 @@
+#!/bin/bash
+# Usage: ./fetch-blockchain-tests.sh [dir]
+# Downloads the pinned release of the Ethereum Execution Spec Tests and extracts the blockchain
+# tests of Cancun and Prague into the given directory (default crates/core/testdata).
+set -euo pipefail
+
+# Pinned release of the fixtures, override to test against a different release
+EEST_VERSION="${EEST_VERSION:-v4.5.0}"
+DIR="${1:-crates/core/testdata}"
+
+TMP="$(mktemp -d)"
+trap 'rm -rf "$TMP"' EXIT
+
+curl -fsSL "https://github.com/ethereum/execution-spec-tests/releases/download/${EEST_VERSION}/fixtures_stable.tar.gz" \
+  | tar -xz -C "$TMP"
+
+rm -rf "$DIR/blockchain_tests"
+mkdir -p "$DIR/blockchain_tests"
+for fork in cancun prague; do
+  cp -r "$TMP/fixtures/blockchain_tests/$fork" "$DIR/blockchain_tests/"
+done
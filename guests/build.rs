@@ -30,10 +30,6 @@ fn main() {
 
     let mut guest_opts = GuestOptionsBuilder::default();
 
-    // pass the unsafe-pre-merge feature through to the guest
-    #[cfg(feature = "unsafe-pre-merge")]
-    guest_opts.features(vec!["unsafe-pre-merge".to_string()]);
-
     // Use Docker for deterministic builds if RISC0_USE_DOCKER is set.
     if env::var("RISC0_USE_DOCKER").is_ok() {
         let docker_tag = format!("r0.{RISC0_RUST_VERSION}");
@@ -49,7 +45,18 @@ fn main() {
         guest_opts.use_docker(docker_opts);
     }
 
+    // the OP guests are a separate package, so that the Ethereum guests are built without the
+    // `optimism` features; they do not support pre-merge blocks
+    let op_guest_options = guest_opts.build().expect("failed to build guest options");
+
+    // pass the unsafe-pre-merge feature through to the guest
+    #[cfg(feature = "unsafe-pre-merge")]
+    guest_opts.features(vec!["unsafe-pre-merge".to_string()]);
+
     let guest_options = guest_opts.build().expect("failed to build guest options");
 
-    risc0_build::embed_methods_with_options(HashMap::from([("stateless-client", guest_options)]));
+    risc0_build::embed_methods_with_options(HashMap::from([
+        ("stateless-client", guest_options),
+        ("op-stateless-client", op_guest_options),
+    ]));
 }
@@ -0,0 +1,53 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Guest entry point for OP-stack chains.
+//!
+//! This is a separate crate from the Ethereum guests, so that the `optimism` features of
+//! `zeth-core` and `zeth-chainspec` are never enabled for them.
+
+#![deny(unsafe_code)]
+
+use risc0_zkvm::guest::env;
+use serde::de::DeserializeOwned;
+use zeth_chainspec::optimism::OpChainSpec;
+use zeth_core::{
+    input::{InputReader, read_input},
+    optimism::{OpEvmConfig, validate_op_block_with_witness},
+};
+
+/// Reads the input directly from the standard input of the guest.
+struct Stdin;
+
+impl InputReader for Stdin {
+    fn read<T: DeserializeOwned>(&mut self) -> T {
+        env::read()
+    }
+}
+
+/// Validates the OP-stack block from the input and commits its hash.
+pub fn entry(evm_config: OpEvmConfig<OpChainSpec>) {
+    let chain_spec = evm_config.chain_spec();
+    env::log(&format!("EVM config: {chain_spec}"));
+
+    env::log("cycle-tracker-report-start: read_input");
+    let (block, witness) = read_input(&mut Stdin);
+    env::log("cycle-tracker-report-end: read_input");
+
+    env::log("cycle-tracker-report-start: validation");
+    let block_hash = validate_op_block_with_witness(block, witness, evm_config).unwrap();
+    env::log("cycle-tracker-report-end: validation");
+
+    env::commit_slice(block_hash.as_slice());
+}
@@ -0,0 +1,100 @@
+// Copyright 2025 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Example guest for a chain that extends mainnet with an additional precompile.
+//!
+//! The precompile at [KECCAK256_ADDRESS] returns the Keccak-256 hash of its input. Everything else,
+//! including the witness handling and the state root computation, is shared with the other guests.
+
+use alloy_evm::{
+    Database, Evm, EvmEnv, EvmFactory,
+    eth::{EthEvm, EthEvmContext, EthEvmFactory},
+    precompiles::{DynPrecompile, PrecompileInput, PrecompilesMap},
+};
+use revm::{
+    Inspector,
+    context::{
+        TxEnv,
+        result::{EVMError, HaltReason},
+    },
+    inspector::NoOpInspector,
+    precompile::{PrecompileError, PrecompileOutput, PrecompileResult},
+    primitives::{Address, address, hardfork::SpecId, keccak256},
+};
+use zeth_chainspec::MAINNET;
+use zeth_core::EthEvmConfig;
+
+/// Address of the additional Keccak-256 precompile.
+const KECCAK256_ADDRESS: Address = address!("0x0000000000000000000000000000000000000a00");
+
+/// Base gas cost of the precompile, same as the `KECCAK256` opcode.
+const KECCAK256_BASE: u64 = 30;
+/// Gas cost per 32-byte word of input, same as the `KECCAK256` opcode.
+const KECCAK256_PER_WORD: u64 = 6;
+
+fn keccak256_precompile(input: PrecompileInput<'_>) -> PrecompileResult {
+    let words = input.data.len().div_ceil(32) as u64;
+    let gas_used = KECCAK256_BASE + KECCAK256_PER_WORD * words;
+    if gas_used > input.gas {
+        return Err(PrecompileError::OutOfGas);
+    }
+
+    Ok(PrecompileOutput::new(gas_used, keccak256(input.data).into()))
+}
+
+/// EVM factory that adds the Keccak-256 precompile to the default Ethereum EVM.
+#[derive(Debug, Clone, Copy, Default)]
+struct CustomEvmFactory;
+
+impl CustomEvmFactory {
+    fn add_precompiles<DB: Database, I>(evm: &mut EthEvm<DB, I, PrecompilesMap>)
+    where
+        I: Inspector<EthEvmContext<DB>>,
+    {
+        evm.precompiles_mut().apply_precompile(&KECCAK256_ADDRESS, |_| {
+            Some(DynPrecompile::from(keccak256_precompile))
+        });
+    }
+}
+
+impl EvmFactory for CustomEvmFactory {
+    type Evm<DB: Database, I: Inspector<EthEvmContext<DB>>> = EthEvm<DB, I, PrecompilesMap>;
+    type Context<DB: Database> = EthEvmContext<DB>;
+    type Tx = TxEnv;
+    type Error<DBError: core::error::Error + Send + Sync + 'static> = EVMError<DBError>;
+    type HaltReason = HaltReason;
+    type Spec = SpecId;
+    type Precompiles = PrecompilesMap;
+
+    fn create_evm<DB: Database>(&self, db: DB, input: EvmEnv) -> Self::Evm<DB, NoOpInspector> {
+        let mut evm = EthEvmFactory::default().create_evm(db, input);
+        Self::add_precompiles(&mut evm);
+        evm
+    }
+
+    fn create_evm_with_inspector<DB: Database, I: Inspector<Self::Context<DB>>>(
+        &self,
+        db: DB,
+        input: EvmEnv,
+        inspector: I,
+    ) -> Self::Evm<DB, I> {
+        let mut evm = EthEvmFactory::default().create_evm_with_inspector(db, input, inspector);
+        Self::add_precompiles(&mut evm);
+        evm
+    }
+}
+
+pub fn main() {
+    stateless_client::entry(EthEvmConfig::new_with_evm_factory(MAINNET.clone(), CustomEvmFactory));
+}
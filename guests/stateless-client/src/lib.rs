@@ -14,20 +14,169 @@
 
 #![deny(unsafe_code)]
 
-use risc0_zkvm::guest::env;
+use revm::primitives::B256;
+use risc0_zkvm::{Digest, guest::env};
+use serde::de::DeserializeOwned;
+use std::fmt::Debug;
 use zeth_chainspec::ChainSpec;
-use zeth_core::{EthEvmConfig, StatelessInput, validate_block};
+use zeth_core::{
+    ConfigureEvm, EthEvmConfig, EthPrimitives, IndexedWitness,
+    call::{CallInput, execute_call},
+    chunk::{AggregationInput, AggregationJournal, ChunkInput, aggregate_chunks, execute_chunk},
+    header_chain::{HeaderChainInput, validate_header_chain},
+    input::{InputReader, read_input},
+    proposal::{BlockProposal, ProposalJournal, execute_proposal},
+    storage_proof::{StorageProofInput, verify_storage_proofs},
+    validate_block_with_witness,
+};
 
-pub fn entry(evm_config: EthEvmConfig<ChainSpec>) {
+/// Reads the input directly from the standard input of the guest.
+struct Stdin;
+
+impl InputReader for Stdin {
+    fn read<T: DeserializeOwned>(&mut self) -> T {
+        env::read()
+    }
+}
+
+/// Validates the block from the input and commits its hash.
+///
+/// The EVM factory `F` allows chains with custom precompiles to reuse the guest logic.
+pub fn entry<F>(evm_config: EthEvmConfig<ChainSpec, F>)
+where
+    EthEvmConfig<ChainSpec, F>: ConfigureEvm<Primitives = EthPrimitives>,
+{
     let chain_spec = evm_config.chain_spec();
     env::log(&format!("EVM config: {chain_spec}"));
 
+    run(|block, witness| validate_block_with_witness(block, witness, evm_config));
+}
+
+/// Executes the call from the input against the state of its block and commits the journal.
+///
+/// The chain is selected by the chain ID of the input, which is part of the journal.
+pub fn call_entry() {
+    env::log("cycle-tracker-report-start: read_input");
+    let input: CallInput = env::read();
+    env::log("cycle-tracker-report-end: read_input");
+
+    let chain_spec = zeth_chainspec::by_chain_id(input.chain_id).expect("unsupported chain");
+    env::log(&format!("EVM config: {chain_spec}"));
+
+    env::log("cycle-tracker-report-start: execution");
+    let journal = execute_call(input, EthEvmConfig::new(chain_spec)).unwrap();
+    env::log("cycle-tracker-report-end: execution");
+
+    env::commit_slice(&journal.encode());
+}
+
+/// Executes the proposed block from the input and commits the resulting header.
+///
+/// The chain ID is read before the proposal and selects the chain, it is part of the journal.
+pub fn proposal_entry() {
+    let chain_id: u64 = env::read();
+    let chain_spec = zeth_chainspec::by_chain_id(chain_id).expect("unsupported chain");
+    env::log(&format!("EVM config: {chain_spec}"));
+
+    env::log("cycle-tracker-report-start: read_input");
+    let (proposal, witness): (BlockProposal, _) = read_input(&mut Stdin);
+    env::log("cycle-tracker-report-end: read_input");
+
+    env::log("cycle-tracker-report-start: execution");
+    let header = execute_proposal(proposal, witness, EthEvmConfig::new(chain_spec)).unwrap();
+    env::log("cycle-tracker-report-end: execution");
+
+    env::commit_slice(&ProposalJournal { chain_id, header }.encode());
+}
+
+/// Executes the chunk of transactions from the input and commits its journal.
+///
+/// The chain ID is read before the chunk and selects the chain, it is part of the journal.
+pub fn chunk_entry() {
+    let chain_id: u64 = env::read();
+    let chain_spec = zeth_chainspec::by_chain_id(chain_id).expect("unsupported chain");
+    env::log(&format!("EVM config: {chain_spec}"));
+
+    env::log("cycle-tracker-report-start: read_input");
+    let (chunk, witness): (ChunkInput, _) = read_input(&mut Stdin);
+    env::log("cycle-tracker-report-end: read_input");
+
+    env::log("cycle-tracker-report-start: execution");
+    let execution = execute_chunk(chunk, witness, EthEvmConfig::new(chain_spec)).unwrap();
+    env::log("cycle-tracker-report-end: execution");
+
+    env::commit(&execution.journal);
+}
+
+/// Verifies the proofs of all chunks of a block, validates the block against their journals and
+/// commits its hash together with the image ID of the chunk guest.
+///
+/// The chunk image ID is taken from the input, so verifiers must check the committed one, see
+/// [AggregationJournal::verify].
+pub fn chunk_aggregation_entry() {
+    let chain_id: u64 = env::read();
+    let chain_spec = zeth_chainspec::by_chain_id(chain_id).expect("unsupported chain");
+    let chunk_image_id: Digest = env::read();
+    let input: AggregationInput = env::read();
+
+    for journal in &input.chunks {
+        let journal = risc0_zkvm::serde::to_vec(journal).unwrap();
+        env::verify(chunk_image_id, &journal).unwrap();
+    }
+    let block_hash = aggregate_chunks(input, chain_spec).unwrap();
+
+    let journal = AggregationJournal {
+        chain_id,
+        chunk_image_id: B256::from_slice(chunk_image_id.as_bytes()),
+        block_hash,
+    };
+    env::commit_slice(&journal.encode());
+}
+
+/// Verifies the chain of headers from the input and commits its first and last hash.
+///
+/// The chain is selected by the chain ID of the input, which is part of the journal.
+pub fn header_chain_entry() {
+    env::log("cycle-tracker-report-start: read_input");
+    let input: HeaderChainInput = env::read();
+    env::log("cycle-tracker-report-end: read_input");
+
+    let chain_spec = zeth_chainspec::by_chain_id(input.chain_id).expect("unsupported chain");
+    env::log(&format!("EVM config: {chain_spec}"));
+
+    env::log("cycle-tracker-report-start: validation");
+    let journal = validate_header_chain(input, chain_spec).unwrap();
+    env::log("cycle-tracker-report-end: validation");
+
+    env::commit_slice(&journal.encode());
+}
+
+/// Verifies the account and storage proofs from the input against the state root of its header
+/// and commits the block hash together with the proven values.
+pub fn storage_proof_entry() {
+    env::log("cycle-tracker-report-start: read_input");
+    let input: StorageProofInput = env::read();
+    env::log("cycle-tracker-report-end: read_input");
+
+    env::log("cycle-tracker-report-start: validation");
+    let journal = verify_storage_proofs(input).unwrap();
+    env::log("cycle-tracker-report-end: validation");
+
+    env::commit_slice(&journal.encode());
+}
+
+/// Reads the block and its witness, validates it and commits the resulting block hash.
+fn run<B, E>(validate: impl FnOnce(B, IndexedWitness) -> Result<B256, E>)
+where
+    B: DeserializeOwned,
+    E: Debug,
+{
     env::log("cycle-tracker-report-start: read_input");
-    let input: StatelessInput = env::read();
+    let (block, witness) = read_input(&mut Stdin);
     env::log("cycle-tracker-report-end: read_input");
 
     env::log("cycle-tracker-report-start: validation");
-    let block_hash = validate_block(input.block, input.witness, evm_config).unwrap();
+    let block_hash = validate(block, witness).unwrap();
     env::log("cycle-tracker-report-end: validation");
 
     env::commit_slice(block_hash.as_slice());